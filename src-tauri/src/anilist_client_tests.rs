@@ -0,0 +1,292 @@
+use std::{collections::HashMap, fs, future::Future, path::Path, sync::Arc};
+
+use crate::{anilist_response_tests::{expect, ResponseTest}, anime_data::{AnimeInfo, NextAiringEpisode}, api_calls::{self, TokenData, Website}, data_archive::{DataArchive, ImportConflict, ImportSummary}, events::NoEvents, file_operations, graphql_client::{FakeGraphQlClient, GraphQlClient}, list_tracker, user_data::{UserData, UserInfo, UserSettings}};
+
+
+
+// runs the anilist flows against a fake client that answers with the recorded responses in anilist_fixtures
+// flows that go through UserData save to a temporary folder so the user's files are not touched
+pub async fn anilist_client_tests() -> Vec<ResponseTest> {
+
+    let mut test_results: Vec<ResponseTest> = Vec::new();
+    for (name, result) in [
+        ("list sync", list_sync().await),
+        ("sync since last update", sync_since_last_update().await),
+        ("browse", browse().await),
+        ("airing update", airing_update().await),
+        ("save entry", save_entry().await),
+        ("save rejected entry", save_rejected_entry().await),
+        ("no connection", no_connection().await),
+        ("queued update replay", in_test_storage(queued_update_replay()).await),
+        ("pull updates conflict", in_test_storage(pull_updates_conflict()).await),
+        ("remove deleted entries", in_test_storage(remove_deleted_entries()).await),
+    ] {
+        test_results.push(ResponseTest {
+            fixture: String::from(name),
+            passed: result.is_ok(),
+            error: result.err().unwrap_or_default(),
+        });
+    }
+
+    test_results
+}
+
+// a client that answers with the fixtures in order
+fn fake_client(fixtures: &[&str]) -> Result<Arc<FakeGraphQlClient>, String> {
+    let mut responses: Vec<String> = Vec::new();
+    for fixture in fixtures {
+        let path = Path::new("anilist_fixtures").join(format!("{}.json", fixture));
+        responses.push(fs::read_to_string(&path).map_err(|error| format!("{} {}", fixture, error))?);
+    }
+    Ok(Arc::new(FakeGraphQlClient::new(responses)))
+}
+
+// the flow's saves go to a temporary folder and database
+async fn in_test_storage<F: Future<Output = Result<(), String>>>(flow: F) -> Result<(), String> {
+    match file_operations::with_test_storage(flow).await {
+        Ok(result) => result,
+        Err(error) => Err(format!("test storage {}", error)),
+    }
+}
+
+// a user logged into anilist with one anime they're watching and two they completed
+async fn logged_in_user(client: Arc<dyn GraphQlClient>) -> UserData {
+
+    let mut archive = DataArchive::default();
+    let mut settings = UserSettings::new();
+    settings.username = String::from("user");
+    archive.settings = Some(settings);
+    archive.token = Some(anilist_token());
+    for (id, media_id, status, progress) in [(123456789, 21, "CURRENT", 1100), (123456791, 1535, "COMPLETED", 37), (123456795, 5114, "COMPLETED", 64)] {
+        let mut entry = UserInfo::new();
+        entry.id = id;
+        entry.media_id = media_id;
+        entry.status = String::from(status);
+        entry.progress = progress;
+        entry.updated_at = 1750000000;
+        archive.user_data.insert(media_id, entry);
+    }
+    archive.user_lists.insert(String::from("CURRENT"), vec![21]);
+    archive.user_lists.insert(String::from("COMPLETED"), vec![1535, 5114]);
+
+    let mut user_data = UserData::new(Arc::new(NoEvents));
+    user_data.import_archive(&archive, ImportConflict::UseArchive, &mut ImportSummary::default()).await;
+    user_data.set_anilist_client(client);
+    user_data
+}
+
+// change a entry while anilist can't be reached so the change is queued
+async fn queue_change(user_data: &mut UserData, entry: UserInfo) -> Result<(), String> {
+    let offline = fake_client(&[])?;
+    user_data.set_anilist_client(offline.clone());
+    user_data.set_user_data(entry, true).await?;
+    expect("offline requests", &offline.requests().len(), &1)?;
+    Ok(())
+}
+
+fn anilist_token() -> TokenData {
+    let mut token = TokenData::new();
+    token.website = Website::Anilist;
+    token.access_token = String::from("token");
+    token
+}
+
+
+
+async fn list_sync() -> Result<(), String> {
+
+    let client = fake_client(&["user_list_with_media"])?;
+    let tracker = list_tracker::get_tracker(&anilist_token(), &Vec::new(), client.clone());
+
+    let mut user_data: HashMap<i32, UserInfo> = HashMap::new();
+    let mut user_lists: HashMap<String, Vec<i32>> = HashMap::new();
+    if let Some(error) = tracker.get_list(&String::from("user"), &String::from("CURRENT"), &mut user_data, &mut user_lists).await {
+        return Err(error);
+    }
+
+    expect("entries", &user_data.len(), &2)?;
+    // the hidden entry is only in its custom lists
+    expect("watching", &user_lists.get("CURRENT").cloned(), &Some(vec![21]))?;
+
+    let requests = client.requests();
+    expect("requests", &requests.len(), &1)?;
+    expect("statuses", &requests[0].json["variables"]["status"], &serde_json::json!(["CURRENT", "REPEATING"]))?;
+    expect("access token", &requests[0].access_token, &Some(String::from("token")))?;
+    Ok(())
+}
+
+async fn sync_since_last_update() -> Result<(), String> {
+
+    let client = fake_client(&["user_media_updated"])?;
+    let tracker = list_tracker::get_tracker(&anilist_token(), &Vec::new(), client.clone());
+
+    // the first two entries were changed after this time, the second is a manga
+    let updates = tracker.get_updates_since(&String::from("user"), 1755000000).await?;
+    expect("updates", &updates.len(), &1)?;
+    expect("progress", &updates[0].progress, &1101)?;
+    let requests = client.requests();
+    expect("requests", &requests.len(), &1)?;
+    expect("anime only", &requests[0].json["query"].as_str().unwrap_or_default().contains("type: ANIME"), &true)?;
+    Ok(())
+}
+
+async fn browse() -> Result<(), String> {
+
+    let client = fake_client(&["anime_browse"])?;
+    let (anime, has_next_page) = api_calls::anilist_browse_call(&*client, 1, String::from("2013"), String::from("SPRING"), String::new(), String::new(), String::new(), String::new()).await?;
+
+    expect("anime", &anime.len(), &2)?;
+    expect("has next page", &has_next_page, &true)?;
+    let variables = &client.requests()[0].json["variables"];
+    expect("year", &variables["seasonYear"], &serde_json::json!("2013"))?;
+    expect("season", &variables["season"], &serde_json::json!("SPRING"))?;
+    expect("genres", &variables["genres"].is_null(), &true)?;
+    Ok(())
+}
+
+async fn airing_update() -> Result<(), String> {
+
+    let client = fake_client(&["airing_info"])?;
+    let mut anime_data: HashMap<i32, AnimeInfo> = HashMap::new();
+    for id in [21, 1535] {
+        let mut anime = AnimeInfo::default();
+        anime.id = id;
+        anime_data.insert(id, anime);
+    }
+    anime_data.get_mut(&1535).unwrap().next_airing_episode = Some(NextAiringEpisode { airing_at: 1, episode: 37 });
+
+    api_calls::anilist_airing_time(&*client, vec![21, 1535], &mut anime_data).await?;
+
+    expect("next episode", &anime_data[&21].next_airing_episode.as_ref().map(|episode| episode.episode), &Some(1150))?;
+    expect("finished airing", &anime_data[&1535].next_airing_episode.is_none(), &true)?;
+    Ok(())
+}
+
+async fn save_entry() -> Result<(), String> {
+
+    let client = fake_client(&["save_media_list_entry"])?;
+    let tracker = list_tracker::get_tracker(&anilist_token(), &Vec::new(), client.clone());
+
+    let mut anime = UserInfo::new();
+    anime.media_id = 16498;
+    anime.status = String::from("PLANNING");
+    // new entries get their id from anilist
+    expect("id", &tracker.save_entry(&anime).await, &Ok(123456800))?;
+
+    let request = &client.requests()[0].json;
+    let query = request["query"].as_str().unwrap_or_default();
+    // fields without a value are left out so anilist keeps what it has
+    expect("notes sent", &query.contains("$notes"), &false)?;
+    expect("start date sent", &query.contains("$start_year"), &false)?;
+    expect("advanced scores sent", &query.contains("$advanced_scores"), &false)?;
+    expect("media id", &request["variables"]["media_id"], &serde_json::json!(16498))?;
+    expect("entry id", &request["variables"]["id"].is_null(), &true)?;
+    Ok(())
+}
+
+async fn save_rejected_entry() -> Result<(), String> {
+
+    let client = fake_client(&["save_media_list_entry_rejected"])?;
+    let tracker = list_tracker::get_tracker(&anilist_token(), &Vec::new(), client.clone());
+
+    let mut anime = UserInfo::new();
+    anime.id = 123456800;
+    anime.media_id = 16498;
+    anime.status = String::from("COMPLETED");
+    anime.score = 11.0;
+    expect("result", &tracker.save_entry(&anime).await, &Err(list_tracker::ENTRY_REJECTED))?;
+    Ok(())
+}
+
+async fn no_connection() -> Result<(), String> {
+
+    let client: Arc<dyn GraphQlClient> = fake_client(&[])?;
+    expect("result", &api_calls::anilist_get_mal_id(&*client, 21).await, &Err("no connection"))?;
+    Ok(())
+}
+
+async fn queued_update_replay() -> Result<(), String> {
+
+    let mut user_data = logged_in_user(fake_client(&[])?).await;
+    let mut anime = UserInfo::new();
+    anime.media_id = 16498;
+    anime.status = String::from("PLANNING");
+    queue_change(&mut user_data, anime).await?;
+
+    // the anime isn't on the list on anilist yet so nothing newer can be overwritten
+    let client = fake_client(&["media_list_entry_not_on_list", "save_media_list_entry"])?;
+    user_data.set_anilist_client(client.clone());
+    expect("sent", &user_data.check_queued_updates().await, &Ok(1))?;
+    expect("entry id", &user_data.get_user_data(16498).map(|entry| entry.id), &Ok(123456800))?;
+
+    let requests = client.requests();
+    expect("requests", &requests.len(), &2)?;
+    expect("checked media id", &requests[0].json["variables"]["id"], &serde_json::json!(16498))?;
+    expect("saved media id", &requests[1].json["variables"]["media_id"], &serde_json::json!(16498))?;
+
+    // the queue is empty so nothing is sent again
+    expect("sent again", &user_data.check_queued_updates().await, &Ok(0))?;
+    expect("requests after", &client.requests().len(), &2)?;
+    Ok(())
+}
+
+async fn pull_updates_conflict() -> Result<(), String> {
+
+    let mut user_data = logged_in_user(fake_client(&[])?).await;
+    let mut anime = user_data.get_user_data(21)?;
+    anime.progress = 1105;
+    queue_change(&mut user_data, anime).await?;
+
+    // anilist has a different progress for the queued anime and a rewatch of a completed one
+    let client = fake_client(&["user_list_options", "user_media_updated"])?;
+    user_data.set_anilist_client(client.clone());
+    expect("changed", &user_data.pull_updates().await, &Ok(true))?;
+
+    let conflicts = user_data.get_conflicts();
+    expect("conflicts", &conflicts.len(), &1)?;
+    expect("conflict media id", &conflicts[0].media_id, &21)?;
+    expect("local progress", &conflicts[0].local.progress, &1105)?;
+    expect("remote progress", &conflicts[0].remote.progress, &1101)?;
+    // the local entry is kept until the user chooses
+    expect("progress", &user_data.get_user_data(21).map(|entry| entry.progress), &Ok(1105))?;
+    expect("repeat", &user_data.get_user_data(1535).map(|entry| entry.repeat), &Ok(1))?;
+    expect("manga", &user_data.get_user_data(30013).is_err(), &true)?;
+    expect("custom lists", &user_data.get_user_settings().custom_lists.map(|lists| lists.len()), &Some(2))?;
+    expect("requests", &client.requests().len(), &2)?;
+
+    // the conflicting change was taken out of the queue so it isn't sent over the website's change
+    expect("sent", &user_data.check_queued_updates().await, &Ok(0))?;
+    expect("requests after", &client.requests().len(), &2)?;
+    Ok(())
+}
+
+async fn remove_deleted_entries() -> Result<(), String> {
+
+    let mut user_data = logged_in_user(fake_client(&[])?).await;
+    let mut anime = UserInfo::new();
+    anime.media_id = 16498;
+    anime.status = String::from("PLANNING");
+    queue_change(&mut user_data, anime).await?;
+
+    // hidden entries are only on a custom list, they're in none of the status lists
+    for media_id in [30, 6547] {
+        let mut hidden = UserInfo::new();
+        hidden.media_id = media_id;
+        hidden.status = String::from("COMPLETED");
+        hidden.hidden_from_status_lists = true;
+        hidden.custom_lists = vec![String::from("Favourites")];
+        user_data.set_user_data(hidden, false).await?;
+    }
+
+    // 5114 and the hidden 6547 were deleted on anilist, 16498 hasn't been sent yet
+    let client = fake_client(&["user_list_ids"])?;
+    user_data.set_anilist_client(client.clone());
+    expect("removed", &user_data.remove_deleted_entries().await, &Ok(vec![5114, 6547]))?;
+    expect("deleted entry", &user_data.get_user_data(5114).is_err(), &true)?;
+    expect("deleted hidden entry", &user_data.get_user_data(6547).is_err(), &true)?;
+    expect("hidden entry", &user_data.get_user_data(30).is_ok(), &true)?;
+    expect("queued entry", &user_data.get_user_data(16498).is_ok(), &true)?;
+    expect("completed", &user_data.get_list(&String::from("COMPLETED")).await, &Ok(vec![1535]))?;
+    expect("requests", &client.requests().len(), &1)?;
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use std::{fs, path::Path};
+use serde::{Serialize, Deserialize};
+
+use crate::{api_calls::{self, Website}, anilist_responses::{self, AiringInfo, ListCollectionResponse, ListEntryId, MediaListEntry, MediaResponse, MediaWithEntry, PageResponse, SaveEntryResponse, UserResponse}, anime_data::AnimeInfo, http_client::ApiError, user_data::UserInfo};
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ResponseTest {
+    pub fixture: String,
+    pub passed: bool,
+    pub error: String,
+}
+
+
+
+// loads every fixture in anilist_fixtures, they're responses recorded from anilist for the queries in api_calls
+// a fixture passes if it's read into the query's response type and the values that matter come out unchanged
+pub async fn anilist_response_tests() -> Vec<ResponseTest> {
+
+    let folder = Path::new("anilist_fixtures");
+
+    let mut fixtures: Vec<String> = match fs::read_dir(folder) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".json"))
+            .collect(),
+        Err(_error) => return Vec::new(),
+    };
+    fixtures.sort();
+
+    let mut test_results: Vec<ResponseTest> = Vec::new();
+    for fixture in fixtures {
+
+        let result = match fs::read_to_string(folder.join(&fixture)) {
+            Ok(contents) => check_fixture(fixture.trim_end_matches(".json"), &contents),
+            Err(error) => Err(error.to_string()),
+        };
+
+        test_results.push(ResponseTest {
+            passed: result.is_ok(),
+            error: result.err().unwrap_or_default(),
+            fixture,
+        });
+    }
+
+    test_results
+}
+
+// read the fixture as the response of the query it was recorded from
+fn check_fixture(fixture: &str, contents: &str) -> Result<(), String> {
+    match fixture {
+        "media_info" => {
+            let response: MediaResponse<AnimeInfo> = anilist_responses::parse(contents)?;
+            let anime = response.media.ok_or("media is missing")?;
+            expect("media type", &anime.media_type, &String::from("ANIME"))?;
+            expect("title", &anime.title.user_preferred, &Some(String::from("ONE PIECE")))?;
+            expect("airing at", &anime.next_airing_episode.map(|episode| episode.airing_at), &Some(1792807200))?;
+            expect("relation type", &anime.relations.edges[0].relation_type, &String::from("SOURCE"))?;
+            // field names inside text used to be replaced
+            let description = anime.description.unwrap_or_default();
+            if description.contains("new type of legend") == false || description.contains("mediaId and coverImage") == false {
+                return Err(format!("description was changed: {}", description));
+            }
+            Ok(())
+        },
+        "media_info_not_found" => expect_error::<MediaResponse<AnimeInfo>>(contents, ApiError::NotFound),
+        "invalid_token" => expect_error::<MediaResponse<AnimeInfo>>(contents, ApiError::Unauthorized),
+        "rate_limited" => {
+            match anilist_responses::parse::<MediaResponse<AnimeInfo>>(contents) {
+                Err(ApiError::RateLimited(_)) => Ok(()),
+                result => Err(format!("expected rate limited, got {:?}", result.map(|_| ()))),
+            }
+        },
+        "anime_browse" => {
+            let response: PageResponse<AnimeInfo> = anilist_responses::parse(contents)?;
+            expect("has next page", &response.page.page_info.has_next_page, &true)?;
+            expect("anime", &response.page.media.len(), &2)?;
+            expect("season year", &response.page.media[0].season_year, &Some(2013))?;
+            expect("description", &response.page.media[1].description, &None)?;
+            Ok(())
+        },
+        "user_list_with_media" => {
+            let response: ListCollectionResponse<MediaListEntry> = anilist_responses::parse(contents)?;
+            let entries: Vec<UserInfo> = response.collection.lists.into_iter().flat_map(|list| list.entries).map(UserInfo::from).collect();
+            expect("entries", &entries.len(), &2)?;
+            expect("media id", &entries[0].media_id, &21)?;
+            expect("notes", &entries[0].notes, &Some(String::from("the type of show where the mediaId in notes must not change")))?;
+            expect("custom lists", &entries[0].custom_lists, &vec![String::from("Favourites")])?;
+            expect("advanced scores", &entries[0].advanced_scores.is_empty(), &true)?;
+            expect("repeat", &entries[1].repeat, &2)?;
+            expect("hidden", &entries[1].hidden_from_status_lists, &true)?;
+            expect("story score", &entries[1].advanced_scores.get("Story"), &Some(&8.0))?;
+            Ok(())
+        },
+        "user_media_updated" => {
+            let response: PageResponse<MediaListEntry> = anilist_responses::parse(contents)?;
+            expect("manga", &response.page.media_list[1].media.as_ref().and_then(|media| media.media_type.clone()), &Some(String::from("MANGA")))?;
+            let entries: Vec<UserInfo> = response.page.media_list.into_iter().map(UserInfo::from).collect();
+            expect("has next page", &response.page.page_info.has_next_page, &false)?;
+            expect("entries", &entries.len(), &3)?;
+            expect("updated at", &entries[0].updated_at, &1760000500)?;
+            expect("custom lists", &entries[2].custom_lists.is_empty(), &true)?;
+            expect("notes", &entries[2].notes, &Some(String::from("type: rewatch")))?;
+            Ok(())
+        },
+        "airing_info" => {
+            let response: PageResponse<AiringInfo> = anilist_responses::parse(contents)?;
+            expect("anime", &response.page.media.len(), &2)?;
+            expect("episode", &response.page.media[0].next_airing_episode.as_ref().map(|episode| episode.episode), &Some(1150))?;
+            expect("not airing", &response.page.media[1].next_airing_episode.is_none(), &true)?;
+            Ok(())
+        },
+        "save_media_list_entry" => {
+            let response: SaveEntryResponse = anilist_responses::parse(contents)?;
+            expect("id", &response.entry.id, &123456800)?;
+            expect("media id", &response.entry.media_id, &16498)?;
+            Ok(())
+        },
+        "save_media_list_entry_rejected" => expect_error::<SaveEntryResponse>(contents, ApiError::Rejected),
+        "media_list_entry_not_on_list" => {
+            let response: MediaResponse<MediaWithEntry> = anilist_responses::parse(contents)?;
+            let media = response.media.ok_or("media is missing")?;
+            expect("entry", &media.media_list_entry.is_none(), &true)?;
+            Ok(())
+        },
+        "user_list_options" => {
+            let response: UserResponse = anilist_responses::parse(contents)?;
+            let anime_list = response.user.media_list_options.and_then(|options| options.anime_list).ok_or("anime list options are missing")?;
+            expect("custom lists", &anime_list.custom_lists.map(|lists| lists.len()), &Some(2))?;
+            expect("advanced scoring enabled", &anime_list.advanced_scoring_enabled, &Some(false))?;
+            Ok(())
+        },
+        "user_list_ids" => {
+            let response: ListCollectionResponse<ListEntryId> = anilist_responses::parse(contents)?;
+            expect("lists", &response.collection.lists.len(), &3)?;
+            expect("status", &response.collection.lists[0].entries[1].status, &String::from("REPEATING"))?;
+            Ok(())
+        },
+        "oauth_token" => {
+            let token = api_calls::anilist_token_from_response(&contents.to_string())?;
+            expect("website", &matches!(token.website, Website::Anilist), &true)?;
+            expect("expires in", &token.expires_in, &31536000)?;
+            expect("refresh token", &token.refresh_token, &String::from("def50200fixture"))?;
+            expect("issued at", &(token.issued_at > 0), &true)?;
+            Ok(())
+        },
+        "oauth_token_rejected" => match api_calls::anilist_token_from_response(&contents.to_string()) {
+            Err(error) => expect("error", &error, &ApiError::Rejected),
+            Ok(_) => Err(String::from("a refused code was read as a token")),
+        },
+        _ => Err(String::from("no check for this fixture")),
+    }
+}
+
+pub fn expect<T: PartialEq + std::fmt::Debug>(name: &str, value: &T, expected: &T) -> Result<(), String> {
+    if value != expected {
+        return Err(format!("{} is {:?} instead of {:?}", name, value, expected));
+    }
+    Ok(())
+}
+
+fn expect_error<T: serde::de::DeserializeOwned>(contents: &str, expected: ApiError) -> Result<(), String> {
+    match anilist_responses::parse::<T>(contents) {
+        Err(error) if error == expected => Ok(()),
+        Err(error) => Err(format!("expected {}, got {}", expected, error)),
+        Ok(_) => Err(format!("expected {}, the response was read", expected)),
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::{anime_data::NextAiringEpisode, constants, http_client::ApiError, user_data::{Date, UserInfo}};
+
+
+
+// every anilist response has data, errors, or both
+#[derive(Deserialize, Debug, Default)]
+pub struct GraphQlResponse {
+    #[serde(default)]
+    pub data: Value,
+    #[serde(default)]
+    pub errors: Vec<GraphQlError>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct GraphQlError {
+    pub message: String,
+    pub status: Option<u16>,
+}
+
+impl GraphQlError {
+    // the kind of error anilist is describing
+    pub fn to_api_error(&self) -> ApiError {
+        match self.status {
+            _ if self.message == "Invalid token" => ApiError::Unauthorized,
+            Some(401) => ApiError::Unauthorized,
+            Some(404) => ApiError::NotFound,
+            Some(429) => ApiError::RateLimited(constants::RATE_LIMIT_DEFAULT_WAIT),
+            Some(status) if status >= 500 => ApiError::Server(status),
+            _ => ApiError::Rejected,
+        }
+    }
+}
+
+impl GraphQlResponse {
+
+    pub fn from_str(response: &str) -> Result<GraphQlResponse, ApiError> {
+        match serde_json::from_str::<GraphQlResponse>(response) {
+            Ok(response) => Ok(response),
+            Err(error) => {
+                println!("anilist response could not be read: {}", error);
+                Err(ApiError::InvalidResponse)
+            },
+        }
+    }
+
+    // read the data as the query's response type, a error in the response is returned instead if there is one
+    pub fn into_data<T: DeserializeOwned>(self) -> Result<T, ApiError> {
+
+        if let Some(error) = self.errors.first() {
+            for entry in &self.errors {
+                println!("anilist error {:?} {}", entry.status, entry.message);
+            }
+            return Err(error.to_api_error());
+        }
+
+        match serde_json::from_value::<T>(self.data) {
+            Ok(data) => Ok(data),
+            Err(error) => {
+                println!("anilist response did not match the query: {}", error);
+                Err(ApiError::InvalidResponse)
+            },
+        }
+    }
+}
+
+// read a anilist response into the type returned by the query
+pub fn parse<T: DeserializeOwned>(response: &str) -> Result<T, ApiError> {
+    GraphQlResponse::from_str(response)?.into_data()
+}
+
+
+
+// the structs below are the wrappers anilist puts around the data, one for each root field used in a query
+
+// Media(id: $id) { ... }
+#[derive(Deserialize, Debug)]
+pub struct MediaResponse<T> {
+    #[serde(rename = "Media")]
+    pub media: Option<T>,
+}
+
+// Page(page: $page) { pageInfo media mediaList }
+#[derive(Deserialize, Debug)]
+pub struct PageResponse<T> {
+    #[serde(rename = "Page")]
+    pub page: Page<T>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    #[serde(default)]
+    pub page_info: PageInfo,
+    #[serde(default = "Vec::new")]
+    pub media: Vec<T>,
+    #[serde(default = "Vec::new")]
+    pub media_list: Vec<T>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PageInfo {
+    #[serde(default)]
+    pub has_next_page: bool,
+}
+
+// MediaListCollection(userName: $userName) { lists { entries } }
+#[derive(Deserialize, Debug)]
+pub struct ListCollectionResponse<T> {
+    #[serde(rename = "MediaListCollection")]
+    pub collection: ListCollection<T>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ListCollection<T> {
+    #[serde(default = "Vec::new")]
+    pub lists: Vec<MediaList<T>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MediaList<T> {
+    #[serde(default = "Vec::new")]
+    pub entries: Vec<T>,
+}
+
+// User(name: $username) { ... }
+#[derive(Deserialize, Debug)]
+pub struct UserResponse {
+    #[serde(rename = "User")]
+    pub user: AnilistUser,
+}
+
+// SaveMediaListEntry(...) { ... }
+#[derive(Deserialize, Debug)]
+pub struct SaveEntryResponse {
+    #[serde(rename = "SaveMediaListEntry")]
+    pub entry: MediaListEntry,
+}
+
+// DeleteMediaListEntry(id: $id) { deleted }
+#[derive(Deserialize, Debug)]
+pub struct DeleteEntryResponse {
+    #[serde(rename = "DeleteMediaListEntry")]
+    pub entry: DeletedEntry,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DeletedEntry {
+    pub deleted: bool,
+}
+
+
+
+// the user's entry for a anime, the same fields are used by every query that reads the list
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaListEntry {
+    pub id: i32,
+    pub media_id: i32,
+    pub status: String,
+    pub score: f32,
+    pub progress: i32,
+    #[serde(default)]
+    pub repeat: Option<i32>,
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+    #[serde(default)]
+    pub started_at: Option<Date>,
+    #[serde(default)]
+    pub completed_at: Option<Date>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub private: Option<bool>,
+    #[serde(default)]
+    pub hidden_from_status_lists: Option<bool>,
+    #[serde(default)]
+    pub custom_lists: Option<Vec<CustomList>>,
+    #[serde(default)]
+    pub advanced_scores: Option<HashMap<String, f32>>, // null when advanced scoring is turned off
+    #[serde(default)]
+    pub media: Option<MediaKind>, // only the type is read, it's only asked for by queries that could return manga
+}
+
+// tells anime apart from manga
+#[derive(Deserialize, Debug, Clone)]
+pub struct MediaKind {
+    #[serde(rename = "type", default)]
+    pub media_type: Option<String>,
+}
+
+// customLists(asArray: true) returns every custom list with a flag for the ones the entry is on
+#[derive(Deserialize, Debug, Clone)]
+pub struct CustomList {
+    pub name: String,
+    pub enabled: bool,
+}
+
+impl From<MediaListEntry> for UserInfo {
+    fn from(entry: MediaListEntry) -> Self {
+        UserInfo {
+            id: entry.id,
+            media_id: entry.media_id,
+            status: entry.status,
+            score: entry.score,
+            progress: entry.progress,
+            started_at: entry.started_at,
+            completed_at: entry.completed_at,
+            notes: entry.notes,
+            updated_at: entry.updated_at.unwrap_or_default(),
+            repeat: entry.repeat.unwrap_or_default(),
+            private: entry.private.unwrap_or_default(),
+            hidden_from_status_lists: entry.hidden_from_status_lists.unwrap_or_default(),
+            custom_lists: entry.custom_lists.unwrap_or_default().into_iter().filter(|list| list.enabled).map(|list| list.name).collect(),
+            advanced_scores: entry.advanced_scores.unwrap_or_default(),
+        }
+    }
+}
+
+// Media { mediaListEntry { ... } }, null if the anime isn't on the user's list
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaWithEntry {
+    pub media_list_entry: Option<MediaListEntry>,
+}
+
+// a list entry when only the list it's on is needed
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ListEntryId {
+    pub media_id: i32,
+    pub status: String,
+}
+
+// ids of a anime on both websites
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaIds {
+    #[serde(default)]
+    pub id: i32,
+    pub id_mal: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AiringInfo {
+    pub id: i32,
+    pub next_airing_episode: Option<NextAiringEpisode>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AnilistUser {
+    #[serde(default)]
+    pub id: i32,
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+    #[serde(default)]
+    pub media_list_options: Option<MediaListOptions>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaListOptions {
+    #[serde(default)]
+    pub score_format: Option<String>,
+    #[serde(default)]
+    pub anime_list: Option<AnimeListOptions>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimeListOptions {
+    #[serde(default)]
+    pub custom_lists: Option<Vec<String>>,
+    #[serde(default)]
+    pub advanced_scoring: Option<Vec<String>>,
+    #[serde(default)]
+    pub advanced_scoring_enabled: Option<bool>,
+}
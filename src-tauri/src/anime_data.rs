@@ -1,17 +1,21 @@
 use std::{cmp::Ordering, collections::{HashMap, HashSet}, path::Path, time::{SystemTime, UNIX_EPOCH, Instant}, io::ErrorKind};
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 use regex::Regex;
 use serde::{Serialize, Deserialize};
 use walkdir::WalkDir;
 
-use crate::{api_calls, user_data::UserInfo, constants, GLOBAL_REFRESH_UI, file_operations};
+use crate::{api_calls, graphql_client::{self, GraphQlClient}, user_data::UserInfo, constants, events::{EventSink, ScanData, UiEvent}, file_operations, data_archive::{self, DataArchive, ImportConflict, ImportSummary}};
 
 
 
+// saved with rust names, anilist's camel case names are aliases so api responses can be read into the same structs
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct AnimeInfo {
+    #[serde(alias = "averageScore")]
     pub average_score: Option<i32>,
+    #[serde(alias = "coverImage")]
     pub cover_image: CoverImage,
     pub description: Option<String>,
     pub duration: Option<i32>,
@@ -19,20 +23,26 @@ pub struct AnimeInfo {
     pub format: Option<String>,
     pub genres: Vec<String>,
     pub id: i32,
+    #[serde(alias = "idMal")]
     pub id_mal: Option<i32>,
+    #[serde(alias = "isAdult")]
     pub is_adult: bool,
     pub popularity: i32,
     pub season: Option<String>,
+    #[serde(alias = "seasonYear")]
     pub season_year: Option<i32>,
+    #[serde(alias = "startDate")]
     pub start_date: AnilistDate,
     pub title: Title,
     pub trailer: Option<TrailerData>,
+    #[serde(alias = "type")]
     pub media_type: String, // type is a rust keyword
     pub relations: Relations,
     pub recommendations: Option<Recommendations>,
     pub tags: Vec<Tag>,
     pub trending: i32,
     pub studios: Studio,
+    #[serde(alias = "nextAiringEpisode")]
     pub next_airing_episode: Option<NextAiringEpisode>,
     pub synonyms: Vec<String>,
 }
@@ -102,6 +112,7 @@ pub struct Title {
     pub english: Option<String>,
     pub native: Option<String>,
     pub romaji: Option<String>,
+    #[serde(alias = "userPreferred")]
     pub user_preferred: Option<String>,
     pub custom: Option<String>
 }
@@ -120,6 +131,7 @@ pub struct Relations {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Edge {
+    #[serde(alias = "relationType")]
     pub relation_type: String,
     pub node: Node,
 }
@@ -128,7 +140,9 @@ pub struct Edge {
 pub struct Node {
     pub id: i32,
     pub title: Title,
+    #[serde(alias = "coverImage")]
     pub cover_image: CoverImage,
+    #[serde(alias = "type")]
     pub media_type: String,
 }
 
@@ -140,6 +154,7 @@ pub struct Recommendations {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RecNode {
     pub rating: i32,
+    #[serde(alias = "mediaRecommendation")]
     pub media_recommendation: Option<MediaRecommendation>,
 }
 
@@ -147,14 +162,18 @@ pub struct RecNode {
 pub struct  MediaRecommendation {
     pub id: i32,
     pub title: Title,
+    #[serde(alias = "coverImage")]
     pub cover_image: CoverImage,
+    #[serde(alias = "type")]
     pub media_type: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Tag {
     pub name: String,
+    #[serde(alias = "isGeneralSpoiler")]
     pub is_general_spoiler: bool,
+    #[serde(alias = "isMediaSpoiler")]
     pub is_media_spoiler: bool,
     pub description: String,
 }
@@ -167,12 +186,14 @@ pub struct Studio {
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct NodeName {
     pub name: String,
+    #[serde(alias = "isAnimationStudio")]
     pub is_animation_studio: bool
 }
 
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct NextAiringEpisode {
+    #[serde(alias = "airingAt")]
     pub airing_at: i32,
     pub episode: i32,
 }
@@ -262,12 +283,14 @@ pub struct AnimeData {
     pub anime_path: HashMap<i32, HashMap<i32,AnimePath>>,
     pub known_files: HashSet<u64>,
     pub new_anime: bool,
+    pub anilist: Arc<dyn GraphQlClient>, // where anime info is requested from
+    pub events: Arc<dyn EventSink>, // told about scan progress and problems reaching anilist or saving files
 }
 
 impl AnimeData {
 
-    pub fn new() -> AnimeData {
-        AnimeData { data: HashMap::new(), nonexistent_ids: HashSet::new(), needs_scan: Vec::new(), anime_path: HashMap::new(), known_files: HashSet::new(), new_anime: false }
+    pub fn new(events: Arc<dyn EventSink>) -> AnimeData {
+        AnimeData { data: HashMap::new(), nonexistent_ids: HashSet::new(), needs_scan: Vec::new(), anime_path: HashMap::new(), known_files: HashSet::new(), new_anime: false, anilist: graphql_client::default_client(), events }
     }
 
     pub fn clear(&mut self) {
@@ -281,10 +304,10 @@ impl AnimeData {
 
     pub async fn read_files(&mut self) {
         
-        file_operations::read_file_anime_info_cache(&mut self.data).await;
-        file_operations::read_file_anime_missing_ids(&mut self.nonexistent_ids).await;
-        file_operations::read_file_episode_path(&mut self.anime_path).await;
-        file_operations::read_file_known_files(&mut self.known_files).await;
+        file_operations::read_file_anime_info_cache(&*self.events, &mut self.data).await.ok();
+        file_operations::read_file_anime_missing_ids(&*self.events, &mut self.nonexistent_ids).await.ok();
+        file_operations::read_file_episode_path(&*self.events, &mut self.anime_path).await.ok();
+        file_operations::read_file_known_files(&*self.events, &mut self.known_files).await.ok();
     }
 
     pub async fn get_anime_data(&mut self, media_id: i32) -> Result<AnimeInfo, &'static str> {
@@ -299,12 +322,12 @@ impl AnimeData {
             return Ok(anime.clone());
         } else {
             println!("getting anime data from anilist");
-            match api_calls::anilist_get_anime_info_single2(media_id).await {
+            match api_calls::anilist_get_anime_info_single2(&*self.anilist, media_id).await {
                 Ok(result) => {
                     println!("{:?}", result);
                     self.data.insert(result.id, result.clone());
                     self.new_anime = true;
-                    file_operations::write_file_anime_info_cache(&self.data).await;
+                    file_operations::write_anime_info(&*self.events, &vec![result.clone()]).await.ok();
                     return Ok(result);
                 },
                 Err(error) => { 
@@ -323,9 +346,10 @@ impl AnimeData {
         // check for missing ids
         let missing_ids: Vec<i32> = valid_ids.iter().map(|id| *id).filter(|id| self.data.contains_key(id) == false).collect();
         if missing_ids.is_empty() == false {
-            match api_calls::anilist_api_call_multiple(missing_ids.clone()).await {
+            match api_calls::anilist_api_call_multiple(&*self.anilist, missing_ids.clone()).await {
                 Ok(result) => {
                     let missing_from_anilist_ids = self.find_missing_ids(&missing_ids, &result).await;
+                    file_operations::write_anime_info(&*self.events, &result).await.ok();
                     for anime in result {
                         self.data.insert(anime.id, anime);
                         self.new_anime = true;
@@ -333,7 +357,6 @@ impl AnimeData {
                     for id in missing_from_anilist_ids {
                         valid_ids.remove(valid_ids.iter().position(|v_id| *v_id == id).unwrap());
                     }
-                    file_operations::write_file_anime_info_cache(&self.data).await;
                 },
                 Err(error) => return Err(error),
             }
@@ -370,7 +393,7 @@ impl AnimeData {
 
     pub async fn update_anime_data(&mut self, media_id: i32) -> Result<AnimeInfo, &'static str> {
 
-        match api_calls::anilist_api_call(media_id).await {
+        match api_calls::anilist_api_call(&*self.anilist, media_id).await {
             Ok(result) => {
                 self.data.insert(media_id, result.clone());
                 return Ok(result);
@@ -399,7 +422,7 @@ impl AnimeData {
                 missing_ids.push(*id);
             }
         }
-        file_operations::write_file_anime_missing_ids(&self.nonexistent_ids).await;
+        file_operations::write_file_anime_missing_ids(&*self.events, &self.nonexistent_ids).await.ok();
         missing_ids
     }
 
@@ -775,7 +798,7 @@ impl AnimeData {
             Err("Anime does not exist")
         } else {
             self.data.entry(media_id).and_modify(|anime| anime.title.custom = Some(filename));
-            file_operations::write_file_anime_info_cache(&self.data).await;
+            file_operations::write_anime_info(&*self.events, &vec![self.data.get(&media_id).unwrap().clone()]).await.ok();
             Ok(())
         }
     }
@@ -787,6 +810,58 @@ impl AnimeData {
         }
     }
 
+    // copy episode locations, custom titles, and anime missing from anilist into a archive
+    // episode paths are stored relative to the folder they're in
+    pub fn export_archive(&self, archive: &mut DataArchive, folders: &Vec<String>) {
+
+        archive.episode_paths = self.anime_path.iter()
+            .map(|(media_id, episodes)| (*media_id, episodes.iter().map(|(episode, path)| (*episode, data_archive::to_archive_path(path, folders))).collect()))
+            .collect();
+        archive.custom_titles = self.data.iter()
+            .filter_map(|(media_id, anime)| anime.title.custom.clone().map(|title| (*media_id, title)))
+            .collect();
+        archive.missing_ids = self.nonexistent_ids.clone();
+    }
+
+    // add episode locations, custom titles, and anime missing from anilist from a archive
+    // custom titles can only be set for anime that are in the cache
+    pub async fn import_archive(&mut self, archive: &DataArchive, folders: &Vec<String>, conflict: ImportConflict, summary: &mut ImportSummary) {
+
+        self.nonexistent_ids.extend(archive.missing_ids.iter());
+        file_operations::write_file_anime_missing_ids(&*self.events, &self.nonexistent_ids).await.ok();
+
+        for (media_id, episodes) in &archive.episode_paths {
+            let local_episodes = self.anime_path.entry(*media_id).or_default();
+            for (episode, archive_path) in episodes {
+                if conflict == ImportConflict::KeepLocal && local_episodes.contains_key(episode) {
+                    continue;
+                }
+                // the file was in a folder that isn't set on this computer
+                if let Some(path) = data_archive::from_archive_path(archive_path, folders) {
+                    local_episodes.insert(*episode, path);
+                    summary.episode_paths += 1;
+                }
+            }
+        }
+        self.anime_path.retain(|_, episodes| episodes.is_empty() == false);
+        file_operations::write_file_episode_path(&*self.events, &self.anime_path).await.ok();
+
+        let mut changed: Vec<AnimeInfo> = Vec::new();
+        for (media_id, title) in &archive.custom_titles {
+            if let Some(anime) = self.data.get_mut(media_id) {
+                if conflict == ImportConflict::KeepLocal && anime.title.custom.is_some() {
+                    continue;
+                }
+                anime.title.custom = Some(title.clone());
+                changed.push(anime.clone());
+            }
+        }
+        summary.custom_titles = changed.len();
+        if changed.is_empty() == false {
+            file_operations::write_anime_info(&*self.events, &changed).await.ok();
+        }
+    }
+
     pub async fn scan_new_ids(&mut self, folders: Vec<String>) {
 
         for id in self.needs_scan.clone() {
@@ -809,26 +884,27 @@ impl AnimeData {
         let mut count = 0;
         let known_files = self.known_files.clone();
         self.known_files.clear(); // clear this so missing files are removed
-        GLOBAL_REFRESH_UI.lock().await.scan_data.total_folders = folders.len() as i32;
+        let mut progress = ScanData::default();
+        progress.total_folders = folders.len() as i32;
         for folder in folders {
             count += 1;
-            let mut refresh_ui = GLOBAL_REFRESH_UI.lock().await;
-            refresh_ui.scan_data.current_folder = count;
-            refresh_ui.scan_data.completed_chunks = 0;
-            drop(refresh_ui);
-            if self.scan_folder(folder, skip_files, media_id, &known_files).await {
+            progress.current_folder = count;
+            progress.completed_chunks = 0;
+            self.events.send(UiEvent::ScanProgress(progress.clone())).await;
+            if self.scan_folder(folder, skip_files, media_id, &known_files, &mut progress).await {
                 file_found = true;
             }
         }
-        file_operations::write_file_episode_path(&self.anime_path).await;
-        file_operations::write_file_known_files(&self.known_files).await;
-        let mut refresh_ui = GLOBAL_REFRESH_UI.lock().await;
-        refresh_ui.scan_data.total_folders = 0;
-        refresh_ui.scan_data.current_folder = 0;
+        file_operations::write_file_episode_path(&*self.events, &self.anime_path).await.ok();
+        file_operations::write_file_known_files(&*self.events, &self.known_files).await.ok();
+        progress.total_folders = 0;
+        progress.current_folder = 0;
+        self.events.send(UiEvent::ScanProgress(progress)).await;
         file_found
     }
 
-    pub async fn scan_folder(&mut self, folder: String, skip_files: bool, media_id: Option<i32>, known_files: &HashSet<u64>) -> bool {
+    // progress is updated as files are checked and sent to events every 100 files
+    pub async fn scan_folder(&mut self, folder: String, skip_files: bool, media_id: Option<i32>, known_files: &HashSet<u64>, progress: &mut ScanData) -> bool {
 
         let mut file_found = false;
         let path = Path::new(&folder);
@@ -839,7 +915,8 @@ impl AnimeData {
         let mut count = 0;
         
         let iter = WalkDir::new(path).into_iter();
-        GLOBAL_REFRESH_UI.lock().await.scan_data.total_chunks = iter.count() as i32;
+        progress.total_chunks = iter.count() as i32;
+        self.events.send(UiEvent::ScanProgress(progress.clone())).await;
 
         let valid_extensions = ["mkv", "mp4", "avi"];
         for entry in WalkDir::new(path).into_iter().filter_map(Result::ok) {
@@ -851,7 +928,8 @@ impl AnimeData {
             count += 1;
             if count % 100 == 0 {
                 //println!("{}", count);
-                GLOBAL_REFRESH_UI.lock().await.scan_data.completed_chunks = count;
+                progress.completed_chunks = count;
+                self.events.send(UiEvent::ScanProgress(progress.clone())).await;
             }
 
             if let Some(ext) = entry.path().extension() {
@@ -928,7 +1006,7 @@ impl AnimeData {
         while get_info.is_empty() == false {
             println!("get_info size {}", get_info.len());
             println!("{:?}", get_info);
-            match api_calls::anilist_api_call_multiple(get_info.clone()).await {
+            match api_calls::anilist_api_call_multiple(&*self.anilist, get_info.clone()).await {
                 Ok(_result) => {
                     
                     let anime_ids = get_info.clone();
@@ -950,7 +1028,7 @@ impl AnimeData {
                 },
                 Err(error) => {
                     if error == "no connection" {
-                        GLOBAL_REFRESH_UI.lock().await.no_internet = true;
+                        self.events.send(UiEvent::Connectivity(false)).await;
                         break;
                     } else {
                         println!("error getting prequel data: {}", error);
@@ -1220,6 +1298,39 @@ impl AnimeData {
         episodes
     }
 
+    // get the next episode's airing time for the anime that are airing, returns how many were checked
+    pub async fn refresh_airing_times(&mut self, media_ids: &HashSet<i32>) -> Result<usize, &'static str> {
+
+        let airing_ids: Vec<i32> = media_ids.iter()
+            .filter(|id| self.data.get(id).map_or(false, |anime| anime.next_airing_episode.is_some()))
+            .cloned()
+            .collect();
+        for ids in airing_ids.chunks(constants::ANIME_PER_PAGE) {
+            api_calls::anilist_airing_time(&*self.anilist, ids.to_vec(), &mut self.data).await?;
+        }
+
+        let airing: Vec<AnimeInfo> = airing_ids.iter().filter_map(|id| self.data.get(id).cloned()).collect();
+        file_operations::write_anime_info(&*self.events, &airing).await.ok();
+        Ok(airing_ids.len())
+    }
+
+    // download the info of these anime again so changes on anilist like episode counts are seen, returns how many were downloaded
+    pub async fn refresh_anime_info(&mut self, media_ids: &HashSet<i32>) -> Result<usize, &'static str> {
+
+        let ids: Vec<i32> = media_ids.iter().filter(|id| self.nonexistent_ids.contains(id) == false).cloned().collect();
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let result = api_calls::anilist_api_call_multiple(&*self.anilist, ids).await?;
+        file_operations::write_anime_info(&*self.events, &result).await.ok();
+        let downloaded = result.len();
+        for anime in result {
+            self.data.insert(anime.id, anime);
+        }
+        Ok(downloaded)
+    }
+
     // show will be searched for the next time folders are scanned. 
     // this scan will not skip files that were previously checked, 
     // instead it will skip comparing those files against multiple anime
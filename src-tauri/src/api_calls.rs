@@ -1,15 +1,16 @@
 use std::{cmp::{Ordering, max}, collections::{HashMap, HashSet}};
 
 
-use reqwest::Client;
 use serde::{Serialize, Deserialize};
 use serde_json::{json, Value};
 
 
-use crate::{secrets, file_operations, GLOBAL_REFRESH_UI, user_data::UserInfo, anime_data::{self, AnimeInfo, NextAiringEpisode}};
+use crate::{secrets, constants, http_client, graphql_client::GraphQlClient, user_data::UserInfo, anime_data::{self, AnimeInfo}};
+use crate::anilist_responses::{self, GraphQlResponse, MediaResponse, PageResponse, ListCollectionResponse, UserResponse, SaveEntryResponse, DeleteEntryResponse, MediaListEntry, MediaWithEntry, ListEntryId, MediaIds, AiringInfo};
 
 
 // the structs below replicate the structure of data being returned by anilist api calls
+// anilist's camel case names are aliases, the wrappers around each response are in anilist_responses
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TrailerData {
     pub id: String,
@@ -21,6 +22,7 @@ pub struct Title {
     pub english: Option<String>,
     pub native: Option<String>,
     pub romaji: Option<String>,
+    #[serde(alias = "userPreferred")]
     pub user_preferred: Option<String>,
     pub custom: Option<String>
 }
@@ -89,12 +91,15 @@ impl AnilistDate {
 pub struct Node {
     pub id: i32,
     pub title: Title,
+    #[serde(alias = "coverImage")]
     pub cover_image: CoverImage,
+    #[serde(alias = "type")]
     pub media_type: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Edge {
+    #[serde(alias = "relationType")]
     pub relation_type: String,
     pub node: Node,
 }
@@ -108,13 +113,16 @@ pub struct Relations {
 pub struct  MediaRecommendation {
     pub id: i32,
     pub title: Title,
+    #[serde(alias = "coverImage")]
     pub cover_image: CoverImage,
+    #[serde(alias = "type")]
     pub media_type: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RecNode {
     pub rating: i32,
+    #[serde(alias = "mediaRecommendation")]
     pub media_recommendation: Option<MediaRecommendation>,
 }
 
@@ -126,7 +134,9 @@ pub struct Recommendations {
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Tag {
     pub name: String,
+    #[serde(alias = "isGeneralSpoiler")]
     pub is_general_spoiler: bool,
+    #[serde(alias = "isMediaSpoiler")]
     pub is_media_spoiler: bool,
     pub description: String,
 }
@@ -134,17 +144,22 @@ pub struct Tag {
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct MangaInfo {
+    #[serde(alias = "averageScore")]
     pub average_score: Option<i32>,
+    #[serde(alias = "coverImage")]
     pub cover_image: CoverImage,
     pub description: Option<String>,
     pub format: Option<String>,
     pub genres: Vec<String>,
     pub id: i32,
+    #[serde(alias = "isAdult")]
     pub is_adult: bool,
     pub popularity: i32,
+    #[serde(alias = "startDate")]
     pub start_date: AnilistDate,
     pub title: Title,
     pub trailer: Option<TrailerData>,
+    #[serde(alias = "type")]
     pub media_type: String, // type is a rust keyword
     pub relations: Relations,
     pub recommendations: Option<Recommendations>,
@@ -164,6 +179,7 @@ pub struct Staff {
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct StaffNodes {
     pub name: Name,
+    #[serde(alias = "primaryOccupations")]
     pub primary_occupations: Vec<String>,
 }
 
@@ -180,6 +196,7 @@ pub struct Studio {
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct NodeName {
     pub name: String,
+    #[serde(alias = "isAnimationStudio")]
     pub is_animation_studio: bool
 }
 
@@ -193,7 +210,7 @@ pub struct Data {
     pub data: Media
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Website {
     None,
     Anilist,
@@ -219,18 +236,20 @@ impl Website {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TokenData {
     pub website: Website,
     pub token_type: String,
     pub expires_in: i32,
     pub access_token: String,
-    pub refresh_token: String
+    pub refresh_token: String,
+    #[serde(default)]
+    pub issued_at: u64, // unix time the token was received, 0 if unknown
 }
 
 impl TokenData {
     pub const fn new() -> TokenData {
-        TokenData { website: Website::None, token_type: String::new(), expires_in: 0, access_token: String::new(), refresh_token: String::new() }
+        TokenData { website: Website::None, token_type: String::new(), expires_in: 0, access_token: String::new(), refresh_token: String::new(), issued_at: 0 }
     }
 
     pub fn clear(&mut self) {
@@ -239,27 +258,28 @@ impl TokenData {
         self.expires_in = 0;
         self.access_token.clear();
         self.refresh_token.clear();
+        self.issued_at = 0;
+    }
+
+    // returns true if the token has expired or will expire within the next hour
+    // tokens without a known issue time are assumed to be valid until the website rejects them
+    pub fn is_expired(&self) -> bool {
+        if self.issued_at == 0 || self.expires_in <= 0 {
+            return false;
+        }
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        now + constants::ONE_HOUR >= self.issued_at + self.expires_in as u64
     }
 }
 
 
+// how the user set up their list on the website
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
-pub struct UserAnimeInfo {
-    pub id: i32,
-    pub media_id: i32,
-    pub status: String,
-    pub score: f32,
-    pub progress: i32,
-    pub started_at: Option<AnilistDate>,
-    pub completed_at: Option<AnilistDate>,
-    pub notes: Option<String>,
+pub struct ListOptions {
+    pub custom_lists: Vec<String>,
+    pub advanced_scoring: Vec<String>, // empty if advanced scoring is turned off
 }
 
-impl UserAnimeInfo {
-    pub const fn new() -> UserAnimeInfo {
-        UserAnimeInfo { id: 0, media_id: 0, status: String::new(), score: 0.0, progress: 0, started_at: None, completed_at: None, notes: None }
-    }
-}
 
 // request json for anilist api
 const ANIME_INFO_QUERY: &str = "
@@ -269,16 +289,6 @@ query ($id: Int) {
     }
 }";
 
-// get every list from a user with all user data for each anime
-const ANIME_LIST_QUERY: &str = "
-query ($username: String) {
-    MediaListCollection (userName: $username, type: ANIME) {
-        lists {
-            name entries { id idMal mediaId status score progress startedAt { year month day } completedAt { year month day } } status
-        }
-    }
-}";
-
 // query for absolutely all data of a specific anime
 /*
 const ANIME_ALL_INFO_QUERY: &str = "
@@ -318,7 +328,8 @@ query($page: Int $type: MediaType $format: [MediaFormat] $season: MediaSeason $s
 }";
 
 // retrieve a list of anime based on criteria like the year/season it was released, format, or genre
-pub async fn anilist_browse_call(page: usize, year: String, season: String, genre: String, format: String, search: String, order: String) -> Result<serde_json::Value, &'static str> {
+// returns the anime on the page and if there is another page
+pub async fn anilist_browse_call(client: &dyn GraphQlClient, page: usize, year: String, season: String, genre: String, format: String, search: String, order: String) -> Result<(Vec<AnimeInfo>, bool), &'static str> {
 
     let mut variables = json!({"page": page, "type": "ANIME"});
     if year.is_empty() == false {
@@ -342,13 +353,8 @@ pub async fn anilist_browse_call(page: usize, year: String, season: String, genr
 
     let json = json!({"query": ANIME_BROWSE, "variables": variables});
     
-    match post(&json, None).await {
-        Ok(result) => {
-            let response = anilist_to_snake_case(result);
-            return Ok(serde_json::from_str(&response).unwrap());
-        },
-        Err(error) => return Err(error),
-    }
+    let response: PageResponse<AnimeInfo> = anilist_responses::parse(&client.post(&json, None).await?)?;
+    Ok((response.page.media, response.page.page_info.has_next_page))
 }
 
 
@@ -361,26 +367,21 @@ mutation ($id: Int) {
 }";
 
 // remove a anime from the users anime list
-pub async fn anilist_remove_entry(id: i32, access_token: String) -> Result<bool, &'static str> {
+pub async fn anilist_remove_entry(client: &dyn GraphQlClient, id: i32, access_token: String) -> Result<bool, &'static str> {
 
     let json = json!({"query": ANIME_DELETE_ENTRY, "variables": {"id": id}});
 
-    match post(&json, Some(&access_token)).await {
-        Ok(result) => {
-            let deleted = serde_json::from_str::<serde_json::Value>(&result).unwrap()["data"]["DeleteMediaListEntry"]["deleted"].as_bool().unwrap();
-            return Ok(deleted);
-        },
-        Err(error) => return Err(error),
-    }
+    let response: DeleteEntryResponse = anilist_responses::parse(&client.post(&json, Some(&access_token)).await?)?;
+    Ok(response.entry.deleted)
 }
 
 
 
 // retrieve information on anime using it's anilist id
-pub async fn anilist_api_call(id: i32) -> Result<AnimeInfo, &'static str> {
+pub async fn anilist_api_call(client: &dyn GraphQlClient, id: i32) -> Result<AnimeInfo, &'static str> {
     
     // reuse call multiple for maintainability
-    match anilist_api_call_multiple(vec![id]).await {
+    match anilist_api_call_multiple(client, vec![id]).await {
         Ok(result) => {
             if let Some(first) = result.first() {
                 return Ok(first.to_owned());
@@ -410,7 +411,7 @@ query($page: Int $ids: [Int]) {
     }
 }";
 // get anime data from anilist for all ids
-pub async fn anilist_api_call_multiple(get_ids: Vec<i32>) -> Result<Vec<AnimeInfo>, &'static str>  {
+pub async fn anilist_api_call_multiple(client: &dyn GraphQlClient, get_ids: Vec<i32>) -> Result<Vec<AnimeInfo>, &'static str>  {
 
     let mut anime_info: Vec<AnimeInfo> = Vec::new();
     let pages = ceiling_div(get_ids.len(), 50);
@@ -428,47 +429,47 @@ pub async fn anilist_api_call_multiple(get_ids: Vec<i32>) -> Result<Vec<AnimeInf
         let sub_vec = &get_ids[start..end];
         let json = json!({"query": ANIME_INFO_QUERY_MULTIPLE, "variables": { "page": 0, "ids": sub_vec}});
 
-        match post(&json, None).await {
-            Ok(result) => {
-                let response = anilist_to_snake_case(result);
-                let mut anime_json: serde_json::Value = serde_json::from_str(&response).unwrap();
-                let anime_vec: Vec<AnimeInfo> = serde_json::from_value(anime_json["data"]["Page"]["media"].take()).unwrap();
-                anime_info.extend(anime_vec);
-            },
-            Err(error) => {
-                return Err(error); 
-            },
-        }
+        let response: PageResponse<AnimeInfo> = anilist_responses::parse(&client.post(&json, None).await?)?;
+        anime_info.extend(response.page.media);
     }
 
     return Ok(anime_info);
 }
 
-// convert names used by anilist to snake case used by rust
-fn anilist_to_snake_case(anilist_json: String) -> String {
-
-    anilist_json
-        .replace("\"Media\"", "\"media\"")
-        .replace("averageScore", "average_score")
-        .replace("coverImage", "cover_image")
-        .replace("isAdult", "is_adult")
-        .replace("seasonYear", "season_year")
-        .replace("type", "media_type")
-        .replace("startDate", "start_date")
-        .replace("userPreferred", "user_preferred")
-        .replace("relationType", "relation_type")
-        .replace("mediaRecommendation", "media_recommendation")
-        .replace("isGeneralSpoiler", "is_general_spoiler")
-        .replace("isMediaSpoiler", "is_media_spoiler")
-        .replace("nextAiringEpisode", "next_airing_episode")
-        .replace("airingAt", "airing_at")
-        .replace("mediaId", "media_id")
-        .replace("startedAt", "started_at")
-        .replace("completedAt", "completed_at")
-        .replace("isAnimationStudio", "is_animation_studio")
-        .replace("primaryOccupations", "primary_occupations")
-        .replace("updatedAt", "updated_at")
-        .replace("idMal", "id_mal")
+const MAL_ID_QUERY: &str = "
+query($page: Int $ids: [Int]) {
+    Page(page: $page, perPage: 50) {
+        media(type: ANIME, idMal_in: $ids) { id idMal }
+    }
+}";
+// get the anilist ids of anime using their myanimelist ids
+// returns a map of myanimelist id to anilist id, anime missing from anilist are left out
+pub async fn anilist_ids_from_mal_ids(client: &dyn GraphQlClient, mal_ids: Vec<i32>) -> Result<HashMap<i32, i32>, &'static str> {
+
+    let mut id_map: HashMap<i32, i32> = HashMap::new();
+    for chunk in mal_ids.chunks(50) {
+
+        let json = json!({"query": MAL_ID_QUERY, "variables": { "page": 0, "ids": chunk}});
+        let response: PageResponse<MediaIds> = anilist_responses::parse(&client.post(&json, None).await?)?;
+        for anime in response.page.media {
+            if let Some(id_mal) = anime.id_mal {
+                id_map.insert(id_mal, anime.id);
+            }
+        }
+    }
+
+    Ok(id_map)
+}
+
+
+
+const ANILIST_MAL_ID_QUERY: &str = "query($id: Int) { Media(id: $id, type: ANIME) { idMal } }";
+// get the myanimelist id of a anime using its anilist id
+pub async fn anilist_get_mal_id(client: &dyn GraphQlClient, media_id: i32) -> Result<Option<i32>, &'static str> {
+
+    let json = json!({"query": ANILIST_MAL_ID_QUERY, "variables": {"id": media_id}});
+    let response: MediaResponse<MediaIds> = anilist_responses::parse(&client.post(&json, None).await?)?;
+    Ok(response.media.and_then(|anime| anime.id_mal))
 }
 
 fn ceiling_div(x: usize, y: usize) -> usize {
@@ -484,7 +485,8 @@ query($userName: String, $status: [MediaListStatus]) {
     lists {
       name 
       entries {
-        id mediaId status score progress updatedAt startedAt { year month day } completedAt { year month day } notes
+        id mediaId status score progress repeat updatedAt startedAt { year month day } completedAt { year month day } notes
+        private hiddenFromStatusLists customLists(asArray: true) advancedScores
         media {
           id idMal title { userPreferred romaji english native } synonyms coverImage { large } season seasonYear type format episodes trending
           duration isAdult genres averageScore popularity description status trailer { id site } startDate { year month day }
@@ -501,7 +503,7 @@ query($userName: String, $status: [MediaListStatus]) {
 
 // retrieve information on anime using it's anilist id
 // returns a message if a error occurred
-pub async fn anilist_get_list(username: String, status: String, access_token: String, anime_user_data: &mut HashMap<i32, UserInfo>, anime_user_list_lock: &mut HashMap<String, Vec<i32>>) -> Option<String> {
+pub async fn anilist_get_list(client: &dyn GraphQlClient, username: String, status: String, access_token: String, anime_user_data: &mut HashMap<i32, UserInfo>, anime_user_list_lock: &mut HashMap<String, Vec<i32>>) -> Option<String> {
 
     // create query json
     let status_array = 
@@ -513,47 +515,42 @@ pub async fn anilist_get_list(username: String, status: String, access_token: St
 
     let json = json!({"query": USER_LIST_WITH_MEDIA, "variables": {"userName": username, "status": status_array}});
 
-    match post(&json, Some(&access_token)).await {
-        Ok(result) => {
-            let response = anilist_to_snake_case(result);
+    let response = match client.post(&json, Some(&access_token)).await {
+        Ok(result) => result,
+        Err(error) => return Some(String::from(<&str>::from(error))),
+    };
 
-            let response_json: serde_json::Value = serde_json::from_str::<serde_json::Value>(&response).unwrap();
+    // the message is shown to the user, for example when the username doesn't exist
+    let response = match GraphQlResponse::from_str(&response) {
+        Ok(response) => response,
+        Err(error) => return Some(String::from(<&str>::from(error))),
+    };
+    if let Some(error) = response.errors.first() {
+        return Some(error.message.clone());
+    }
+    let collection: ListCollectionResponse<MediaListEntry> = match response.into_data() {
+        Ok(collection) => collection,
+        Err(error) => return Some(String::from(<&str>::from(error))),
+    };
 
-            if response_json.is_object() && response_json.get("errors").is_some() {
-                let message = response_json["errors"][0]["message"].as_str().unwrap().to_string();
-                return Some(message)
-            }
-            let lists: serde_json::Value = serde_json::from_str::<serde_json::Value>(&response).unwrap()["data"]["MediaListCollection"]["lists"].take();
-        
-            let anime_user_list = anime_user_list_lock.entry(status).or_default();
-        
-            anime_user_list.clear();
-            for list in lists.as_array().unwrap() {
-                
-                for entry in list["entries"].as_array().unwrap() {
-                    
-                    let user_info: UserInfo = UserInfo { id: entry["id"].as_i64().unwrap() as i32, 
-                                                                    media_id: entry["media_id"].as_i64().unwrap() as i32, 
-                                                                    status: entry["status"].as_str().unwrap().to_string(), 
-                                                                    score: entry["score"].as_f64().unwrap() as f32, 
-                                                                    progress: entry["progress"].as_i64().unwrap() as i32, 
-                                                                    started_at: serde_json::from_value(entry["started_at"].clone()).unwrap(), 
-                                                                    completed_at: serde_json::from_value(entry["completed_at"].clone()).unwrap(),
-                                                                    notes: serde_json::from_value(entry["notes"].clone()).unwrap(),
-                                                                    updated_at: entry["updated_at"].as_u64().unwrap()};
-        
-                    anime_user_data.insert(user_info.media_id, user_info);
-                    let mut media: AnimeInfo = serde_json::from_value(entry["media"].clone()).unwrap();
-                    media.studios.nodes.retain(|node| {node.is_animation_studio == true });
-        
-                    if anime_user_list.contains(&media.id) == false {
+    let anime_user_list = anime_user_list_lock.entry(status).or_default();
+
+    anime_user_list.clear();
+    for list in collection.collection.lists {
         
-                        anime_user_list.push(media.id);
-                    }
-                }
+        for entry in list.entries {
+            
+            let user_info = UserInfo::from(entry);
+            let media_id = user_info.media_id;
+            let hidden = user_info.hidden_from_status_lists;
+            anime_user_data.insert(media_id, user_info);
+
+            // hidden entries are only shown in their custom lists
+            if hidden == false && anime_user_list.contains(&media_id) == false {
+
+                anime_user_list.push(media_id);
             }
-        },
-        Err(error) => return Some(String::from(error)),
+        }
     }
     
     None
@@ -561,10 +558,43 @@ pub async fn anilist_get_list(username: String, status: String, access_token: St
 
 
 
+// query for the ids of every anime on the user's list and the status they're listed under
+const USER_LIST_IDS: &str = "
+query($userName: String) {
+  MediaListCollection(userName: $userName, type:ANIME) {
+    lists {
+      entries {
+        mediaId status
+      }
+    }
+  }
+}";
+
+// get the ids of the anime on each of the user's lists, rewatching anime are in the CURRENT list
+pub async fn anilist_get_list_ids(client: &dyn GraphQlClient, username: String, access_token: String) -> Result<HashMap<String, HashSet<i32>>, &'static str> {
+
+    let json = json!({"query": USER_LIST_IDS, "variables": {"userName": username}});
+    let token = if access_token.is_empty() { None } else { Some(&access_token) };
+    let response: ListCollectionResponse<ListEntryId> = anilist_responses::parse(&client.post(&json, token).await?)?;
+
+    let mut list_ids: HashMap<String, HashSet<i32>> = HashMap::new();
+    // custom lists repeat entries from the status lists, the entry's status is used so they end up in the same set
+    for list in response.collection.lists {
+        for entry in list.entries {
+            let list_name = if entry.status == "REPEATING" { String::from("CURRENT") } else { entry.status };
+            list_ids.entry(list_name).or_default().insert(entry.media_id);
+        }
+    }
+
+    Ok(list_ids)
+}
+
+
+
 // query for a specific list along with all user data and media data for the anime on that list
 const MEDIA_INFO: &str = "query ($id: Int) {
     Media (id: $id, type: ANIME) { # Insert our variables into the query arguments (id) (type: ANIME is hard-coded in the query)
-        id idMal title { userPreferred romaji english native } coverImage { large } season seasonYear type format episodes trending
+        id idMal title { userPreferred romaji english native } synonyms coverImage { large } season seasonYear type format episodes trending
         duration isAdult genres averageScore popularity description status trailer { id site } startDate { year month day }
         relations { edges { relationType node { id title { romaji english native userPreferred } coverImage { large } type } } }
         recommendations { nodes { rating mediaRecommendation { id title { romaji english native userPreferred } coverImage { large } type } } }
@@ -574,40 +604,23 @@ const MEDIA_INFO: &str = "query ($id: Int) {
     }
 }";
 
-pub async fn anilist_get_anime_info_single(anime_id: i32) -> Result<AnimeInfo, &'static str> {
+pub async fn anilist_get_anime_info_single(client: &dyn GraphQlClient, anime_id: i32) -> Result<AnimeInfo, &'static str> {
 
     // create client and query json
     let json = json!({"query": MEDIA_INFO, "variables": {"id": anime_id}});
 
     // get media information from anilist api
-    match post(&json, None).await {
-        Ok(result) => {
-            let response = anilist_to_snake_case(result);
-
-            if let Ok(mut anime_value) = serde_json::from_str::<serde_json::Value>(&response) {
-
-                if let Ok(anime_data) = serde_json::from_value::<AnimeInfo>(anime_value["data"]["media"].take()) {
-
-                    return Ok(anime_data);
-                } else {
-                    println!("serde_json::from_value::<AnimeInfo>(anime_value[\"data\"][\"media\"].take()) failed");
-                    println!("{:?}", anime_value);
-                    return Err("AnimeInfo was not returned");
-                }
-            } else {
-                println!("serde_json::from_str::<serde_json::Value>(&response) failed");
-                println!("{}", response);
-                return Err("Value was not returned");
-            }
-        },
-        Err(error) => return Err(error),
+    let response: MediaResponse<AnimeInfo> = anilist_responses::parse(&client.post(&json, None).await?)?;
+    match response.media {
+        Some(anime_data) => Ok(anime_data),
+        None => Err("AnimeInfo was not returned"),
     }
 }
 
 // query for a specific list along with all user data and media data for the anime on that list
 const MEDIA_INFO2: &str = "query ($id: Int) {
     Media (id: $id, type: ANIME) { # Insert our variables into the query arguments (id) (type: ANIME is hard-coded in the query)
-        id idMal title { userPreferred romaji english native } coverImage { large } season seasonYear type format episodes trending
+        id idMal title { userPreferred romaji english native } synonyms coverImage { large } season seasonYear type format episodes trending
         duration isAdult genres averageScore popularity description status trailer { id site } startDate { year month day }
         relations { edges { relationType node { id title { romaji english native userPreferred } coverImage { large } type } } }
         recommendations { nodes { rating mediaRecommendation { id title { romaji english native userPreferred } coverImage { large } type } } }
@@ -617,32 +630,16 @@ const MEDIA_INFO2: &str = "query ($id: Int) {
     }
 }";
 
-pub async fn anilist_get_anime_info_single2(anime_id: i32) -> Result<anime_data::AnimeInfo, &'static str> {
+pub async fn anilist_get_anime_info_single2(client: &dyn GraphQlClient, anime_id: i32) -> Result<anime_data::AnimeInfo, &'static str> {
 
     // create client and query json
     let json = json!({"query": MEDIA_INFO2, "variables": {"id": anime_id}});
 
     // get media information from anilist api
-    match post(&json, None).await {
-        Ok(result) => {
-            let response = anilist_to_snake_case(result);
-
-            if let Ok(mut anime_value) = serde_json::from_str::<serde_json::Value>(&response) {
-
-                if let Ok(anime_data) = serde_json::from_value::<anime_data::AnimeInfo>(anime_value["data"]["media"].take()) {
-
-                    return Ok(anime_data);
-                } else {
-                    println!("serde_json::from_value::<AnimeInfo>(anime_value[\"data\"][\"media\"].take()) failed");
-                    println!("{:?}", anime_value);
-                }
-            } else {
-                println!("serde_json::from_str::<serde_json::Value>(&response) failed");
-                println!("{}", response);
-            }
-            return Err("Cannot extract response");
-        },
-        Err(error) => return Err(error),
+    let response: MediaResponse<anime_data::AnimeInfo> = anilist_responses::parse(&client.post(&json, None).await?)?;
+    match response.media {
+        Some(anime_data) => Ok(anime_data),
+        None => Err("Cannot extract response"),
     }
 }
 
@@ -660,54 +657,46 @@ const MEDIA_INFO_MANGA: &str = "query ($id: Int) {
     }
 }";
 // get information on manga (or light novel) from anilist
-pub async fn anilist_get_manga_ln_info(media_id: i32) -> Result<MangaInfo, &'static str> {
+pub async fn anilist_get_manga_ln_info(client: &dyn GraphQlClient, media_id: i32) -> Result<MangaInfo, &'static str> {
     
     // create client and query json
     let json = json!({"query": MEDIA_INFO_MANGA, "variables": {"id": media_id}});
 
     // get media information from anilist api
-    match post(&json, None).await {
-        Ok(result) => {
-            let response = anilist_to_snake_case(result);
-            println!("{}", response);
-            let mut anime_value: serde_json::Value = serde_json::from_str(&response).unwrap();
-            let mut manga_data: MangaInfo = serde_json::from_value(anime_value["data"]["media"].take()).unwrap();
+    let response: MediaResponse<MangaInfo> = anilist_responses::parse(&client.post(&json, None).await?)?;
+    let mut manga_data = match response.media {
+        Some(manga_data) => manga_data,
+        None => return Err("MangaInfo was not returned"),
+    };
             
-            manga_data.staff.nodes.retain(|staff_member| { 
-                staff_member.primary_occupations.len() > 0 && 
-                staff_member.primary_occupations.first().unwrap().contains("Translator") == false 
-            });
+    manga_data.staff.nodes.retain(|staff_member| { 
+        staff_member.primary_occupations.len() > 0 && 
+        staff_member.primary_occupations.first().unwrap().contains("Translator") == false 
+    });
 
-            return Ok(manga_data)
-        },
-        Err(error) => return Err(error),
-    }
+    Ok(manga_data)
 }
 
 
 
-// gets the users anime lists with all user data on each anime
-pub async fn anilist_list_query_call(username: String, access_token: String) -> Result<String, &'static str> {
-
-    // create client and query json
-    let json = json!({"query": ANIME_LIST_QUERY, "variables": {"username": username}});
+// exchanges a code the user pastes in for a access token that is used to authorize access
+pub async fn anilist_get_access_token(code: String) -> Result<TokenData, http_client::ApiError> {
 
-    // get media information from anilist api
-    match post(&json, Some(&access_token)).await {
-        Ok(result) => {
-            let response = anilist_to_snake_case(result);
-            return Ok(response)
-        },
-        Err(error) => return Err(error),
-    }
+    let response_string = anilist_token_response(code).await?;
+    anilist_token_from_response(&response_string)
 }
 
 
 
 // exchanges a code the user pastes in for a access token that is used to authorize access
-pub async fn anilist_get_access_token(code: String) -> TokenData {
+pub async fn anilist_get_access_token2(code: String) -> Result<TokenData, http_client::ApiError> {
 
-    let client = Client::new();
+    let response_string = anilist_token_response(code).await?;
+    anilist_token_from_response(&response_string)
+}
+
+// send the code to anilist, returns the body of the response
+async fn anilist_token_response(code: String) -> Result<String, http_client::ApiError> {
 
     let json = serde_json::json!({
         "grant_type": "authorization_code",
@@ -717,66 +706,42 @@ pub async fn anilist_get_access_token(code: String) -> TokenData {
         "code": code
     });
 
-    let response = client.post("https://anilist.co/api/v2/oauth/token")
+    let request = http_client::CLIENT.post("https://anilist.co/api/v2/oauth/token")
         .header("Content-Type", "application/json")
         .header("Accept", "application/json")
-        .json(&json)
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await;
+        .json(&json);
 
-    let response_string = response.unwrap();
-
-    if response_string.contains("\"error\"") {
-        return TokenData { website: Website::Anilist, token_type: json.to_string(), expires_in: 0, access_token: response_string, refresh_token: String::new() };
+    let response = http_client::send_with_retry(&http_client::ANILIST_LIMITER, request).await?;
+    match response.text().await {
+        Ok(response_string) => Ok(response_string),
+        Err(_error) => Err(http_client::ApiError::Other),
     }
-    
-    return serde_json::from_str(&response_string).unwrap();
 }
 
+// read the token returned by anilist, a refused code is returned as a error
+pub fn anilist_token_from_response(response_string: &String) -> Result<TokenData, http_client::ApiError> {
 
+    let value: Value = match serde_json::from_str(response_string) {
+        Ok(value) => value,
+        Err(_error) => return Err(http_client::ApiError::InvalidResponse),
+    };
 
-// exchanges a code the user pastes in for a access token that is used to authorize access
-pub async fn anilist_get_access_token2(code: String) -> TokenData {
-
-    let client = Client::new();
-
-    let json = serde_json::json!({
-        "grant_type": "authorization_code",
-        "client_id": secrets::CLIENT_ID,
-        "client_secret": secrets::CLIENT_SECRET,
-        "redirect_uri": secrets::REDIRECT_URI,
-        "code": code
-    });
-
-    let response = client.post("https://anilist.co/api/v2/oauth/token")
-        .header("Content-Type", "application/json")
-        .header("Accept", "application/json")
-        .json(&json)
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await;
-
-    let response_string = response.unwrap();
-
-    if response_string.contains("\"error\"") {
-        return TokenData { website: Website::Anilist, token_type: json.to_string(), expires_in: 0, access_token: response_string, refresh_token: String::new() };
+    if let Some(error) = value["error"].as_str() {
+        println!("anilist refused the code: {} {}", error, value["message"].as_str().or(value["hint"].as_str()).unwrap_or_default());
+        return Err(http_client::ApiError::Rejected);
     }
 
-    let value: Value = serde_json::from_str(&response_string).unwrap();
-    let token: TokenData = TokenData { 
-        website: Website::Anilist, 
-        token_type: value["token_type"].as_str().unwrap().to_string(), 
-        expires_in: value["expires_in"].as_i64().unwrap() as i32, 
-        access_token: value["access_token"].as_str().unwrap().to_string(), 
-        refresh_token: value["refresh_token"].as_str().unwrap().to_string() 
-    };
-    
-    return token;
+    match (value["token_type"].as_str(), value["expires_in"].as_i64(), value["access_token"].as_str(), value["refresh_token"].as_str()) {
+        (Some(token_type), Some(expires_in), Some(access_token), Some(refresh_token)) => Ok(TokenData {
+            website: Website::Anilist,
+            token_type: token_type.to_string(),
+            expires_in: expires_in as i32,
+            access_token: access_token.to_string(),
+            refresh_token: refresh_token.to_string(),
+            issued_at: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |time| time.as_secs()),
+        }),
+        _ => Err(http_client::ApiError::InvalidResponse),
+    }
 }
 
 
@@ -790,8 +755,8 @@ const AIRING_INFO: &str = "query($page: Int $ids: [Int]) {
         }
     }
 }";
-// get data for the next airing episode for given ids
-pub async fn anilist_airing_time(anime_ids: Vec<i32>, anime_data: &mut HashMap<i32, AnimeInfo>) -> Result<(), &'static str> {
+// get data for the next airing episode for given ids, the caller saves anime_data if it needs to be kept
+pub async fn anilist_airing_time(client: &dyn GraphQlClient, anime_ids: Vec<i32>, anime_data: &mut HashMap<i32, AnimeInfo>) -> Result<(), &'static str> {
     
     if anime_ids.is_empty() {
         return Ok(());
@@ -800,36 +765,28 @@ pub async fn anilist_airing_time(anime_ids: Vec<i32>, anime_data: &mut HashMap<i
     // create query json
     let json = json!({"query": AIRING_INFO, "variables": {"ids": anime_ids}});
     // get airing data from anilist
-    match post(&json, None).await {
-        Ok(result) => {
-            let response = anilist_to_snake_case(result);
-            // get list of media
-            let airing_times: serde_json::Value = serde_json::from_str(&response).unwrap();
-            let media = airing_times["data"]["Page"]["media"].as_array().unwrap();
-            // change each anime's airing info
-            for anime in media {
-        
-                let id = anime["id"].as_i64().unwrap() as i32;
-                if anime["next_airing_episode"].is_null() == false {
-        
-                    let airing_at = anime["next_airing_episode"]["airing_at"].as_i64().unwrap() as i32;
-                    let episode = anime["next_airing_episode"]["episode"].as_i64().unwrap() as i32;
-        
-                    if let Some(anime) = anime_data.get_mut(&id) {
-                        anime.next_airing_episode = Some(NextAiringEpisode { airing_at, episode});
-                    }
-                } else {
-                    if let Some(anime) = anime_data.get_mut(&id) {
-                        anime.next_airing_episode = None;
-                    }
-                }
-            }
+    let response: PageResponse<AiringInfo> = anilist_responses::parse(&client.post(&json, None).await?)?;
+    // change each anime's airing info
+    for airing_info in response.page.media {
+        if let Some(anime) = anime_data.get_mut(&airing_info.id) {
+            anime.next_airing_episode = airing_info.next_airing_episode;
+        }
+    }
 
-            // anime data has been changed so save changes to disk
-            file_operations::write_file_anime_info_cache(&anime_data);
-            Ok(())
-        },
-        Err(error) => return Err(error),
+    Ok(())
+}
+
+
+
+// smallest query anilist will answer
+const PING_QUERY: &str = "query { Page(perPage: 1) { pageInfo { total } } }";
+// false if anilist couldn't be reached, any answer including a error means the connection works
+pub async fn anilist_ping(client: &dyn GraphQlClient) -> bool {
+
+    let json = json!({"query": PING_QUERY});
+    match client.post(&json, None).await {
+        Err(http_client::ApiError::Offline) => false,
+        _ => true,
     }
 }
 
@@ -837,17 +794,28 @@ pub async fn anilist_airing_time(anime_ids: Vec<i32>, anime_data: &mut HashMap<i
 
 // query to change the users data for a specific anime
 const ANIME_UPDATE_ENTRY: &str = "
-mutation ($id: Int, $media_id: Int, $status: MediaListStatus, $score: Float, $progress: Int, $start_year: Int, $start_month: Int, $start_day: Int, $end_year: Int, $end_month: Int, $end_day: Int, $notes: String) { 
-    SaveMediaListEntry (id: $id, mediaId: $media_id, status: $status, score: $score, progress: $progress, startedAt: {year: $start_year, month: $start_month, day: $start_day}, completedAt: {year: $end_year, month: $end_month, day: $end_day}, notes: $notes) {
-        id mediaId status score progress startedAt { year month day } completedAt { year month day } notes
+mutation ($id: Int, $media_id: Int, $status: MediaListStatus, $score: Float, $progress: Int, $repeat: Int, $private: Boolean, $hidden_from_status_lists: Boolean, $custom_lists: [String], $advanced_scores: [Float], $start_year: Int, $start_month: Int, $start_day: Int, $end_year: Int, $end_month: Int, $end_day: Int, $notes: String) { 
+    SaveMediaListEntry (id: $id, mediaId: $media_id, status: $status, score: $score, progress: $progress, repeat: $repeat, private: $private, hiddenFromStatusLists: $hidden_from_status_lists, customLists: $custom_lists, advancedScores: $advanced_scores, startedAt: {year: $start_year, month: $start_month, day: $start_day}, completedAt: {year: $end_year, month: $end_month, day: $end_day}, notes: $notes) {
+        id mediaId status score progress repeat startedAt { year month day } completedAt { year month day } notes
     }
 }";
 
 // change the users entry data on anilist with the current data
-pub async fn update_user_entry(access_token: String, anime: UserInfo) -> Result<String, &'static str> {
+// advanced_scoring is the user's advanced scoring categories, anilist takes the scores in that order
+// returns the id of the list entry, new entries get their id from anilist
+pub async fn update_user_entry(client: &dyn GraphQlClient, access_token: String, anime: UserInfo, advanced_scoring: &Vec<String>) -> Result<i32, &'static str> {
 
     let mut mutation: String = ANIME_UPDATE_ENTRY.to_string();
-    let mut variables = json!({"media_id": anime.media_id, "status": anime.status, "score": anime.score, "progress": anime.progress});
+    let mut variables = json!({"media_id": anime.media_id, "status": anime.status, "score": anime.score, "progress": anime.progress, "repeat": anime.repeat,
+        "private": anime.private, "hidden_from_status_lists": anime.hidden_from_status_lists, "custom_lists": anime.custom_lists});
+
+    if advanced_scoring.is_empty() {
+        mutation = mutation.replace(", $advanced_scores: [Float]", "");
+        mutation = mutation.replace(", advancedScores: $advanced_scores", "");
+    } else {
+        let scores: Vec<f32> = advanced_scoring.iter().map(|category| anime.advanced_scores.get(category).cloned().unwrap_or_default()).collect();
+        variables["advanced_scores"] = json!(scores);
+    }
 
     if anime.id != 0 {
         variables["id"] = json!(anime.id);
@@ -884,86 +852,88 @@ pub async fn update_user_entry(access_token: String, anime: UserInfo) -> Result<
 
     let json = json!({"query": mutation, "variables": variables});
     
-    match post(&json, Some(&access_token)).await {
-        Ok(result) => {
-            let response = anilist_to_snake_case(result);
-            Ok(response)
-        },
-        Err(error) => return Err(error),
-    }
+    // a error here means anilist rejected the change, retrying the same mutation will not help
+    let response: SaveEntryResponse = anilist_responses::parse(&client.post(&json, Some(&access_token)).await?)?;
+    Ok(response.entry.id)
+}
+
+
+
+const MEDIA_LIST_ENTRY: &str = "query($id: Int) { Media(id: $id, type: ANIME) { mediaListEntry { id mediaId status score progress repeat updatedAt startedAt { year month day } completedAt { year month day } notes private hiddenFromStatusLists customLists(asArray: true) advancedScores } } }";
+// get the users current entry on anilist for a single anime, None if the anime is not on their list
+pub async fn get_media_list_entry(client: &dyn GraphQlClient, media_id: i32, access_token: String) -> Result<Option<UserInfo>, &'static str> {
+
+    let json = json!({"query": MEDIA_LIST_ENTRY, "variables": {"id": media_id}});
+
+    let response: MediaResponse<MediaWithEntry> = anilist_responses::parse(&client.post(&json, Some(&access_token)).await?)?;
+    Ok(response.media.and_then(|media| media.media_list_entry).map(UserInfo::from))
 }
 
 
 
 const USER_SCORE_FORMAT: &str = " query($username: String) { User(name: $username) { mediaListOptions { scoreFormat } } }";
 // get the number the users score is out of (1/3, 1/5, 1/10, 1/100, etc)
-pub async fn get_user_score_format(username: String) -> Result<String, &'static str> {
+pub async fn get_user_score_format(client: &dyn GraphQlClient, username: String) -> Result<String, &'static str> {
 
     let json = json!({"query": USER_SCORE_FORMAT, "variables": {"username": username}});
 
-    match post(&json, None).await {
-        Ok(result) => {
-            let format = serde_json::from_str::<serde_json::Value>(&result).unwrap()["data"]["User"]["mediaListOptions"]["scoreFormat"].to_string().replace("\"", "");
-            Ok(format)
-        },
-        Err(error) => return Err(error),
+    let response: UserResponse = anilist_responses::parse(&client.post(&json, None).await?)?;
+    match response.user.media_list_options.and_then(|options| options.score_format) {
+        Some(format) => Ok(format),
+        None => Err("Value was not returned"),
     }
 }
 
 
 
-const USER_MEDIA_DATA: &str = "query($ids: [Int]) { Page(page: 0, perPage: 50) { media(id_in: $ids) { mediaListEntry { id mediaId status score progress updatedAt startedAt { year month day } completedAt { year month day } } } } }";
+const USER_LIST_OPTIONS: &str = " query($username: String) { User(name: $username) { mediaListOptions { animeList { customLists advancedScoring advancedScoringEnabled } } } }";
+// get the names of the user's custom lists and advanced scoring categories
+pub async fn get_user_list_options(client: &dyn GraphQlClient, username: String) -> Result<ListOptions, &'static str> {
+
+    let json = json!({"query": USER_LIST_OPTIONS, "variables": {"username": username}});
+    let response: UserResponse = anilist_responses::parse(&client.post(&json, None).await?)?;
+    let anime_list = match response.user.media_list_options.and_then(|options| options.anime_list) {
+        Some(anime_list) => anime_list,
+        None => return Err("anilist returned error"),
+    };
+
+    let mut options = ListOptions { custom_lists: anime_list.custom_lists.unwrap_or_default(), advanced_scoring: Vec::new() };
+    if anime_list.advanced_scoring_enabled == Some(true) {
+        options.advanced_scoring = anime_list.advanced_scoring.unwrap_or_default();
+    }
+
+    Ok(options)
+}
+
+
+
+const USER_MEDIA_DATA: &str = "query($ids: [Int]) { Page(page: 0, perPage: 50) { media(id_in: $ids) { mediaListEntry { id mediaId status score progress repeat updatedAt startedAt { year month day } completedAt { year month day } } } } }";
 // get user data for the requested ids
-pub async fn get_media_user_data(ids: Vec<i32>, access_token: String) -> Result<Vec<UserAnimeInfo>, &'static str> {
+pub async fn get_media_user_data(client: &dyn GraphQlClient, ids: Vec<i32>, access_token: String) -> Result<Vec<UserInfo>, &'static str> {
     
     let json = json!({"query": USER_MEDIA_DATA, "variables": {"ids": ids}});
     println!("{:?}", json);
-    match post(&json, Some(&access_token)).await {
-        Ok(result) => {
-            let response = anilist_to_snake_case(result);
-
-            let response_json = serde_json::from_str::<serde_json::Value>(&response).unwrap();
-            println!("{:?}", response_json);
-            let mut user_anime_data: Vec<UserAnimeInfo> = Vec::new();
-            if let Some(media) = response_json["data"]["Page"]["media"].as_array(){
-        
-                for entry in media {
-                    if let Ok(updated_anime) = serde_json::from_value(entry["mediaListEntry"].clone()) {
-                        user_anime_data.push(updated_anime);
-                    } else {
-
-                    }
-                }
-            }
-            Ok(user_anime_data)
-        },
-        Err(error) => return Err(error),
-    }
+    let response: PageResponse<MediaWithEntry> = anilist_responses::parse(&client.post(&json, Some(&access_token)).await?)?;
+    // anime that aren't on the user's list have no entry
+    Ok(response.page.media.into_iter().filter_map(|media| media.media_list_entry).map(UserInfo::from).collect())
 }
 
 
 
 const USER_ID_QUERY: &str = "query($userName: String) { User(name: $userName) { id } }";
 // get the user id for the requested username
-pub async fn get_user_id(username: String) -> Option<i32> {
+pub async fn get_user_id(client: &dyn GraphQlClient, username: String) -> Option<i32> {
     
     let json = json!({"query": USER_ID_QUERY, "variables": {"userName": username}});
     println!("{}", json.to_string());
 
-    match post(&json, None).await {
-        Ok(result) => {
-
-            let result_value = serde_json::from_str::<serde_json::Value>(&result).unwrap();
-            let result_map = result_value.as_object().unwrap();
-            if result_map.contains_key("errors") {
-                println!("Result: {}", result);
-                return None;
-            } else {
-                let user_id = result_map["data"]["User"]["id"].as_i64().unwrap() as i32;
-                return Some(user_id);
-            }
+    let result = client.post(&json, None).await.ok()?;
+    match anilist_responses::parse::<UserResponse>(&result) {
+        Ok(response) => Some(response.user.id),
+        Err(_error) => {
+            println!("Result: {}", result);
+            None
         },
-        Err(_error) => return None,
     }
 }
 
@@ -971,34 +941,34 @@ pub async fn get_user_id(username: String) -> Option<i32> {
 
 const USER_UPDATED_AT: &str = "query($userName: String) { User(name: $userName) { updatedAt } }";
 // get the last time the user's data was changed
-pub async fn get_user_updated_at(username: String) -> Option<u64> {
+pub async fn get_user_updated_at(client: &dyn GraphQlClient, username: String) -> Option<u64> {
     
     let json = json!({"query": USER_UPDATED_AT, "variables": {"username": username}});
 
-    match post(&json, None).await {
-        Ok(result) => {
-            let updated_at = serde_json::from_str::<serde_json::Value>(&result).unwrap()["data"]["User"]["updatedAt"].as_u64();
-            return updated_at;
-        },
-        Err(_error) => return None,
-    }
+    let result = client.post(&json, None).await.ok()?;
+    anilist_responses::parse::<UserResponse>(&result).ok()?.user.updated_at
 }
 
 
 
 const USER_MEDIA_UPDATED: &str = "
-query($name: String) { 
-    Page(page: 0, perPage: 10){
+query($name: String, $page: Int) { 
+    Page(page: $page, perPage: 50){
         pageInfo {
             hasNextPage
         }
-        mediaList(userName: $name, sort: UPDATED_TIME_DESC) {
+        mediaList(userName: $name, type: ANIME, sort: UPDATED_TIME_DESC) {
             updatedAt
             mediaId
             id
             progress
             score
             status
+            repeat
+            private
+            hiddenFromStatusLists
+            customLists(asArray: true)
+            advancedScores
             startedAt {
               day
               month
@@ -1010,81 +980,43 @@ query($name: String) {
               year
             }
       		notes
+            media {
+              type
+            }
         }
     }
 }";
-// returns a list of media that has been updated after the supplied time
-pub async fn get_updated_media_ids(username: String, page: i32) -> Result<Vec<UserInfo>, &'static str> {
+// returns the user's list entries that were changed after the supplied unix time, newest first
+// pages are requested until a entry older than the time is found
+pub async fn get_updated_media_ids(client: &dyn GraphQlClient, username: String, since: u64, access_token: String) -> Result<Vec<UserInfo>, &'static str> {
     
-    // get list of recent activity about the user
-    let json = json!({"query": USER_MEDIA_UPDATED, "variables": {"name": username,"page": page}});
-    match post(&json, None).await {
-        Ok(result) => {
-            let response = anilist_to_snake_case(result);
-            let response_value = serde_json::from_str::<serde_json::Value>(&response).unwrap();
-
-            // print errors
-            if let Some(error_list) = response_value["errors"].as_array() {
-                for entry in error_list {
-                    if let Some(message) = entry["message"].as_str() {
-                        println!("message {}", message);
-                    }
-                    if let Some(status) = entry["status"].as_i64() {
-                        println!("status {}", status);
-                    }
-                }
-                return Err("anilist returned error");
-            }
-
-            // get user info out of json response
-            let mut media_entrys: Vec<UserInfo> = Vec::new();
-            if let Some(media_list) = response_value["data"]["Page"]["mediaList"].as_array() {
-                for media in media_list {
-                    let data: UserInfo = serde_json::from_value(media.to_owned()).unwrap();
-                    media_entrys.push(data);
-                }
+    let mut media_entrys: Vec<UserInfo> = Vec::new();
+    let mut page = 1;
+    loop {
+        // get list of recent activity about the user
+        let json = json!({"query": USER_MEDIA_UPDATED, "variables": {"name": username,"page": page}});
+        let token = if access_token.is_empty() { None } else { Some(&access_token) }; // private lists need the token
+        let response: PageResponse<MediaListEntry> = anilist_responses::parse(&client.post(&json, token).await?)?;
+
+        for entry in response.page.media_list {
+            let manga = entry.media.as_ref().and_then(|media| media.media_type.as_deref()).map_or(false, |media_type| media_type != "ANIME");
+            let data = UserInfo::from(entry);
+            // everything after this was synced already
+            if data.updated_at <= since {
                 return Ok(media_entrys);
             }
-        },
-        Err(error) => return Err(error),
-    }
-    return Ok(Vec::new());
-}
-
-
-// send post json to https://graphql.anilist.co/ and return its response as a string
-// access token in necessary for creating, updating, deleting, and reading private data
-pub async fn post(json: &Value, access_token: Option<&String>) -> Result<String, &'static str> {
-
-    let client = Client::new();
-    let mut request_builder = client.post("https://graphql.anilist.co/");
-    if access_token.is_some() {
-        request_builder = request_builder.header("Authorization", String::from("Bearer ") + access_token.unwrap());
-    }
-
-    let response: Result<reqwest::Response, reqwest::Error> = request_builder.header("Content-Type", "application/json")
-        .header("Accept", "application/json")
-        .body(json.to_string())
-        .send()
-        .await;
-
-    if response.is_err() {
-        let error = response.err().unwrap();
-        if error.is_connect() { // no internet
-            println!("connect error");
-            return Err("no connection")
-        }
-        else if error.is_request() { // no internet
-            println!("request error");
-            return Err("bad request")
+            // manga entries would end up on the anime list
+            if manga {
+                continue;
+            }
+            media_entrys.push(data);
         }
-        else {
-            println!("{:?}", error);
-            return Err("error")
+
+        if response.page.page_info.has_next_page == false {
+            break;
         }
+        page += 1;
     }
-    else {
-        let response_string = response.unwrap().text().await.unwrap();
-        return Ok(response_string)
-    }
-}
\ No newline at end of file
+
+    Ok(media_entrys)
+}
@@ -2,15 +2,39 @@ pub const ANIME_PER_PAGE: usize = 50;
 pub const SECONDS_IN_MINUTES: i32 = 60;
 pub const DEFAULT_HIGHLIGHT_COLOR: &str = "rgb(96, 217, 236)";
 pub const ANIME_UPDATE_DELAY: u64 = 5;
+pub const DEFAULT_WATCHED_PERCENT: i32 = 80;
+pub const COMPLETION_POLICIES: [&'static str; 3] = ["PERCENT","MINUTES","CREDITS"];
+pub const MAX_WATCH_TIME_GAP: f64 = 60.0; // longest time between checks that is counted as watching, in seconds
+pub const WATCH_TIME_EXPIRY: u64 = 12 * 60 * 60; // episodes that haven't been played for this long are no longer tracked, in seconds
+pub const CREDITS_LENGTH: f64 = 2.0 * 60.0; // ending song and next episode preview, in seconds
 pub const SIMILARITY_SCORE_THRESHOLD: f64 = 0.8;
 pub const STARTUP_SCAN_DELAY: u64 = 30;
 pub const ONE_HOUR: u64 = 60 * 60;
-pub const NO_INTERNET_UPDATE_INTERVAL: u64 = 5 * 60;
+pub const NO_INTERNET_UPDATE_INTERVAL: i32 = 5 * 60; // seconds between checking if the website can be reached again
+pub const DEFAULT_PLAYER_DETECTION_INTERVAL: i32 = 10; // seconds between checking media players, must be shorter than MAX_WATCH_TIME_GAP
+pub const DEFAULT_QUEUE_FLUSH_INTERVAL: i32 = 30; // seconds between sending changes that are waiting for the website
+pub const DEFAULT_LIBRARY_SCAN_INTERVAL: i32 = 60; // minutes between scanning folders for new episodes
+pub const DEFAULT_AIRING_REFRESH_INTERVAL: i32 = 60; // minutes between getting airing times
+pub const DEFAULT_CACHE_REFRESH_INTERVAL: i32 = 24 * 60; // minutes between downloading the info of the user's anime again
+pub const DISABLED_JOB_CHECK_INTERVAL: u64 = 60; // seconds between checking if a job that was turned off has been turned on
 pub const BROWSE_PAGE_LIMIT: i32 = 4;
 pub const FILENAME_CHUNKS: usize = 200;
 pub const USER_LISTS: [&'static str; 5] = ["CURRENT","COMPLETED","PAUSED","DROPPED","PLANNING"];
 pub const USER_STATUSES: [&'static str; 6] = ["CURRENT","COMPLETED","PAUSED","DROPPED","PLANNING","REPEATING"];
 pub const MAL_USER_STATUSES: [&'static str; 5] = ["watching","completed","on_hold","dropped","plan_to_watch"];
+pub const LOCAL_SCORE_FORMAT: &str = "POINT_10_DECIMAL"; // scores of a list kept without a account
+pub const SYNC_INTERVAL: u64 = 15 * 60; // time between pulling changes made on the website, in seconds
+pub const SYNC_OVERLAP: u64 = 5 * 60; // changes this long before the last sync are pulled again in case the clocks differ, in seconds
+pub const DEFAULT_DELETION_CHECK_INTERVAL: i32 = 60; // minutes between checking for anime removed from the list on the website
+pub const ANILIST_GRAPHQL_URL: &str = "https://graphql.anilist.co/";
+#[cfg(debug_assertions)]
+pub const ANILIST_URL_VARIABLE: &str = "GEKIJOU_ANILIST_URL"; // environment variable that points anilist queries somewhere else, like a local test server
+pub const ANILIST_REQUESTS_PER_MINUTE: f64 = 90.0; // anilist rejects requests past this rate
+pub const MAL_REQUESTS_PER_MINUTE: f64 = 60.0; // myanimelist doesn't publish a limit, this keeps bulk syncs polite
+pub const MAX_RETRIES: u32 = 3; // times a failed request is sent again before giving up
+pub const RETRY_BASE_DELAY: u64 = 1; // seconds before the first retry, doubled after each failure
+pub const RETRY_MAX_DELAY: u64 = 30; // longest wait between retries, in seconds
+pub const RATE_LIMIT_DEFAULT_WAIT: u64 = 60; // seconds to wait when rate limited without a retry-after header
 
 
 #[cfg(debug_assertions)]
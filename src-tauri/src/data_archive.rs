@@ -0,0 +1,98 @@
+use std::{collections::{HashMap, HashSet}, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{anime_data::AnimePath, api_calls::TokenData, user_data::{UserInfo, UserSettings}, watch_history::WatchRecord};
+
+
+
+// everything needed to move gekijou to another computer, saved as a single file
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DataArchive {
+    pub exported_at: u64, // unix time in seconds
+    pub settings: Option<UserSettings>,
+    pub token: Option<TokenData>, // only included if the user asked for it, it gives access to their account
+    pub user_data: HashMap<i32, UserInfo>,
+    pub user_lists: HashMap<String, Vec<i32>>,
+    pub episode_paths: HashMap<i32, HashMap<i32, ArchivePath>>,
+    pub custom_titles: HashMap<i32, String>,
+    pub watch_history: Vec<WatchRecord>,
+    pub missing_ids: HashSet<i32>,
+}
+
+// a episode file, stored relative to one of the user's folders so it can be found on another computer
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ArchivePath {
+    pub folder: Option<usize>, // index into the settings' folders, None if the file is outside of them and the path is absolute
+    pub path: String, // separated by / on every system
+    pub similarity_score: f64,
+}
+
+// what to do when the archive and this computer both have data for the same thing
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ImportConflict {
+    KeepLocal,
+    UseArchive,
+    Newest, // user entries are compared by when they were last changed, everything else uses the archive
+}
+
+// what an import changed, shown to the user when it's finished
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ImportSummary {
+    pub added: usize, // anime that weren't on this computer's list
+    pub replaced: usize, // anime that were on the list and were replaced by the archive
+    pub kept: usize, // anime that were on the list and were kept
+    pub settings: bool, // settings were replaced
+    pub token: bool, // the login was replaced
+    pub episode_paths: usize,
+    pub custom_titles: usize,
+    pub watch_records: usize,
+}
+
+impl ImportConflict {
+
+    // true if the archive's entry should replace the local entry
+    pub fn use_archive(&self, local_updated_at: u64, archive_updated_at: u64) -> bool {
+        match self {
+            ImportConflict::KeepLocal => false,
+            ImportConflict::UseArchive => true,
+            ImportConflict::Newest => archive_updated_at > local_updated_at,
+        }
+    }
+}
+
+
+
+// store a episode path relative to the folder it was found in
+pub fn to_archive_path(anime_path: &AnimePath, folders: &Vec<String>) -> ArchivePath {
+
+    let path = Path::new(&anime_path.path);
+    for (index, folder) in folders.iter().enumerate() {
+        if folder.is_empty() {
+            continue;
+        }
+        if let Ok(relative) = path.strip_prefix(folder) {
+            let components: Vec<String> = relative.components().map(|component| component.as_os_str().to_string_lossy().to_string()).collect();
+            return ArchivePath { folder: Some(index), path: components.join("/"), similarity_score: anime_path.similarity_score };
+        }
+    }
+
+    ArchivePath { folder: None, path: anime_path.path.clone(), similarity_score: anime_path.similarity_score }
+}
+
+// turn a stored path back into a full path using the folders on this computer
+// returns None if the folder the file was in isn't set
+pub fn from_archive_path(archive_path: &ArchivePath, folders: &Vec<String>) -> Option<AnimePath> {
+
+    let path = match archive_path.folder {
+        Some(index) => {
+            let folder = folders.get(index).filter(|folder| folder.is_empty() == false)?;
+            let mut path = PathBuf::from(folder);
+            path.extend(archive_path.path.split('/'));
+            path.to_string_lossy().to_string()
+        },
+        None => archive_path.path.clone(),
+    };
+
+    Some(AnimePath { path, similarity_score: archive_path.similarity_score })
+}
@@ -0,0 +1,49 @@
+use std::{fs, path::Path};
+use serde::Deserialize;
+
+use crate::{anilist_response_tests::{expect, ResponseTest}, anime_data::AnimePath, data_archive};
+
+
+
+// a episode path exported on one computer and imported on another
+#[derive(Deserialize)]
+struct EpisodePathFixture {
+    name: String,
+    exported_folders: Vec<String>,
+    imported_folders: Vec<String>,
+    path: String,
+    archive_folder: Option<usize>,
+    archive_path: String,
+    imported_path: Option<String>, // None if the file can't be found from the imported folders
+}
+
+// moves each path in archive_fixtures/episode_paths.json through an archive and checks where it ends up
+pub fn archive_path_tests() -> Vec<ResponseTest> {
+
+    let fixtures: Vec<EpisodePathFixture> = match fs::read_to_string(Path::new("archive_fixtures").join("episode_paths.json")) {
+        Ok(contents) => match serde_json::from_str(&contents) {
+            Ok(fixtures) => fixtures,
+            Err(error) => return vec![ResponseTest { fixture: String::from("episode_paths.json"), passed: false, error: error.to_string() }],
+        },
+        Err(error) => return vec![ResponseTest { fixture: String::from("episode_paths.json"), passed: false, error: error.to_string() }],
+    };
+
+    fixtures.iter().map(|fixture| {
+        let result = round_trip(fixture);
+        ResponseTest { fixture: format!("archive path {}", fixture.name), passed: result.is_ok(), error: result.err().unwrap_or_default() }
+    }).collect()
+}
+
+fn round_trip(fixture: &EpisodePathFixture) -> Result<(), String> {
+
+    let anime_path = AnimePath { path: fixture.path.clone(), similarity_score: 0.9 };
+    let archive_path = data_archive::to_archive_path(&anime_path, &fixture.exported_folders);
+    expect("folder", &archive_path.folder, &fixture.archive_folder)?;
+    expect("archive path", &archive_path.path, &fixture.archive_path)?;
+
+    let imported = data_archive::from_archive_path(&archive_path, &fixture.imported_folders);
+    // paths are compared by their parts so the separator used by this system doesn't matter
+    expect("imported path", &imported.as_ref().map(|imported| Path::new(&imported.path).to_path_buf()), &fixture.imported_path.as_ref().map(|path| Path::new(path).to_path_buf()))?;
+    expect("similarity score", &imported.map(|imported| imported.similarity_score).unwrap_or(0.9), &0.9)?;
+    Ok(())
+}
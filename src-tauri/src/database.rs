@@ -0,0 +1,372 @@
+use std::{collections::{HashMap, HashSet}, path::Path, sync::Mutex};
+
+use rusqlite::{params, Connection, Transaction};
+
+use crate::{anime_data::{AnimeInfo, AnimePath}, file_operations::PersistError, user_data::UserInfo};
+
+
+
+const DATABASE_FILE: &str = "gekijou.db";
+
+// anime and user entries are stored as json so new fields don't need a table change
+const CREATE_TABLES: &str = "
+    CREATE TABLE IF NOT EXISTS anime_cache (media_id INTEGER PRIMARY KEY, data TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS user_entries (media_id INTEGER PRIMARY KEY, data TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS user_lists (list TEXT NOT NULL, position INTEGER NOT NULL, media_id INTEGER NOT NULL, PRIMARY KEY (list, position));
+    CREATE TABLE IF NOT EXISTS episode_paths (media_id INTEGER NOT NULL, episode INTEGER NOT NULL, path TEXT NOT NULL, similarity_score REAL NOT NULL, PRIMARY KEY (media_id, episode));
+    CREATE TABLE IF NOT EXISTS known_files (hash INTEGER PRIMARY KEY);
+    CREATE TABLE IF NOT EXISTS missing_ids (media_id INTEGER PRIMARY KEY);
+";
+
+// version 1 is the tables above filled from the json files
+// DATABASE_MIGRATIONS[n] upgrades a database from version n + 1 to version n + 2, add to it when the tables change
+const DATABASE_MIGRATIONS: [&str; 2] = [
+    // 2: list entries got a rewatch count
+    "UPDATE user_entries SET data = json_set(data, '$.repeat', 0) WHERE json_type(data, '$.repeat') IS NULL;",
+    // 3: list entries got the rest of anilist's list entry fields
+    "UPDATE user_entries SET data = json_set(data, '$.private', json('false'), '$.hidden_from_status_lists', json('false'), '$.custom_lists', json('[]'), '$.advanced_scores', json('{}'))
+        WHERE json_type(data, '$.private') IS NULL;",
+];
+pub const DATABASE_VERSION: i32 = DATABASE_MIGRATIONS.len() as i32 + 1;
+
+lazy_static! {
+    // the connection isn't shared between threads, every use locks it
+    static ref DATABASE: Mutex<Option<Connection>> = Mutex::new(None);
+}
+
+#[cfg(debug_assertions)]
+tokio::task_local! {
+    // set by with_test_database
+    static TEST_DATABASE: std::cell::RefCell<Connection>;
+}
+
+
+
+// data read out of the json files that were used before the database
+#[derive(Default)]
+pub struct JsonData {
+    pub anime_cache: HashMap<i32, AnimeInfo>,
+    pub user_data: HashMap<i32, UserInfo>,
+    pub user_lists: HashMap<String, Vec<i32>>,
+    pub episode_path: HashMap<i32, HashMap<i32, AnimePath>>,
+    pub known_files: HashSet<u64>,
+    pub missing_ids: HashSet<i32>,
+}
+
+
+
+// open the database in the folder, creating it if it doesn't exist
+// returns true if the database is new and needs data moved into it
+pub fn open(folder: &Path) -> Result<bool, PersistError> {
+
+    let mut connection = Connection::open(folder.join(DATABASE_FILE))?;
+    connection.execute_batch("PRAGMA journal_mode = WAL;")?;
+
+    let version: i32 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    // tables this version doesn't know about can't be read or written safely
+    if version > DATABASE_VERSION {
+        return Err(PersistError::NewerVersion(version as u64));
+    }
+    connection.execute_batch(CREATE_TABLES)?;
+
+    if version > 0 {
+        let transaction = connection.transaction()?;
+        upgrade(&transaction, version)?;
+        transaction.commit()?;
+    }
+
+    *DATABASE.lock().unwrap() = Some(connection);
+    Ok(version == 0)
+}
+
+
+
+// move data from the json files into the database, the files are left as they are
+pub fn migrate_json(data: JsonData) -> Result<(), rusqlite::Error> {
+
+    with_transaction(|transaction| {
+        replace_anime_cache(transaction, &data.anime_cache)?;
+        replace_user_data(transaction, &data.user_data)?;
+        replace_user_lists(transaction, &data.user_lists)?;
+        replace_episode_paths(transaction, &data.episode_path)?;
+        replace_known_files(transaction, &data.known_files)?;
+        replace_missing_ids(transaction, &data.missing_ids)?;
+        // marks the migration as done
+        transaction.pragma_update(None, "user_version", 1)?;
+        upgrade(transaction, 1)
+    })
+}
+
+// run the migrations from a database version to the current version
+fn upgrade(connection: &Connection, version: i32) -> Result<(), rusqlite::Error> {
+
+    for (index, migration) in DATABASE_MIGRATIONS.iter().enumerate().skip(version as usize - 1) {
+        connection.execute_batch(migration)?;
+        connection.pragma_update(None, "user_version", index as i32 + 2)?;
+    }
+    Ok(())
+}
+
+
+
+// close the database and delete it
+pub fn delete(folder: &Path) -> bool {
+
+    *DATABASE.lock().unwrap() = None;
+
+    for file in [DATABASE_FILE.to_string(), format!("{}-wal", DATABASE_FILE), format!("{}-shm", DATABASE_FILE)] {
+        let path = folder.join(file);
+        if path.exists() && std::fs::remove_file(path).is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+
+
+// run changes in a transaction so they are all written or none of them are
+fn with_transaction<F: FnOnce(&Transaction) -> Result<(), rusqlite::Error>>(changes: F) -> Result<(), rusqlite::Error> {
+
+    #[cfg(debug_assertions)]
+    if TEST_DATABASE.try_with(|_| ()).is_ok() {
+        return TEST_DATABASE.with(|connection| run_transaction(&mut connection.borrow_mut(), changes));
+    }
+
+    let mut database = DATABASE.lock().unwrap();
+    match database.as_mut() {
+        Some(connection) => run_transaction(connection, changes),
+        None => Err(rusqlite::Error::InvalidPath("database is not open".into())),
+    }
+}
+
+fn run_transaction<F: FnOnce(&Transaction) -> Result<(), rusqlite::Error>>(connection: &mut Connection, changes: F) -> Result<(), rusqlite::Error> {
+    let transaction = connection.transaction()?;
+    changes(&transaction)?;
+    transaction.commit()
+}
+
+fn with_connection<T, F: FnOnce(&Connection) -> Result<T, rusqlite::Error>>(query: F) -> Result<T, rusqlite::Error> {
+
+    #[cfg(debug_assertions)]
+    if TEST_DATABASE.try_with(|_| ()).is_ok() {
+        return TEST_DATABASE.with(|connection| query(&connection.borrow()));
+    }
+
+    let database = DATABASE.lock().unwrap();
+    match database.as_ref() {
+        Some(connection) => query(connection),
+        None => Err(rusqlite::Error::InvalidPath("database is not open".into())),
+    }
+}
+
+
+
+// runs the future with every read and write going to a empty database in memory instead of the user's
+// only the future sees it, gekijou keeps using the user's database while fixture tests run
+#[cfg(debug_assertions)]
+pub async fn with_test_database<F: std::future::Future>(future: F) -> Result<F::Output, rusqlite::Error> {
+
+    let connection = Connection::open_in_memory()?;
+    connection.execute_batch(CREATE_TABLES)?;
+    connection.pragma_update(None, "user_version", DATABASE_VERSION)?;
+    Ok(TEST_DATABASE.scope(std::cell::RefCell::new(connection), future).await)
+}
+
+
+
+pub fn write_anime_cache(anime_data: &HashMap<i32, AnimeInfo>) -> Result<(), rusqlite::Error> {
+    with_transaction(|transaction| replace_anime_cache(transaction, anime_data))
+}
+
+// add or replace some anime without rewriting the whole cache
+pub fn write_anime_info(anime: &Vec<AnimeInfo>) -> Result<(), rusqlite::Error> {
+    with_transaction(|transaction| {
+        let mut statement = transaction.prepare("INSERT OR REPLACE INTO anime_cache (media_id, data) VALUES (?1, ?2)")?;
+        for info in anime {
+            statement.execute(params![info.id, to_json(info)?])?;
+        }
+        Ok(())
+    })
+}
+
+pub fn read_anime_cache(anime_data: &mut HashMap<i32, AnimeInfo>) -> Result<(), rusqlite::Error> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare("SELECT media_id, data FROM anime_cache")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (media_id, data) = row?;
+            match serde_json::from_str(&data) {
+                Ok(anime) => { anime_data.insert(media_id, anime); },
+                Err(error) => println!("anime_cache {}: {}", media_id, error),
+            }
+        }
+        Ok(())
+    })
+}
+
+fn replace_anime_cache(transaction: &Transaction, anime_data: &HashMap<i32, AnimeInfo>) -> Result<(), rusqlite::Error> {
+    transaction.execute("DELETE FROM anime_cache", [])?;
+    let mut statement = transaction.prepare("INSERT INTO anime_cache (media_id, data) VALUES (?1, ?2)")?;
+    for (media_id, anime) in anime_data {
+        statement.execute(params![media_id, to_json(anime)?])?;
+    }
+    Ok(())
+}
+
+
+
+pub fn write_user_data(user_data: &HashMap<i32, UserInfo>) -> Result<(), rusqlite::Error> {
+    with_transaction(|transaction| replace_user_data(transaction, user_data))
+}
+
+// add or replace a single entry on the user's list
+pub fn write_user_entry(entry: &UserInfo) -> Result<(), rusqlite::Error> {
+    with_connection(|connection| {
+        connection.execute("INSERT OR REPLACE INTO user_entries (media_id, data) VALUES (?1, ?2)", params![entry.media_id, to_json(entry)?])?;
+        Ok(())
+    })
+}
+
+pub fn read_user_data(user_data: &mut HashMap<i32, UserInfo>) -> Result<(), rusqlite::Error> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare("SELECT media_id, data FROM user_entries")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (media_id, data) = row?;
+            match serde_json::from_str(&data) {
+                Ok(entry) => { user_data.insert(media_id, entry); },
+                Err(error) => println!("user_entries {}: {}", media_id, error),
+            }
+        }
+        Ok(())
+    })
+}
+
+fn replace_user_data(transaction: &Transaction, user_data: &HashMap<i32, UserInfo>) -> Result<(), rusqlite::Error> {
+    transaction.execute("DELETE FROM user_entries", [])?;
+    let mut statement = transaction.prepare("INSERT INTO user_entries (media_id, data) VALUES (?1, ?2)")?;
+    for (media_id, entry) in user_data {
+        statement.execute(params![media_id, to_json(entry)?])?;
+    }
+    Ok(())
+}
+
+
+
+pub fn write_user_lists(user_lists: &HashMap<String, Vec<i32>>) -> Result<(), rusqlite::Error> {
+    with_transaction(|transaction| replace_user_lists(transaction, user_lists))
+}
+
+pub fn read_user_lists(user_lists: &mut HashMap<String, Vec<i32>>) -> Result<(), rusqlite::Error> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare("SELECT list, media_id FROM user_lists ORDER BY list, position")?;
+        let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?)))?;
+        for row in rows {
+            let (list, media_id) = row?;
+            user_lists.entry(list).or_insert_with(Vec::new).push(media_id);
+        }
+        Ok(())
+    })
+}
+
+fn replace_user_lists(transaction: &Transaction, user_lists: &HashMap<String, Vec<i32>>) -> Result<(), rusqlite::Error> {
+    transaction.execute("DELETE FROM user_lists", [])?;
+    let mut statement = transaction.prepare("INSERT INTO user_lists (list, position, media_id) VALUES (?1, ?2, ?3)")?;
+    for (list, media_ids) in user_lists {
+        for (position, media_id) in media_ids.iter().enumerate() {
+            statement.execute(params![list, position as i64, media_id])?;
+        }
+    }
+    Ok(())
+}
+
+
+
+pub fn write_episode_paths(episode_path: &HashMap<i32, HashMap<i32, AnimePath>>) -> Result<(), rusqlite::Error> {
+    with_transaction(|transaction| replace_episode_paths(transaction, episode_path))
+}
+
+pub fn read_episode_paths(episode_path: &mut HashMap<i32, HashMap<i32, AnimePath>>) -> Result<(), rusqlite::Error> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare("SELECT media_id, episode, path, similarity_score FROM episode_paths")?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, AnimePath { path: row.get(2)?, similarity_score: row.get(3)? }))
+        })?;
+        for row in rows {
+            let (media_id, episode, path) = row?;
+            episode_path.entry(media_id).or_insert_with(HashMap::new).insert(episode, path);
+        }
+        Ok(())
+    })
+}
+
+fn replace_episode_paths(transaction: &Transaction, episode_path: &HashMap<i32, HashMap<i32, AnimePath>>) -> Result<(), rusqlite::Error> {
+    transaction.execute("DELETE FROM episode_paths", [])?;
+    let mut statement = transaction.prepare("INSERT INTO episode_paths (media_id, episode, path, similarity_score) VALUES (?1, ?2, ?3, ?4)")?;
+    for (media_id, episodes) in episode_path {
+        for (episode, path) in episodes {
+            statement.execute(params![media_id, episode, path.path, path.similarity_score])?;
+        }
+    }
+    Ok(())
+}
+
+
+
+pub fn write_known_files(known_files: &HashSet<u64>) -> Result<(), rusqlite::Error> {
+    with_transaction(|transaction| replace_known_files(transaction, known_files))
+}
+
+pub fn read_known_files(known_files: &mut HashSet<u64>) -> Result<(), rusqlite::Error> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare("SELECT hash FROM known_files")?;
+        let rows = statement.query_map([], |row| row.get::<_, i64>(0))?;
+        for row in rows {
+            known_files.insert(row? as u64);
+        }
+        Ok(())
+    })
+}
+
+// sqlite integers are signed, hashes are stored with the same bits
+fn replace_known_files(transaction: &Transaction, known_files: &HashSet<u64>) -> Result<(), rusqlite::Error> {
+    transaction.execute("DELETE FROM known_files", [])?;
+    let mut statement = transaction.prepare("INSERT INTO known_files (hash) VALUES (?1)")?;
+    for hash in known_files {
+        statement.execute(params![*hash as i64])?;
+    }
+    Ok(())
+}
+
+
+
+pub fn write_missing_ids(missing_ids: &HashSet<i32>) -> Result<(), rusqlite::Error> {
+    with_transaction(|transaction| replace_missing_ids(transaction, missing_ids))
+}
+
+pub fn read_missing_ids(missing_ids: &mut HashSet<i32>) -> Result<(), rusqlite::Error> {
+    with_connection(|connection| {
+        let mut statement = connection.prepare("SELECT media_id FROM missing_ids")?;
+        let rows = statement.query_map([], |row| row.get::<_, i32>(0))?;
+        for row in rows {
+            missing_ids.insert(row?);
+        }
+        Ok(())
+    })
+}
+
+fn replace_missing_ids(transaction: &Transaction, missing_ids: &HashSet<i32>) -> Result<(), rusqlite::Error> {
+    transaction.execute("DELETE FROM missing_ids", [])?;
+    let mut statement = transaction.prepare("INSERT INTO missing_ids (media_id) VALUES (?1)")?;
+    for media_id in missing_ids {
+        statement.execute(params![media_id])?;
+    }
+    Ok(())
+}
+
+
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, rusqlite::Error> {
+    serde_json::to_string(value).map_err(|error| rusqlite::Error::ToSqlConversionFailure(Box::new(error)))
+}
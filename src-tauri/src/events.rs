@@ -0,0 +1,87 @@
+use async_trait::async_trait;
+use serde::{Serialize, Deserialize};
+use crate::user_data::UserInfo;
+
+
+
+// things that happen away from the ui that it may need to show
+// the payload is sent to the ui as is, the variant is given by the event's name
+#[derive(Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum UiEvent {
+    ListChanged, // anime were added, removed, or moved on the user's list
+    EntryUpdated(UserInfo), // the user's entry for a anime was saved
+    ScanProgress(ScanData),
+    TrackingStarted(i32), // a episode of this anime is being watched in a media player
+    TrackingStopped(i32), // the episode was closed, finished, or cancelled
+    Error(String),
+    Connectivity(bool), // true when the website could be reached
+    QueuedUpdates(usize), // number of changes waiting to be sent to the website
+    SyncConflicts(usize),
+    Reauthorize(bool), // the login was rejected and the user needs to log in again
+    Loading(Option<String>), // message for the loading dialog, None closes it
+    FilesRecovered(RecoveryReport), // saved files that were damaged when they were read
+}
+
+impl UiEvent {
+
+    // name the ui listens for
+    pub fn name(&self) -> &'static str {
+        match self {
+            UiEvent::ListChanged => "list-changed",
+            UiEvent::EntryUpdated(_) => "entry-updated",
+            UiEvent::ScanProgress(_) => "scan-progress",
+            UiEvent::TrackingStarted(_) => "tracking-started",
+            UiEvent::TrackingStopped(_) => "tracking-stopped",
+            UiEvent::Error(_) => "error",
+            UiEvent::Connectivity(_) => "connectivity-changed",
+            UiEvent::QueuedUpdates(_) => "queued-updates",
+            UiEvent::SyncConflicts(_) => "sync-conflicts",
+            UiEvent::Reauthorize(_) => "reauthorize",
+            UiEvent::Loading(_) => "loading",
+            UiEvent::FilesRecovered(_) => "files-recovered",
+        }
+    }
+}
+
+// files that were restored from their backup, and files whose backup couldn't be read either
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RecoveryReport {
+    pub recovered: Vec<String>,
+    pub lost: Vec<String>,
+}
+
+// progress of a folder scan, total_folders is 0 when no scan is running
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ScanData {
+    pub current_folder: i32,
+    pub total_folders: i32,
+    pub completed_chunks: i32,
+    pub total_chunks: i32,
+}
+
+impl ScanData {
+
+    pub fn clear(&mut self) {
+        self.current_folder = 0;
+        self.total_folders = 0;
+        self.completed_chunks = 0;
+        self.total_chunks = 0;
+    }
+}
+
+
+
+// receives events from user data, anime data, and file operations so they don't need to know about the ui
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    async fn send(&self, event: UiEvent);
+}
+
+// ignores every event, used when there is no ui to tell
+pub struct NoEvents;
+
+#[async_trait]
+impl EventSink for NoEvents {
+    async fn send(&self, _event: UiEvent) {}
+}
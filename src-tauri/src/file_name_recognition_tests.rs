@@ -1,7 +1,8 @@
 use std::{path::Path, fs::File, io::Read};
 use serde::{Serialize, Deserialize};
+use tauri::async_runtime::Mutex;
 
-use crate::{GLOBAL_ANIME_DATA, anime_data::IdentifyInfo};
+use crate::anime_data::{AnimeData, IdentifyInfo};
 
 
 
@@ -25,7 +26,7 @@ pub struct FilenameTest {
 
 
 
-pub async fn filename_tests() -> Vec<FilenameTest> {
+pub async fn filename_tests(anime_data: &Mutex<AnimeData>) -> Vec<FilenameTest> {
 
     let file_path = Path::new("filename_tests.json");
 
@@ -46,7 +47,7 @@ pub async fn filename_tests() -> Vec<FilenameTest> {
         Ok(file) => file,
     };
     
-    let mut anime_data = GLOBAL_ANIME_DATA.lock().await;
+    let mut anime_data = anime_data.lock().await;
     let filenames_values: Vec<serde_json::Value> = serde_json::from_str(&buffer).unwrap();
     let mut test_results: Vec<FilenameTest> = Vec::new();
     let mut anime_ids: Vec<i32> = Vec::new();
@@ -1,16 +1,21 @@
 use std::collections::{HashMap, HashSet};
-use std::fs::{self, create_dir};
+use std::fmt;
+use std::fs::{self, create_dir_all};
 use std::fs::File;
-use std::io::{Write, Read};
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use tauri::async_runtime::Mutex;
 
 use crate::anime_data::{AnimeInfo, AnimePath};
+use crate::data_archive::DataArchive;
+use crate::database::{self, JsonData};
+use crate::schema;
 use crate::api_calls::TokenData;
-use crate::user_data::{UserSettings, UserInfo};
-use crate::GLOBAL_REFRESH_UI;
+use crate::user_data::{SyncState, UserSettings, UserInfo};
+use crate::watch_history::WatchRecord;
+use crate::events::{EventSink, RecoveryReport, UiEvent};
+use crate::WatchingTracking;
 
 extern crate dirs;
 
@@ -20,188 +25,363 @@ const GEKIJOU_FOLDER: &str = "Gekijou_debug";
 #[cfg(not(debug_assertions))]
 const GEKIJOU_FOLDER: &str = "Gekijou";
 
-pub async fn write_file_token_data(token: &TokenData) {
-    write_file_data(&token, "token");
+
+
+// why data couldn't be saved or loaded
+#[derive(Debug)]
+pub enum PersistError {
+    NoConfigFolder,
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Database(rusqlite::Error),
+    NewerVersion(u64), // the file was saved by a newer version of gekijou
 }
 
-pub async fn read_file_token_data(token: &mut TokenData) -> Result<(), &'static str> {
-    read_file_data(token, "token").await
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PersistError::NoConfigFolder => write!(f, "config folder not found"),
+            PersistError::Io(error) => write!(f, "{}", error),
+            PersistError::Json(error) => write!(f, "invalid json, {}", error),
+            PersistError::Database(error) => write!(f, "database, {}", error),
+            PersistError::NewerVersion(version) => write!(f, "saved by a newer version of gekijou (format {})", version),
+        }
+    }
 }
 
-pub async fn write_file_user_settings(settings: &UserSettings) {
-    write_file_data(&settings, "user_settings");
+impl From<std::io::Error> for PersistError {
+    fn from(error: std::io::Error) -> Self {
+        PersistError::Io(error)
+    }
 }
 
-pub async fn read_file_user_settings(settings: &mut UserSettings) -> Result<(), &'static str> {
-    read_file_data(settings, "user_settings").await
+impl From<serde_json::Error> for PersistError {
+    fn from(error: serde_json::Error) -> Self {
+        PersistError::Json(error)
+    }
 }
 
-pub async fn write_file_user_data(user_data: &HashMap<i32, UserInfo>) {
-    write_file_data(&user_data, "user_data");
+impl From<rusqlite::Error> for PersistError {
+    fn from(error: rusqlite::Error) -> Self {
+        PersistError::Database(error)
+    }
 }
 
-pub async fn read_file_user_data(user_data: &mut HashMap<i32, UserInfo>) -> Result<(), &'static str> {
-    read_file_data(user_data, "user_data").await
+
+pub async fn write_file_token_data(events: &dyn EventSink, token: &TokenData) -> Result<(), PersistError> {
+    report_write(events, write_file_data(&token, "token"), "token").await
 }
 
-pub async fn write_file_user_lists(user_lists: &HashMap<String, Vec<i32>>) {
-    write_file_data(&user_lists, "user_lists");
+pub async fn read_file_token_data(events: &dyn EventSink, token: &mut TokenData) -> Result<(), PersistError> {
+    read_file_data(events, token, "token").await
 }
 
-pub async fn read_file_user_lists(user_lists: &mut HashMap<String, Vec<i32>>) -> Result<(), &'static str> {
-    read_file_data(user_lists, "user_lists").await
+pub async fn write_file_user_settings(events: &dyn EventSink, settings: &UserSettings) -> Result<(), PersistError> {
+    report_write(events, write_file_data(&settings, "user_settings"), "user_settings").await
 }
 
-pub async fn write_file_update_queue(update_queue: &Vec<UserInfo>) {
-    write_file_data(&update_queue, "update_queue");
+pub async fn read_file_user_settings(events: &dyn EventSink, settings: &mut UserSettings) -> Result<(), PersistError> {
+    read_file_data(events, settings, "user_settings").await
 }
 
-pub async fn read_file_update_queue(update_queue: &mut Vec<UserInfo>) -> Result<(), &'static str> {
-    read_file_data(update_queue, "update_queue").await
+pub async fn write_file_user_data(events: &dyn EventSink, user_data: &HashMap<i32, UserInfo>) -> Result<(), PersistError> {
+    report_write(events, database::write_user_data(user_data).map_err(PersistError::from), "user_data").await
 }
 
-pub async fn write_file_anime_info_cache(anime_data: &HashMap<i32, AnimeInfo>) {
-    write_file_data(&anime_data, "anime_cache");
+pub async fn read_file_user_data(events: &dyn EventSink, user_data: &mut HashMap<i32, UserInfo>) -> Result<(), PersistError> {
+    read_database_data(events, database::read_user_data(user_data), "user_data").await
 }
 
-pub async fn read_file_anime_info_cache(anime_data: &mut HashMap<i32, AnimeInfo>) -> Result<(), &'static str> {
-    read_file_data(anime_data, "anime_cache").await
+// add or replace a single entry without rewriting all the user's data
+pub async fn write_user_entry(events: &dyn EventSink, entry: &UserInfo) -> Result<(), PersistError> {
+    report_write(events, database::write_user_entry(entry).map_err(PersistError::from), "user_data").await
 }
 
-pub async fn write_file_anime_missing_ids(missing_ids: &HashSet<i32>) {
-    write_file_data(&missing_ids, "404_ids");
+pub async fn write_file_user_lists(events: &dyn EventSink, user_lists: &HashMap<String, Vec<i32>>) -> Result<(), PersistError> {
+    report_write(events, database::write_user_lists(user_lists).map_err(PersistError::from), "user_lists").await
 }
 
-pub async fn read_file_anime_missing_ids(missing_ids: &mut HashSet<i32>) -> Result<(), &'static str> {
-    read_file_data(missing_ids, "404_ids").await
+pub async fn read_file_user_lists(events: &dyn EventSink, user_lists: &mut HashMap<String, Vec<i32>>) -> Result<(), PersistError> {
+    read_database_data(events, database::read_user_lists(user_lists), "user_lists").await
 }
 
-pub async fn write_file_episode_path(episode_path: &HashMap<i32, HashMap<i32,AnimePath>>) {
-    write_file_data(&episode_path, "episode_path");
+pub async fn write_file_update_queue(events: &dyn EventSink, update_queue: &Vec<UserInfo>) -> Result<(), PersistError> {
+    report_write(events, write_file_data(&update_queue, "update_queue"), "update_queue").await
 }
 
-pub async fn read_file_episode_path(episode_path: &mut HashMap<i32, HashMap<i32,AnimePath>>) -> Result<(), &'static str> {
-    read_file_data(episode_path, "episode_path").await
+pub async fn read_file_update_queue(events: &dyn EventSink, update_queue: &mut Vec<UserInfo>) -> Result<(), PersistError> {
+    read_file_data(events, update_queue, "update_queue").await
 }
 
-pub async fn write_file_known_files(known_files: &HashSet<u64>) {
-    write_file_data(&known_files, "known_files");
+pub async fn write_file_sync_state(events: &dyn EventSink, sync_state: &SyncState) -> Result<(), PersistError> {
+    report_write(events, write_file_data(&sync_state, "sync_state"), "sync_state").await
 }
 
-pub async fn read_file_known_files(known_files: &mut HashSet<u64>) -> Result<(), &'static str> {
-    read_file_data(known_files, "known_files").await
+pub async fn read_file_sync_state(events: &dyn EventSink, sync_state: &mut SyncState) -> Result<(), PersistError> {
+    read_file_data(events, sync_state, "sync_state").await
 }
 
+pub async fn write_file_watching_tracking(events: &dyn EventSink, watching_tracking: &HashMap<i32, WatchingTracking>) -> Result<(), PersistError> {
+    report_write(events, write_file_data(&watching_tracking, "watching_tracking"), "watching_tracking").await
+}
 
+pub async fn read_file_watching_tracking(events: &dyn EventSink, watching_tracking: &mut HashMap<i32, WatchingTracking>) -> Result<(), PersistError> {
+    read_file_data(events, watching_tracking, "watching_tracking").await
+}
 
-async fn write_file_data_mutex<T: Serialize>(global: &Mutex<T>, filename: &str) {
-    write_file_data(&*global.lock().await, filename);
+pub async fn write_file_watch_history(events: &dyn EventSink, watch_history: &Vec<WatchRecord>) -> Result<(), PersistError> {
+    report_write(events, write_file_data(&watch_history, "watch_history"), "watch_history").await
 }
 
-// writes all held data on anime to a file
-pub fn write_file_data<T: Serialize>(global: &T, filename: &str) {
-    
-    let file_location = format!("{}/{}/{}.json", dirs::config_dir().unwrap().to_str().unwrap(), GEKIJOU_FOLDER, filename);
-    let file_backup_location = format!("{}/{}/{}_backup.json", dirs::config_dir().unwrap().to_str().unwrap(), GEKIJOU_FOLDER, filename);
-    let file_path = Path::new(&file_location);
-    let file_backup_path = Path::new(&file_backup_location);
-    let mut file: File;
-
-    // backup file before replacing it
-    if file_path.exists() {
-
-        // remove backup so we can make a new one
-        if file_backup_path.exists() {
-            match fs::remove_file(file_backup_path) {
-                Err(why) => panic!("unable to remove, {}", why),
-                Ok(file) => file,
-            };
-        }
+pub async fn read_file_watch_history(events: &dyn EventSink, watch_history: &mut Vec<WatchRecord>) -> Result<(), PersistError> {
+    read_file_data(events, watch_history, "watch_history").await
+}
+
+// add or replace some anime without rewriting the whole cache
+pub async fn write_anime_info(events: &dyn EventSink, anime: &Vec<AnimeInfo>) -> Result<(), PersistError> {
+    report_write(events, database::write_anime_info(anime).map_err(PersistError::from), "anime_cache").await
+}
 
-        // change file into backup file
-        match fs::rename(file_path, file_backup_path) {
-            Err(why) => panic!("unable to move, {}", why),
-            Ok(file) => file,
-        };
+pub async fn write_file_anime_info_cache(events: &dyn EventSink, anime_data: &HashMap<i32, AnimeInfo>) -> Result<(), PersistError> {
+    report_write(events, database::write_anime_cache(anime_data).map_err(PersistError::from), "anime_cache").await
+}
+
+pub async fn read_file_anime_info_cache(events: &dyn EventSink, anime_data: &mut HashMap<i32, AnimeInfo>) -> Result<(), PersistError> {
+    read_database_data(events, database::read_anime_cache(anime_data), "anime_cache").await
+}
+
+pub async fn write_file_anime_missing_ids(events: &dyn EventSink, missing_ids: &HashSet<i32>) -> Result<(), PersistError> {
+    report_write(events, database::write_missing_ids(missing_ids).map_err(PersistError::from), "404_ids").await
+}
+
+pub async fn read_file_anime_missing_ids(events: &dyn EventSink, missing_ids: &mut HashSet<i32>) -> Result<(), PersistError> {
+    read_database_data(events, database::read_missing_ids(missing_ids), "404_ids").await
+}
+
+pub async fn write_file_episode_path(events: &dyn EventSink, episode_path: &HashMap<i32, HashMap<i32,AnimePath>>) -> Result<(), PersistError> {
+    report_write(events, database::write_episode_paths(episode_path).map_err(PersistError::from), "episode_path").await
+}
+
+pub async fn read_file_episode_path(events: &dyn EventSink, episode_path: &mut HashMap<i32, HashMap<i32,AnimePath>>) -> Result<(), PersistError> {
+    read_database_data(events, database::read_episode_paths(episode_path), "episode_path").await
+}
+
+pub async fn write_file_known_files(events: &dyn EventSink, known_files: &HashSet<u64>) -> Result<(), PersistError> {
+    report_write(events, database::write_known_files(known_files).map_err(PersistError::from), "known_files").await
+}
+
+pub async fn read_file_known_files(events: &dyn EventSink, known_files: &mut HashSet<u64>) -> Result<(), PersistError> {
+    read_database_data(events, database::read_known_files(known_files), "known_files").await
+}
+
+
+
+// opens the database that stores the anime cache, user's list, and scanned files
+// the first time it's opened the data from the json files that used to store them is moved into it
+pub async fn open_database(events: &dyn EventSink) {
+
+    let folder = match gekijou_folder() {
+        Ok(folder) => folder,
+        Err(error) => {
+            println!("open_database: {}", error);
+            events.send(UiEvent::Error(String::from("Can't open database"))).await;
+            return;
+        },
+    };
+
+    match database::open(&folder) {
+        Ok(true) => {
+            let mut data = JsonData::default();
+            read_file_data(events, &mut data.anime_cache, "anime_cache").await.ok();
+            read_file_data(events, &mut data.user_data, "user_data").await.ok();
+            read_file_data(events, &mut data.user_lists, "user_lists").await.ok();
+            read_file_data(events, &mut data.episode_path, "episode_path").await.ok();
+            read_file_data(events, &mut data.known_files, "known_files").await.ok();
+            read_file_data(events, &mut data.missing_ids, "404_ids").await.ok();
+            if let Err(error) = database::migrate_json(data) {
+                println!("migrate_json: {}", error);
+                events.send(UiEvent::Error(String::from("Can't move data into the database"))).await;
+            }
+        },
+        Ok(false) => {},
+        Err(error) => {
+            println!("open_database: {}", error);
+            events.send(UiEvent::Error(String::from("Can't open database"))).await;
+        },
+    }
+}
+
+// reports errors from reading the database the same way as errors from reading files
+async fn read_database_data(events: &dyn EventSink, result: Result<(), rusqlite::Error>, name: &str) -> Result<(), PersistError> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            println!("{}: {}", name, error);
+            events.send(UiEvent::Error(String::from(name.to_owned() + " Can't read database"))).await;
+            Err(PersistError::from(error))
+        },
     }
+}
+
+// tells the user when something couldn't be saved, the error is still returned so the caller can react to it
+async fn report_write(events: &dyn EventSink, result: Result<(), PersistError>, name: &str) -> Result<(), PersistError> {
+    if let Err(error) = &result {
+        println!("{}: {}", name, error);
+        events.send(UiEvent::Error(format!("{} Can't be saved: {}", name, error))).await;
+    }
+    result
+}
+
 
-    if gekijou_folder_exists_or_created() {
 
-        // create the file
-        file = match File::create(file_path) {
-            Err(why) => panic!("unable to open, {}", why),
-            Ok(file) => file,
-        };
+// writes all held data to a file with a schema version header, the previous file is kept as a backup
+// the file is written next to the original and moved over it so a crash can't leave it half written
+pub fn write_file_data<T: Serialize>(global: &T, filename: &str) -> Result<(), PersistError> {
 
-        // write contents into file
-        match file.write_all(serde_json::to_string(global).unwrap().as_bytes()) {
-            Err(why) => panic!("ERROR: {}", why),
-            Ok(file) => file,
-        };
+    let folder = gekijou_folder()?;
+    let file_path = folder.join(format!("{}.json", filename));
+    let file_backup_path = folder.join(format!("{}_backup.json", filename));
+
+    let contents = schema::to_versioned_json(global)?;
+
+    // backup file before replacing it, a damaged file shouldn't replace a good backup
+    if let Ok(previous) = fs::read(&file_path) {
+        if serde_json::from_slice::<serde_json::Value>(&previous).is_ok() {
+            write_file_atomic(&file_backup_path, &previous)?;
+        }
     }
+
+    write_file_atomic(&file_path, &contents)
 }
 
+// write to a temporary file, flush it to disk, then rename it over the destination
+fn write_file_atomic(file_path: &Path, contents: &[u8]) -> Result<(), PersistError> {
+
+    let temp_path = file_path.with_extension("json.tmp");
+    let mut file = File::create(&temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
 
+    fs::rename(&temp_path, file_path)?;
 
-async fn read_file_data_mutex<T: DeserializeOwned>(global: &Mutex<T>, filename: &str) -> Result<(), &'static str> {
-    read_file_data(&mut *global.lock().await, filename).await
+    // the rename is only durable once the folder is flushed, windows doesn't allow opening folders
+    #[cfg(unix)]
+    if let Some(folder) = file_path.parent() {
+        File::open(folder)?.sync_all()?;
+    }
+
+    Ok(())
 }
-// reads all stored data from a file into the global collection
-pub async fn read_file_data<T: DeserializeOwned>(global: &mut T, filename: &str) -> Result<(), &'static str> {
 
-    let file_location = format!("{}/{}/{}.json", dirs::config_dir().unwrap().to_str().unwrap(), GEKIJOU_FOLDER, filename);
-    let file_path = Path::new(&file_location);
-    
-    if file_path.exists() {
-
-        // open the file
-        let mut file = match File::open(&file_path) {
-            Err(_why) => {
-                GLOBAL_REFRESH_UI.lock().await.errors.push(String::from(filename.to_owned() + " Can't open file"));
-                return Err("Can't open file");
-            },
-            Ok(file) => file,
-        };
+
+
+// reads all stored data from a file into the collection, problems reading it are sent to events
+// if the file is missing or damaged the backup is used instead and becomes the file again
+pub async fn read_file_data<T: DeserializeOwned>(events: &dyn EventSink, global: &mut T, filename: &str) -> Result<(), PersistError> {
+
+    let folder = gekijou_folder()?;
+    let file_path = folder.join(format!("{}.json", filename));
+    let file_backup_path = folder.join(format!("{}_backup.json", filename));
     
-        // read all data out of the file
-        let mut buffer = String::new();
-        match file.read_to_string(&mut buffer) {
-            Err(_why) => {
-                GLOBAL_REFRESH_UI.lock().await.errors.push(String::from(filename.to_owned() + " Can't read file"));
-                return Err("Can't open file");
-            },
-            Ok(file) => file,
-        };
-        
-        match serde_json::from_str(&buffer) {
-            Ok(result) => {
-                *global = result;
-            },
-            Err(_error) => {
-                GLOBAL_REFRESH_UI.lock().await.errors.push(String::from(filename.to_owned() + " Can't process json"));
-            },
-        }
+    if file_path.exists() == false && file_backup_path.exists() == false {
+        return Ok(());
     }
 
-    Ok(())
+    let error = match read_json(&file_path, filename) {
+        Ok(result) => {
+            *global = result;
+            return Ok(());
+        },
+        Err(error) => error,
+    };
+    println!("{}: {}", filename, error);
+
+    // the file isn't damaged, restoring an older backup over it would lose data
+    if let PersistError::NewerVersion(_) = error {
+        events.send(UiEvent::Error(format!("{} Can't be read: {}", filename, error))).await;
+        return Err(error);
+    }
+
+    match read_json(&file_backup_path, filename) {
+        Ok(result) => {
+            *global = result;
+            if let Ok(backup) = fs::read(&file_backup_path) {
+                write_file_atomic(&file_path, &backup).ok();
+            }
+            events.send(UiEvent::FilesRecovered(RecoveryReport { recovered: vec![filename.to_string()], lost: Vec::new() })).await;
+            Ok(())
+        },
+        Err(_backup_error) => {
+            events.send(UiEvent::FilesRecovered(RecoveryReport { recovered: Vec::new(), lost: vec![filename.to_string()] })).await;
+            Err(error)
+        },
+    }
 }
 
+// files saved by older versions are upgraded to the current format as they're read
+fn read_json<T: DeserializeOwned>(file_path: &Path, filename: &str) -> Result<T, PersistError> {
+    let buffer = fs::read_to_string(file_path)?;
+    schema::from_versioned_json(filename, &buffer)
+}
 
 
 
-// checks if gekijou folder exists, if it does not exist it will try to create it
-// returns true if the folder exists
-fn gekijou_folder_exists_or_created() -> bool {
+// save a archive of the user's data to a file the user picked
+pub fn write_archive(archive: &DataArchive, path: &str) -> Result<(), PersistError> {
+    write_file_atomic(Path::new(path), &schema::to_versioned_json(archive)?)
+}
 
-    let folder = dirs::config_dir().unwrap().join(GEKIJOU_FOLDER);
-    if folder.exists() {
-        true
-    } else {
-        match create_dir(folder) {
-            Err(why) => panic!("ERROR: {}", why),
-            Ok(_e) => true,
-        }
+// read a archive exported by this or a older version of gekijou
+pub fn read_archive(path: &str) -> Result<DataArchive, PersistError> {
+    let buffer = fs::read_to_string(path)?;
+    schema::from_versioned_json("archive", &buffer)
+}
+
+
+
+// save a exported list to a file the user picked
+pub fn write_export(path: &str, contents: &str) -> Result<(), PersistError> {
+    write_file_atomic(Path::new(path), contents.as_bytes())
+}
+
+pub fn read_export(path: &str) -> Result<String, PersistError> {
+    Ok(fs::read_to_string(path)?)
+}
+
+
+
+#[cfg(debug_assertions)]
+tokio::task_local! {
+    // set by with_test_storage
+    static TEST_FOLDER: PathBuf;
+}
+
+// runs the future with files saved to a temporary folder and the database kept in memory so the user's data isn't touched
+// used by fixture tests that go through UserData, the folder is deleted afterwards
+#[cfg(debug_assertions)]
+pub async fn with_test_storage<F: std::future::Future>(future: F) -> Result<F::Output, PersistError> {
+
+    let folder = std::env::temp_dir().join(format!("gekijou_test_{}", rand::random::<u32>()));
+    create_dir_all(&folder)?;
+    let result = TEST_FOLDER.scope(folder.clone(), database::with_test_database(future)).await;
+    fs::remove_dir_all(&folder).ok();
+    Ok(result?)
+}
+
+// gekijou's folder in the config directory, it's created if it doesn't exist
+fn gekijou_folder() -> Result<PathBuf, PersistError> {
+
+    #[cfg(debug_assertions)]
+    if let Ok(folder) = TEST_FOLDER.try_with(|folder| folder.clone()) {
+        return Ok(folder);
+    }
+
+    let folder = dirs::config_dir().ok_or(PersistError::NoConfigFolder)?.join(GEKIJOU_FOLDER);
+    if folder.exists() == false {
+        create_dir_all(&folder)?;
     }
+    Ok(folder)
 }
 
 
@@ -211,7 +391,11 @@ pub fn delete_data() -> bool {
 
     let files = vec!["token","token_backup","user_settings","user_settings_backup","anime_cache","anime_cache_backup",
                                 "user_data","user_data_backup","user_lists","user_lists_backup","episode_path","episode_path_backup",
-                                "update_queue","update_queue_backup","known_files","known_files_backup","404_ids","404_ids_backup"];
+                                "update_queue","update_queue_backup","known_files","known_files_backup","404_ids","404_ids_backup","watching_tracking","watching_tracking_backup","watch_history","watch_history_backup","sync_state","sync_state_backup"];
+
+    if database::delete(&dirs::config_dir().unwrap().join(GEKIJOU_FOLDER)) == false {
+        return false;
+    }
 
     for file in files {
 
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+#[cfg(debug_assertions)]
+use std::{collections::VecDeque, sync::Mutex};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::{constants, http_client::{self, ApiError}};
+
+
+
+lazy_static! {
+    static ref DEFAULT_CLIENT: Arc<dyn GraphQlClient> = Arc::new(HttpGraphQlClient::from_env());
+}
+
+// the client used to reach anilist when nothing else was given
+pub fn default_client() -> Arc<dyn GraphQlClient> {
+    DEFAULT_CLIENT.clone()
+}
+
+
+
+// sends graphql queries to anilist and returns the response text
+#[async_trait]
+pub trait GraphQlClient: Send + Sync {
+
+    // access token in necessary for creating, updating, deleting, and reading private data
+    async fn post(&self, json: &Value, access_token: Option<&String>) -> Result<String, ApiError>;
+}
+
+
+
+// sends queries over the internet
+pub struct HttpGraphQlClient {
+    endpoint: String,
+}
+
+impl HttpGraphQlClient {
+
+    pub fn new(endpoint: String) -> HttpGraphQlClient {
+        HttpGraphQlClient { endpoint }
+    }
+
+    // debug builds can change the endpoint with a environment variable to use a local server instead of anilist
+    pub fn from_env() -> HttpGraphQlClient {
+        #[cfg(debug_assertions)]
+        if let Ok(endpoint) = std::env::var(constants::ANILIST_URL_VARIABLE) {
+            if endpoint.is_empty() == false {
+                return HttpGraphQlClient::new(endpoint);
+            }
+        }
+        HttpGraphQlClient::new(String::from(constants::ANILIST_GRAPHQL_URL))
+    }
+}
+
+#[async_trait]
+impl GraphQlClient for HttpGraphQlClient {
+
+    async fn post(&self, json: &Value, access_token: Option<&String>) -> Result<String, ApiError> {
+
+        let mut request_builder = http_client::CLIENT.post(&self.endpoint);
+        if access_token.is_some() {
+            request_builder = request_builder.header("Authorization", String::from("Bearer ") + access_token.unwrap());
+        }
+
+        let request_builder = request_builder.header("Content-Type", "application/json")
+            .header("Accept", "application/json")
+            .body(json.to_string());
+
+        let response = http_client::send_with_retry(&http_client::ANILIST_LIMITER, request_builder).await?;
+        let response_string = match response.text().await {
+            Ok(response_string) => response_string,
+            Err(_error) => return Err(ApiError::Other),
+        };
+        if access_token.is_some() && response_string.contains("\"Invalid token\"") {
+            println!("invalid token");
+            return Err(ApiError::Unauthorized)
+        }
+        Ok(response_string)
+    }
+}
+
+
+
+// a query that was sent to a fake client
+#[cfg(debug_assertions)]
+#[derive(Debug, Clone)]
+pub struct FakeRequest {
+    pub json: Value,
+    pub access_token: Option<String>,
+}
+
+// answers queries with recorded responses in the order they were added, used to test without a connection
+// once the responses run out every query fails as if there was no internet, only built for debug builds
+#[cfg(debug_assertions)]
+pub struct FakeGraphQlClient {
+    responses: Mutex<VecDeque<String>>,
+    requests: Mutex<Vec<FakeRequest>>,
+}
+
+#[cfg(debug_assertions)]
+impl FakeGraphQlClient {
+
+    pub fn new(responses: Vec<String>) -> FakeGraphQlClient {
+        FakeGraphQlClient { responses: Mutex::new(responses.into()), requests: Mutex::new(Vec::new()) }
+    }
+
+    // every query received so far, oldest first
+    pub fn requests(&self) -> Vec<FakeRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[cfg(debug_assertions)]
+#[async_trait]
+impl GraphQlClient for FakeGraphQlClient {
+
+    async fn post(&self, json: &Value, access_token: Option<&String>) -> Result<String, ApiError> {
+
+        self.requests.lock().unwrap().push(FakeRequest { json: json.clone(), access_token: access_token.cloned() });
+        match self.responses.lock().unwrap().pop_front() {
+            Some(response) => Ok(response),
+            None => Err(ApiError::Offline),
+        }
+    }
+}
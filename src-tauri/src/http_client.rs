@@ -0,0 +1,197 @@
+use std::{fmt, time::{Duration, Instant}};
+
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use tauri::async_runtime::Mutex;
+
+use crate::constants;
+
+
+
+lazy_static! {
+    // one client for every request so connections are reused
+    pub static ref CLIENT: Client = Client::new();
+    pub static ref ANILIST_LIMITER: Mutex<RateLimiter> = Mutex::new(RateLimiter::new(constants::ANILIST_REQUESTS_PER_MINUTE));
+    pub static ref MAL_LIMITER: Mutex<RateLimiter> = Mutex::new(RateLimiter::new(constants::MAL_REQUESTS_PER_MINUTE));
+}
+
+
+
+// why a request to a website failed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApiError {
+    Offline, // the website couldn't be reached
+    RateLimited(u64), // too many requests, seconds until requests are accepted again
+    Unauthorized, // the access token expired or was revoked
+    NotFound,
+    Server(u16), // the website had a problem, the status code it returned
+    BadRequest, // the request couldn't be sent
+    Rejected, // the website understood the request but refused it
+    InvalidResponse, // the response didn't have the expected shape
+    Other,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::RateLimited(seconds) => write!(f, "rate limited for {} seconds", seconds),
+            ApiError::Server(status) => write!(f, "website returned {}", status),
+            error => write!(f, "{}", <&'static str>::from(*error)),
+        }
+    }
+}
+
+// the rest of the program passes errors as messages, these are the messages it checks for
+impl From<ApiError> for &'static str {
+    fn from(error: ApiError) -> Self {
+        match error {
+            ApiError::Offline => "no connection",
+            ApiError::RateLimited(_) => "rate limited",
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::NotFound => "not found",
+            ApiError::Server(_) => "server error",
+            ApiError::BadRequest => "bad request",
+            ApiError::Rejected => "anilist returned error",
+            ApiError::InvalidResponse => "Value was not returned",
+            ApiError::Other => "error",
+        }
+    }
+}
+
+impl From<ApiError> for String {
+    fn from(error: ApiError) -> Self {
+        error.to_string()
+    }
+}
+
+
+
+// token bucket, a request takes a token and tokens refill at the rate the website allows
+pub struct RateLimiter {
+    capacity: f64,
+    tokens: f64,
+    per_second: f64,
+    last_refill: Instant,
+    blocked_until: Option<Instant>, // the website asked for no requests until this time
+}
+
+impl RateLimiter {
+
+    pub fn new(requests_per_minute: f64) -> RateLimiter {
+        RateLimiter { capacity: requests_per_minute, tokens: requests_per_minute, per_second: requests_per_minute / 60.0, last_refill: Instant::now(), blocked_until: None }
+    }
+
+    // take a token if one is available, otherwise returns how long to wait before trying again
+    fn try_acquire(&mut self) -> Result<(), Duration> {
+
+        let now = Instant::now();
+        if let Some(blocked_until) = self.blocked_until {
+            if blocked_until > now {
+                return Err(blocked_until - now);
+            }
+            self.blocked_until = None;
+        }
+
+        self.tokens = (self.tokens + now.duration_since(self.last_refill).as_secs_f64() * self.per_second).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.per_second))
+        }
+    }
+
+    // the website says how many requests are left, don't send more than that
+    fn update_remaining(&mut self, remaining: f64) {
+        self.tokens = self.tokens.min(remaining);
+    }
+
+    fn block_for(&mut self, duration: Duration) {
+        self.blocked_until = Some(Instant::now() + duration);
+        self.tokens = 0.0;
+    }
+}
+
+// wait until the limiter allows another request
+async fn acquire(limiter: &Mutex<RateLimiter>) {
+    loop {
+        let wait = match limiter.lock().await.try_acquire() {
+            Ok(()) => return,
+            Err(wait) => wait,
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
+
+
+
+// send a request, waiting for the rate limit and retrying when the website is busy or can't be reached
+// requests are retried with a increasing delay, the error from the last attempt is returned if none succeed
+pub async fn send_with_retry(limiter: &Mutex<RateLimiter>, request: RequestBuilder) -> Result<Response, ApiError> {
+
+    let mut delay = Duration::from_secs(constants::RETRY_BASE_DELAY);
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let last_attempt = attempt > constants::MAX_RETRIES;
+
+        // requests with a streamed body can't be sent again
+        let attempt_request = match request.try_clone() {
+            Some(attempt_request) => attempt_request,
+            None => return Err(ApiError::BadRequest),
+        };
+
+        acquire(limiter).await;
+        let error = match attempt_request.send().await {
+            Ok(response) => {
+                if let Some(remaining) = header_number(&response, "X-RateLimit-Remaining") {
+                    limiter.lock().await.update_remaining(remaining);
+                }
+
+                let status = response.status();
+                if status == StatusCode::TOO_MANY_REQUESTS {
+                    let retry_after = header_number(&response, "Retry-After").map_or(constants::RATE_LIMIT_DEFAULT_WAIT, |seconds| seconds as u64);
+                    println!("rate limited for {} seconds", retry_after);
+                    limiter.lock().await.block_for(Duration::from_secs(retry_after));
+                    ApiError::RateLimited(retry_after)
+                } else if status.is_server_error() {
+                    println!("server error {}", status);
+                    ApiError::Server(status.as_u16())
+                } else if status == StatusCode::UNAUTHORIZED { // access token has expired or was revoked
+                    println!("unauthorized");
+                    return Err(ApiError::Unauthorized);
+                } else {
+                    // other errors like not found are described in the body, the caller decides what to do with them
+                    return Ok(response);
+                }
+            },
+            Err(error) => {
+                if error.is_connect() || error.is_timeout() { // no internet
+                    println!("connect error");
+                    ApiError::Offline
+                } else if error.is_request() {
+                    println!("request error");
+                    return Err(ApiError::BadRequest);
+                } else {
+                    println!("{:?}", error);
+                    return Err(ApiError::Other);
+                }
+            },
+        };
+
+        if last_attempt {
+            return Err(error);
+        }
+        // the rate limiter already waits for the time the website asked for
+        if let ApiError::RateLimited(_) = error {
+            continue;
+        }
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(Duration::from_secs(constants::RETRY_MAX_DELAY));
+    }
+}
+
+fn header_number(response: &Response, name: &str) -> Option<f64> {
+    response.headers().get(name)?.to_str().ok()?.trim().parse().ok()
+}
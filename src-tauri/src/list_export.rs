@@ -0,0 +1,277 @@
+use std::{collections::HashMap, io::Cursor};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{anime_data::AnimeInfo, user_data::{score_from_ten, score_to_ten, Date, UserInfo}};
+
+
+
+// formats the user's list can be exported to
+pub const EXPORT_FORMATS: [&'static str; 3] = ["MAL_XML", "CSV", "JSON"];
+
+// myanimelist's export uses different names for the statuses, indexed the same as USER_STATUSES
+const MAL_XML_STATUSES: [&'static str; 6] = ["Watching", "Completed", "On-Hold", "Dropped", "Plan to Watch", "Watching"];
+
+
+
+// a entry read from a myanimelist xml export
+#[derive(Debug, Clone, Default)]
+pub struct MalXmlEntry {
+    pub mal_id: i32,
+    pub status: String, // gekijou status
+    pub score: f32, // out of 10
+    pub progress: i32,
+    pub started_at: Option<Date>,
+    pub completed_at: Option<Date>,
+    pub notes: Option<String>,
+    pub repeat: i32,
+}
+
+// what a list import changed
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ListImportSummary {
+    pub added: usize,
+    pub replaced: usize,
+    pub skipped: usize, // already on the list
+    pub not_found: usize, // no anilist anime has the myanimelist id
+}
+
+
+
+// myanimelist scores are whole numbers out of 10
+pub fn score_to_mal(score: f32, score_format: &Option<String>) -> i32 {
+    (score_to_ten(score, score_format).round() as i32).clamp(0, 10)
+}
+
+// convert a myanimelist score into the user's score format
+pub fn score_from_mal(score: f32, score_format: &Option<String>) -> f32 {
+    score_from_ten(score, score_format)
+}
+
+
+
+// the user's list in the format myanimelist exports and imports, anime without a myanimelist id are left out
+pub fn to_mal_xml(entries: &Vec<(UserInfo, Option<AnimeInfo>)>, username: &String, score_format: &Option<String>) -> String {
+
+    let mut anime_xml = String::new();
+    let mut status_counts: HashMap<&str, usize> = HashMap::new();
+    let mut total = 0;
+    for (entry, anime) in entries {
+
+        let anime = match anime {
+            Some(anime) => anime,
+            None => continue,
+        };
+        let mal_id = match anime.id_mal {
+            Some(mal_id) => mal_id,
+            None => continue,
+        };
+        let status_index = crate::constants::USER_STATUSES.iter().position(|status| *status == entry.status).unwrap_or(4);
+        let status = MAL_XML_STATUSES[status_index];
+        *status_counts.entry(status).or_default() += 1;
+        total += 1;
+
+        anime_xml.push_str("\t<anime>\n");
+        push_element(&mut anime_xml, "series_animedb_id", &mal_id.to_string());
+        push_element(&mut anime_xml, "series_title", &anime.title.romaji.clone().unwrap_or_default());
+        push_element(&mut anime_xml, "series_type", &anime.format.clone().unwrap_or_default());
+        push_element(&mut anime_xml, "series_episodes", &anime.episodes.unwrap_or(0).to_string());
+        push_element(&mut anime_xml, "my_id", "0");
+        push_element(&mut anime_xml, "my_watched_episodes", &entry.progress.to_string());
+        push_element(&mut anime_xml, "my_start_date", &mal_xml_date(&entry.started_at));
+        push_element(&mut anime_xml, "my_finish_date", &mal_xml_date(&entry.completed_at));
+        push_element(&mut anime_xml, "my_score", &score_to_mal(entry.score, score_format).to_string());
+        push_element(&mut anime_xml, "my_status", status);
+        push_element(&mut anime_xml, "my_comments", &entry.notes.clone().unwrap_or_default());
+        push_element(&mut anime_xml, "my_times_watched", &entry.repeat.to_string());
+        push_element(&mut anime_xml, "my_rewatching", if entry.status == "REPEATING" { "1" } else { "0" });
+        push_element(&mut anime_xml, "update_on_import", "1");
+        anime_xml.push_str("\t</anime>\n");
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" ?>\n<myanimelist>\n\t<myinfo>\n");
+    push_element(&mut xml, "user_name", username);
+    push_element(&mut xml, "user_export_type", "1");
+    push_element(&mut xml, "user_total_anime", &total.to_string());
+    push_element(&mut xml, "user_total_watching", &status_counts.get("Watching").unwrap_or(&0).to_string());
+    push_element(&mut xml, "user_total_completed", &status_counts.get("Completed").unwrap_or(&0).to_string());
+    push_element(&mut xml, "user_total_onhold", &status_counts.get("On-Hold").unwrap_or(&0).to_string());
+    push_element(&mut xml, "user_total_dropped", &status_counts.get("Dropped").unwrap_or(&0).to_string());
+    push_element(&mut xml, "user_total_plantowatch", &status_counts.get("Plan to Watch").unwrap_or(&0).to_string());
+    xml.push_str("\t</myinfo>\n");
+    xml.push_str(&anime_xml);
+    xml.push_str("</myanimelist>\n");
+    xml
+}
+
+fn push_element(xml: &mut String, name: &str, value: &str) {
+    xml.push_str(&format!("\t\t<{}>{}</{}>\n", name, escape_xml(value), name));
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// myanimelist uses 0000-00-00 for unknown dates and 00 for unknown parts
+fn mal_xml_date(date: &Option<Date>) -> String {
+    match date {
+        Some(date) => format!("{:04}-{:02}-{:02}", date.year.unwrap_or(0), date.month.unwrap_or(0), date.day.unwrap_or(0)),
+        None => String::from("0000-00-00"),
+    }
+}
+
+fn mal_xml_date_to_date(text: &str) -> Option<Date> {
+    let mut parts = text.split('-').map(|part| part.parse::<i32>().ok().filter(|value| *value > 0));
+    let date = Date { year: parts.next().flatten(), month: parts.next().flatten(), day: parts.next().flatten() };
+    if date.year.is_none() && date.month.is_none() && date.day.is_none() {
+        None
+    } else {
+        Some(date)
+    }
+}
+
+
+
+// read the anime from a myanimelist xml export
+pub fn parse_mal_xml(contents: String) -> Result<Vec<MalXmlEntry>, &'static str> {
+
+    let doc = xml::reader::EventReader::new(Cursor::new(contents));
+
+    let mut entries: Vec<MalXmlEntry> = Vec::new();
+    let mut entry = MalXmlEntry::default(); // temporary holds the entry being parsed
+    let mut rewatching = false;
+    let mut element_name = String::new(); // tracks what property a value belongs to
+    for event in doc {
+        let text = match event {
+            Ok(xml::reader::XmlEvent::StartElement { name, .. }) => {
+                element_name = name.local_name;
+                continue;
+            },
+            Ok(xml::reader::XmlEvent::Characters(text)) | Ok(xml::reader::XmlEvent::CData(text)) => text,
+            Ok(xml::reader::XmlEvent::EndElement { name }) => {
+                // current entry is completed, store it and reset temp variables
+                if name.local_name == "anime" {
+                    if rewatching && entry.status == "CURRENT" {
+                        entry.status = String::from("REPEATING");
+                    }
+                    if entry.mal_id > 0 {
+                        entries.push(entry);
+                    }
+                    entry = MalXmlEntry::default();
+                    rewatching = false;
+                }
+                element_name = String::new();
+                continue;
+            },
+            Ok(_) => continue,
+            Err(_error) => return Err("Not a myanimelist export"),
+        };
+
+        match element_name.as_str() {
+            "series_animedb_id" => entry.mal_id = text.trim().parse().unwrap_or_default(),
+            "my_watched_episodes" => entry.progress = text.trim().parse().unwrap_or_default(),
+            "my_start_date" => entry.started_at = mal_xml_date_to_date(text.trim()),
+            "my_finish_date" => entry.completed_at = mal_xml_date_to_date(text.trim()),
+            "my_score" => entry.score = text.trim().parse().unwrap_or_default(),
+            "my_status" => {
+                entry.status = match MAL_XML_STATUSES.iter().position(|status| *status == text.trim()) {
+                    Some(index) => crate::constants::USER_STATUSES[index].to_string(),
+                    None => String::from("PLANNING"),
+                };
+            },
+            "my_comments" => entry.notes = Some(text).filter(|notes| notes.is_empty() == false),
+            "my_times_watched" => entry.repeat = text.trim().parse().unwrap_or_default(),
+            "my_rewatching" => rewatching = text.trim() == "1",
+            _ => {},
+        }
+    }
+
+    Ok(entries)
+}
+
+
+
+// the user's list as comma separated values, one anime per line
+pub fn to_csv(entries: &Vec<(UserInfo, Option<AnimeInfo>)>) -> String {
+
+    let mut csv = String::from("media_id,id_mal,title,format,episodes,status,score,progress,repeat,started_at,completed_at,updated_at,notes\n");
+    for (entry, anime) in entries {
+        let values = [
+            entry.media_id.to_string(),
+            anime.as_ref().and_then(|anime| anime.id_mal).map(|id| id.to_string()).unwrap_or_default(),
+            anime.as_ref().and_then(|anime| anime.title.romaji.clone()).unwrap_or_default(),
+            anime.as_ref().and_then(|anime| anime.format.clone()).unwrap_or_default(),
+            anime.as_ref().and_then(|anime| anime.episodes).map(|episodes| episodes.to_string()).unwrap_or_default(),
+            entry.status.clone(),
+            entry.score.to_string(),
+            entry.progress.to_string(),
+            entry.repeat.to_string(),
+            csv_date(&entry.started_at),
+            csv_date(&entry.completed_at),
+            entry.updated_at.to_string(),
+            entry.notes.clone().unwrap_or_default(),
+        ];
+        let line: Vec<String> = values.iter().map(|value| escape_csv(value)).collect();
+        csv.push_str(&line.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn escape_csv(value: &str) -> String {
+    if value.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// YYYY-MM-DD with unknown parts left out
+fn csv_date(date: &Option<Date>) -> String {
+    match date {
+        Some(date) => [date.year.map(|year| format!("{:04}", year)), date.month.map(|month| format!("{:02}", month)), date.day.map(|day| format!("{:02}", day))]
+            .iter()
+            .map_while(|part| part.clone())
+            .collect::<Vec<String>>()
+            .join("-"),
+        None => String::new(),
+    }
+}
+
+
+
+// the user's list shaped like anilist's MediaList objects
+pub fn to_anilist_json(entries: &Vec<(UserInfo, Option<AnimeInfo>)>) -> Value {
+
+    let list: Vec<Value> = entries.iter().map(|(entry, anime)| {
+        json!({
+            "mediaId": entry.media_id,
+            "status": entry.status,
+            "score": entry.score,
+            "progress": entry.progress,
+            "repeat": entry.repeat,
+            "private": entry.private,
+            "hiddenFromStatusLists": entry.hidden_from_status_lists,
+            "customLists": entry.custom_lists,
+            "advancedScores": entry.advanced_scores,
+            "startedAt": entry.started_at,
+            "completedAt": entry.completed_at,
+            "notes": entry.notes,
+            "updatedAt": entry.updated_at,
+            "media": anime.as_ref().map(|anime| json!({
+                "id": anime.id,
+                "idMal": anime.id_mal,
+                "title": { "romaji": anime.title.romaji, "english": anime.title.english, "native": anime.title.native },
+                "format": anime.format,
+                "episodes": anime.episodes,
+            })),
+        })
+    }).collect();
+
+    json!({ "entries": list })
+}
@@ -0,0 +1,121 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{anilist_response_tests::{expect, ResponseTest}, anime_data::AnimeInfo, list_export::{self, MalXmlEntry}, user_data::{Date, UserInfo}};
+
+
+
+// the anime in export_fixtures/animelist.xml, anilist uses the same ids as myanimelist for these
+const FIXTURE_ANIME: [(i32, &str, Option<i32>); 6] = [(21, "One Piece", None), (20, "Naruto", Some(220)), (1535, "Death Note", Some(37)),
+    (5114, "Fullmetal Alchemist: Brotherhood", Some(64)), (30, "Neon Genesis Evangelion", Some(26)), (16498, "Shingeki no Kyojin", Some(25))];
+
+// reads a list exported from myanimelist, writes it back out as myanimelist xml and csv, and checks nothing changed on the way
+pub fn list_export_tests() -> Vec<ResponseTest> {
+
+    let mut test_results: Vec<ResponseTest> = Vec::new();
+    for (name, result) in [
+        ("mal export read", read_mal_export()),
+        ("mal export round trip", mal_round_trip()),
+        ("csv export", csv_export()),
+    ] {
+        test_results.push(ResponseTest {
+            fixture: String::from(name),
+            passed: result.is_ok(),
+            error: result.err().unwrap_or_default(),
+        });
+    }
+
+    test_results
+}
+
+fn read_fixture(fixture: &str) -> Result<String, String> {
+    fs::read_to_string(Path::new("export_fixtures").join(fixture)).map_err(|error| format!("{} {}", fixture, error))
+}
+
+fn mal_export() -> Result<Vec<MalXmlEntry>, String> {
+    Ok(list_export::parse_mal_xml(read_fixture("animelist.xml")?)?)
+}
+
+// the export's entries as they're added to the list
+fn list_entries(mal_entries: &Vec<MalXmlEntry>) -> Vec<(UserInfo, Option<AnimeInfo>)> {
+
+    let anime: HashMap<i32, (&str, Option<i32>)> = FIXTURE_ANIME.iter().map(|(id, title, episodes)| (*id, (*title, *episodes))).collect();
+    mal_entries.iter().map(|mal_entry| {
+        let mut entry = UserInfo::new();
+        entry.media_id = mal_entry.mal_id;
+        entry.status = mal_entry.status.clone();
+        entry.score = mal_entry.score;
+        entry.progress = mal_entry.progress;
+        entry.started_at = mal_entry.started_at.clone();
+        entry.completed_at = mal_entry.completed_at.clone();
+        entry.notes = mal_entry.notes.clone();
+        entry.repeat = mal_entry.repeat;
+
+        let info = anime.get(&mal_entry.mal_id).map(|(title, episodes)| {
+            let mut info = AnimeInfo::default();
+            info.id = mal_entry.mal_id;
+            info.id_mal = Some(mal_entry.mal_id);
+            info.title.romaji = Some(title.to_string());
+            info.format = Some(String::from("TV"));
+            info.episodes = *episodes;
+            info
+        });
+        (entry, info)
+    }).collect()
+}
+
+fn date(year: Option<i32>, month: Option<i32>, day: Option<i32>) -> Option<Date> {
+    Some(Date { year, month, day })
+}
+
+
+
+fn read_mal_export() -> Result<(), String> {
+
+    let entries = mal_export()?;
+    expect("entries", &entries.len(), &6)?;
+
+    let statuses: Vec<&str> = entries.iter().map(|entry| entry.status.as_str()).collect();
+    expect("statuses", &statuses, &vec!["CURRENT", "REPEATING", "COMPLETED", "PAUSED", "DROPPED", "PLANNING"])?;
+    expect("times watched", &entries[1].repeat, &2)?;
+    expect("score", &entries[2].score, &10.0)?;
+
+    // 0000-00-00 is a date that wasn't set, 00 is a part that wasn't set
+    expect("started", &entries[0].started_at, &date(Some(2020), Some(1), Some(5)))?;
+    expect("not finished", &entries[0].completed_at, &None)?;
+    expect("no day", &entries[1].started_at, &date(Some(2019), Some(3), None))?;
+    expect("only year", &entries[4].started_at, &date(Some(2021), None, None))?;
+    expect("not started", &entries[5].started_at, &None)?;
+
+    expect("empty comments", &entries[0].notes, &None)?;
+    expect("comments", &entries[2].notes, &Some(String::from("rewatch, \"again\" & again")))?;
+    expect("multi line comments", &entries[3].notes, &Some(String::from("paused at\nthe train episode")))?;
+    Ok(())
+}
+
+fn mal_round_trip() -> Result<(), String> {
+
+    let entries = mal_export()?;
+    let xml = list_export::to_mal_xml(&list_entries(&entries), &String::from("user"), &Some(String::from("POINT_10")));
+
+    // rewatching is watching with a flag on myanimelist
+    expect("watching total", &xml.contains("<user_total_watching>2</user_total_watching>"), &true)?;
+    expect("rewatching", &xml.contains("<my_rewatching>1</my_rewatching>"), &true)?;
+    expect("unset date", &xml.contains("<my_start_date>0000-00-00</my_start_date>"), &true)?;
+    expect("partial date", &xml.contains("<my_start_date>2019-03-00</my_start_date>"), &true)?;
+    expect("escaped comments", &xml.contains("<my_comments>rewatch, &quot;again&quot; &amp; again</my_comments>"), &true)?;
+
+    let read_again = list_export::parse_mal_xml(xml)?;
+    expect("entries", &format!("{:?}", read_again), &format!("{:?}", entries))?;
+    Ok(())
+}
+
+fn csv_export() -> Result<(), String> {
+
+    let csv = list_export::to_csv(&list_entries(&mal_export()?));
+    let expected = read_fixture("animelist.csv")?;
+    for (line, (value, expected)) in csv.lines().zip(expected.lines()).enumerate() {
+        expect(&format!("line {}", line + 1), &value, &expected)?;
+    }
+    expect("lines", &csv.lines().count(), &expected.lines().count())?;
+    Ok(())
+}
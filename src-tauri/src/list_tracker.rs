@@ -0,0 +1,207 @@
+use std::{collections::{HashMap, HashSet}, sync::Arc};
+
+use async_trait::async_trait;
+
+use crate::{api_calls::{self, ListOptions, TokenData, Website}, constants, graphql_client::GraphQlClient, mal_api_calls, user_data::UserInfo};
+
+
+
+// returned by save_entry when the website refused the change, sending the same change again will not succeed
+pub const ENTRY_REJECTED: &str = "entry rejected";
+
+
+
+// a website that keeps track of the user's anime list
+#[async_trait]
+pub trait ListTracker: Send + Sync {
+
+    // download one of the user's lists and add it to the user's data
+    // returns a message if a error occurred
+    async fn get_list(&self, username: &String, list: &String, user_data: &mut HashMap<i32, UserInfo>, user_lists: &mut HashMap<String, Vec<i32>>) -> Option<String>;
+
+    // get the user's entry for a anime, None if the anime is not on their list
+    async fn get_entry(&self, anime: &UserInfo) -> Result<Option<UserInfo>, &'static str>;
+
+    // add or change a entry on the user's list, returns the id of the list entry
+    async fn save_entry(&self, anime: &UserInfo) -> Result<i32, &'static str>;
+
+    // remove a entry from the user's list, returns true if the entry was removed
+    async fn delete_entry(&self, anime: &UserInfo) -> Result<bool, &'static str>;
+
+    // get the format the user's scores are in (POINT_10, POINT_100, etc)
+    async fn get_score_format(&self, username: &String) -> Result<String, &'static str>;
+
+    // get the user's custom lists and advanced scoring categories, websites without them return empty options
+    async fn get_list_options(&self, _username: &String) -> Result<ListOptions, &'static str> {
+        Ok(ListOptions::default())
+    }
+
+    // get the entries on the user's list that were changed after the unix time, newest first
+    async fn get_updates_since(&self, username: &String, since: u64) -> Result<Vec<UserInfo>, &'static str>;
+
+    // get the ids of every anime on the user's list grouped by the list they're in, used to find entries deleted on the website
+    async fn get_list_ids(&self, username: &String) -> Result<HashMap<String, HashSet<i32>>, &'static str>;
+}
+
+
+
+// returns the tracker for the website the user logged into, the list is kept locally if they haven't logged in
+// advanced_scoring is the user's advanced scoring categories, needed to send advanced scores to anilist
+// anilist is the client queries are sent through, myanimelist lists use it to look up anilist ids
+pub fn get_tracker(token: &TokenData, advanced_scoring: &Vec<String>, anilist: Arc<dyn GraphQlClient>) -> Box<dyn ListTracker> {
+    match token.website {
+        Website::MyAnimeList => Box::new(MalTracker { access_token: token.access_token.clone(), anilist }),
+        Website::Anilist => Box::new(AnilistTracker { access_token: token.access_token.clone(), advanced_scoring: advanced_scoring.clone(), anilist }),
+        Website::None => Box::new(LocalTracker),
+    }
+}
+
+
+
+pub struct AnilistTracker {
+    access_token: String,
+    advanced_scoring: Vec<String>,
+    anilist: Arc<dyn GraphQlClient>,
+}
+
+#[async_trait]
+impl ListTracker for AnilistTracker {
+
+    async fn get_list(&self, username: &String, list: &String, user_data: &mut HashMap<i32, UserInfo>, user_lists: &mut HashMap<String, Vec<i32>>) -> Option<String> {
+        api_calls::anilist_get_list(&*self.anilist, username.clone(), list.clone(), self.access_token.clone(), user_data, user_lists).await
+    }
+
+    async fn get_entry(&self, anime: &UserInfo) -> Result<Option<UserInfo>, &'static str> {
+        api_calls::get_media_list_entry(&*self.anilist, anime.media_id, self.access_token.clone()).await
+    }
+
+    async fn save_entry(&self, anime: &UserInfo) -> Result<i32, &'static str> {
+        match api_calls::update_user_entry(&*self.anilist, self.access_token.clone(), anime.clone(), &self.advanced_scoring).await {
+            Ok(id) => Ok(id),
+            Err("anilist returned error") => Err(ENTRY_REJECTED),
+            Err(error) => Err(error),
+        }
+    }
+
+    async fn delete_entry(&self, anime: &UserInfo) -> Result<bool, &'static str> {
+        api_calls::anilist_remove_entry(&*self.anilist, anime.id, self.access_token.clone()).await
+    }
+
+    async fn get_score_format(&self, username: &String) -> Result<String, &'static str> {
+        api_calls::get_user_score_format(&*self.anilist, username.clone()).await
+    }
+
+    async fn get_list_options(&self, username: &String) -> Result<ListOptions, &'static str> {
+        api_calls::get_user_list_options(&*self.anilist, username.clone()).await
+    }
+
+    async fn get_updates_since(&self, username: &String, since: u64) -> Result<Vec<UserInfo>, &'static str> {
+        api_calls::get_updated_media_ids(&*self.anilist, username.clone(), since, self.access_token.clone()).await
+    }
+
+    async fn get_list_ids(&self, username: &String) -> Result<HashMap<String, HashSet<i32>>, &'static str> {
+        api_calls::anilist_get_list_ids(&*self.anilist, username.clone(), self.access_token.clone()).await
+    }
+}
+
+
+
+pub struct MalTracker {
+    access_token: String,
+    anilist: Arc<dyn GraphQlClient>,
+}
+
+impl MalTracker {
+
+    // myanimelist entries use the myanimelist id as their list entry id, entries created by gekijou need it looked up
+    async fn get_mal_id(&self, anime: &UserInfo) -> Result<i32, &'static str> {
+        if anime.id != 0 {
+            return Ok(anime.id);
+        }
+        match api_calls::anilist_get_mal_id(&*self.anilist, anime.media_id).await {
+            Ok(Some(mal_id)) => Ok(mal_id),
+            Ok(None) => Err(ENTRY_REJECTED), // anime is not on myanimelist
+            Err(error) => Err(error),
+        }
+    }
+}
+
+#[async_trait]
+impl ListTracker for MalTracker {
+
+    async fn get_list(&self, _username: &String, list: &String, user_data: &mut HashMap<i32, UserInfo>, user_lists: &mut HashMap<String, Vec<i32>>) -> Option<String> {
+        mal_api_calls::mal_get_list(&*self.anilist, list.clone(), self.access_token.clone(), user_data, user_lists).await
+    }
+
+    async fn get_entry(&self, anime: &UserInfo) -> Result<Option<UserInfo>, &'static str> {
+        let mal_id = self.get_mal_id(anime).await?;
+        mal_api_calls::mal_get_list_entry(mal_id, anime.media_id, self.access_token.clone()).await
+    }
+
+    async fn save_entry(&self, anime: &UserInfo) -> Result<i32, &'static str> {
+        let mal_id = self.get_mal_id(anime).await?;
+        match mal_api_calls::mal_update_list_entry(mal_id, anime, self.access_token.clone()).await {
+            Ok(()) => Ok(mal_id),
+            Err("myanimelist returned error") | Err("invalid status") => Err(ENTRY_REJECTED),
+            Err(error) => Err(error),
+        }
+    }
+
+    async fn delete_entry(&self, anime: &UserInfo) -> Result<bool, &'static str> {
+        let mal_id = self.get_mal_id(anime).await?;
+        mal_api_calls::mal_remove_list_entry(mal_id, self.access_token.clone()).await
+    }
+
+    async fn get_score_format(&self, _username: &String) -> Result<String, &'static str> {
+        // myanimelist scores are always out of 10
+        Ok(String::from("POINT_10"))
+    }
+
+    async fn get_updates_since(&self, _username: &String, since: u64) -> Result<Vec<UserInfo>, &'static str> {
+        mal_api_calls::mal_get_updated_entries(&*self.anilist, self.access_token.clone(), since).await
+    }
+
+    async fn get_list_ids(&self, _username: &String) -> Result<HashMap<String, HashSet<i32>>, &'static str> {
+        mal_api_calls::mal_get_list_ids(&*self.anilist, self.access_token.clone()).await
+    }
+}
+
+
+
+// a list that is only kept on this computer, every change succeeds without a connection
+pub struct LocalTracker;
+
+#[async_trait]
+impl ListTracker for LocalTracker {
+
+    async fn get_list(&self, _username: &String, list: &String, _user_data: &mut HashMap<i32, UserInfo>, user_lists: &mut HashMap<String, Vec<i32>>) -> Option<String> {
+        // nothing to download, the list starts empty
+        user_lists.entry(list.clone()).or_default();
+        None
+    }
+
+    async fn get_entry(&self, _anime: &UserInfo) -> Result<Option<UserInfo>, &'static str> {
+        Ok(None)
+    }
+
+    async fn save_entry(&self, anime: &UserInfo) -> Result<i32, &'static str> {
+        Ok(anime.id)
+    }
+
+    async fn delete_entry(&self, _anime: &UserInfo) -> Result<bool, &'static str> {
+        Ok(true)
+    }
+
+    async fn get_score_format(&self, _username: &String) -> Result<String, &'static str> {
+        Ok(String::from(constants::LOCAL_SCORE_FORMAT))
+    }
+
+    async fn get_updates_since(&self, _username: &String, _since: u64) -> Result<Vec<UserInfo>, &'static str> {
+        Ok(Vec::new())
+    }
+
+    async fn get_list_ids(&self, _username: &String) -> Result<HashMap<String, HashSet<i32>>, &'static str> {
+        // this computer's list is the only copy
+        Err("no website")
+    }
+}
@@ -7,105 +7,193 @@
 
 pub mod constants;
 pub mod secrets;
+pub mod http_client;
+pub mod graphql_client;
+pub mod events;
+pub mod scheduler;
 pub mod api_calls;
+pub mod anilist_responses;
 pub mod mal_api_calls;
+pub mod list_tracker;
 pub mod file_operations;
+pub mod database;
+pub mod data_archive;
+pub mod list_export;
 pub mod rss_parser;
 pub mod recommendation;
 pub mod file_name_recognition_tests;
+pub mod schema;
+pub mod schema_tests;
+pub mod anilist_response_tests;
+#[cfg(debug_assertions)]
+pub mod anilist_client_tests;
+pub mod mal_tests;
+pub mod data_archive_tests;
+pub mod list_export_tests;
 pub mod user_data;
 pub mod anime_data;
+pub mod media_players;
+pub mod watch_history;
+#[cfg(target_os = "linux")]
+pub mod mpris;
+#[cfg(all(debug_assertions, target_os = "linux"))]
+pub mod mpris_tests;
 
 #[macro_use]
 extern crate lazy_static;
 
 use anime_data::AnimeInfo;
 use file_name_recognition_tests::FilenameTest;
+use schema_tests::SchemaTest;
+use anilist_response_tests::ResponseTest;
 use regex::Regex;
 use rss_parser::RssEntry;
 use serde::{Serialize, Deserialize};
 use tauri::async_runtime::Mutex;
-use tauri::Manager;
-use user_data::{UserData, UserInfo, UserSettings};
+use tauri::{AppHandle, Manager, State};
+use user_data::{AttachResult, SyncConflict, UserData, UserInfo, UserSettings};
 use window_titles::{Connection, ConnectionTrait};
-use std::{collections::{HashMap, HashSet}, path::Path, time::{Duration, Instant}, ops::{Range, Deref}};
+use std::{collections::{HashMap, HashSet}, path::Path, sync::{Arc, atomic::{AtomicBool, Ordering}}, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
 use open;
 use api_calls::MangaInfo;
-use crate::anime_data::{AnimeData, AnimePath};
+use crate::anime_data::AnimeData;
+use crate::watch_history::{WatchHistory, WatchRecord, WatchSource};
+use crate::data_archive::{DataArchive, ImportConflict, ImportSummary};
+use crate::list_export::ListImportSummary;
+use crate::events::{EventSink, RecoveryReport, UiEvent};
+use crate::scheduler::{Job, JobStatus, Scheduler};
 use rand::Rng;
+use async_trait::async_trait;
 
 
 
-//stores details on which parts of the UI need to be refreshed
+// what the ui shows that doesn't come from a command, sent to it when it opens so it doesn't need to wait for the next event
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
-pub struct RefreshUI {
-    pub anime_list: bool,
-    pub tracking_progress: bool,
-    pub canvas: bool,
+pub struct UiStatus {
     pub no_internet: bool,
-    pub scan_data: ScanData,
-    pub errors: Vec<String>,
-    pub loading_dialog: Option<String>,
+    pub queued_updates: usize,
+    pub sync_conflicts: usize,
+    pub reauthorize: bool,
+    pub errors: Vec<String>, // errors from before the ui was listening for them
+    pub recovery: RecoveryReport, // files that were damaged when they were read at startup
 }
 
-impl RefreshUI {
-    
-    pub fn clear(&mut self) {
-        self.anime_list = false;
-        self.tracking_progress = false;
-        self.canvas = false;
-        self.no_internet = false;
-        self.scan_data.clear();
-        self.errors.clear();
-        self.loading_dialog = None;
-    }
+// sends events to the ui as they happen
+pub struct TauriEvents {
+    app: AppHandle,
+    status: Mutex<UiStatus>,
+    listening: AtomicBool, // errors are kept until the ui asks for its status, they would be lost otherwise
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
-pub struct ScanData {
-    pub current_folder: i32,
-    pub total_folders: i32,
-    pub completed_chunks: i32,
-    pub total_chunks: i32,
+impl TauriEvents {
+
+    pub fn new(app: AppHandle) -> TauriEvents {
+        TauriEvents { app, status: Mutex::new(UiStatus::default()), listening: AtomicBool::new(false) }
+    }
+
+    // false if the last request to the website couldn't reach it
+    pub async fn online(&self) -> bool {
+        self.status.lock().await.no_internet == false
+    }
+
+    // the ui is listening for events from now on, returns what it missed
+    pub async fn ui_status(&self) -> UiStatus {
+        let mut status = self.status.lock().await;
+        self.listening.store(true, Ordering::SeqCst);
+        let ui_status = status.clone();
+        status.errors.clear();
+        status.recovery = RecoveryReport::default();
+        ui_status
+    }
 }
 
-impl ScanData {
-    
-    pub fn clear(&mut self) {
-        self.current_folder = 0;
-        self.total_folders = 0;
-        self.completed_chunks = 0;
-        self.total_chunks = 0;
+#[async_trait]
+impl EventSink for TauriEvents {
+    async fn send(&self, event: UiEvent) {
+        {
+            let mut status = self.status.lock().await;
+            match &event {
+                // only changes are sent, every request reports if the website could be reached
+                UiEvent::Connectivity(online) => {
+                    if status.no_internet == (*online == false) {
+                        return;
+                    }
+                    status.no_internet = *online == false;
+                },
+                UiEvent::QueuedUpdates(count) => status.queued_updates = *count,
+                UiEvent::SyncConflicts(count) => status.sync_conflicts = *count,
+                UiEvent::Reauthorize(reauthorize) => status.reauthorize = *reauthorize,
+                UiEvent::Error(message) if self.listening.load(Ordering::SeqCst) == false => status.errors.push(message.clone()),
+                UiEvent::FilesRecovered(report) if self.listening.load(Ordering::SeqCst) == false => {
+                    status.recovery.recovered.extend(report.recovered.iter().cloned());
+                    status.recovery.lost.extend(report.lost.iter().cloned());
+                },
+                _ => {},
+            }
+        }
+        if let Err(error) = self.app.emit_all(event.name(), event) {
+            println!("send event: {}", error);
+        }
     }
 }
 
 
 
-lazy_static! {
-    static ref GLOBAL_REFRESH_UI: Mutex<RefreshUI> = Mutex::new(RefreshUI::default());
-    static ref GLOBAL_UPDATE_ANIME_DELAYED: Mutex<HashMap<i32, Instant>> = Mutex::new(HashMap::new());
-    static ref GLOBAL_USER_DATA: Mutex<UserData> = Mutex::new(UserData::new());
-    static ref GLOBAL_ANIME_DATA: Mutex<AnimeData> = Mutex::new(AnimeData::new());
-    static ref GLOBAL_MAL_CODE_CHALLENGE: Mutex<String> = Mutex::new(String::new());
-    static ref GLOBAL_LIST_DATA_CACHE: Mutex<Vec<(anime_data::AnimeInfo, UserInfo)>> = Mutex::new(Vec::new());
+// everything the commands share, tauri manages it and passes it to each command that asks for it
+// when more than one is locked they're locked in the order they're declared here so commands can't deadlock each other
+// commands taking state return a Result because tauri can't run async commands that borrow their arguments otherwise
+pub struct AppState {
+    pub user_data: Mutex<UserData>,
+    pub anime_data: Mutex<AnimeData>,
+    pub watching_tracking: Mutex<HashMap<i32, WatchingTracking>>,
+    pub watch_history: Mutex<WatchHistory>,
+    pub list_data_cache: Mutex<Vec<(AnimeInfo, UserInfo)>>, // the list being shown, sorted, so other pages don't need to sort again
+    pub mal_code_challenge: Mutex<String>,
+    pub scheduler: Mutex<Scheduler>, // only locked on its own to record what a job did
+    pub startup_finished: Mutex<bool>,
+    pub scanning: AtomicBool, // a folder scan is running, another one won't be started
+    pub ui_events: Arc<TauriEvents>,
+    pub events: Arc<dyn EventSink>, // the same as ui_events, passed to everything that doesn't need to know about tauri
+}
+
+impl AppState {
+
+    pub fn new(app: AppHandle) -> AppState {
+        let ui_events = Arc::new(TauriEvents::new(app));
+        let events: Arc<dyn EventSink> = ui_events.clone();
+        AppState {
+            user_data: Mutex::new(UserData::new(events.clone())),
+            anime_data: Mutex::new(AnimeData::new(events.clone())),
+            watching_tracking: Mutex::new(HashMap::new()),
+            watch_history: Mutex::new(WatchHistory::new(events.clone())),
+            list_data_cache: Mutex::new(Vec::new()),
+            mal_code_challenge: Mutex::new(String::new()),
+            scheduler: Mutex::new(Scheduler::new()),
+            startup_finished: Mutex::new(false),
+            scanning: AtomicBool::new(false),
+            ui_events,
+            events,
+        }
+    }
 }
 
 
 
 // takes a oauth code from the user and exchanges it for a oauth access token
 #[tauri::command]
-async fn anilist_oauth_token(code: String) -> (bool, String) {
+async fn anilist_oauth_token(state: State<'_, AppState>, code: String) -> Result<(bool, String), ()> {
     
-    GLOBAL_USER_DATA.lock().await.anilist_oauth_token(code).await
+    Ok(state.user_data.lock().await.anilist_oauth_token(code).await)
 }
 
 
 
 // takes a oauth code from the user and exchanges it for a oauth access token
 #[tauri::command]
-async fn mal_oauth_token(code: String) -> (bool, String) {
+async fn mal_oauth_token(state: State<'_, AppState>, code: String) -> Result<(bool, String), ()> {
     
-    GLOBAL_USER_DATA.lock().await.mal_oauth_token(code, GLOBAL_MAL_CODE_CHALLENGE.lock().await.clone()).await
+    let code_challenge = state.mal_code_challenge.lock().await.clone();
+    Ok(state.user_data.lock().await.mal_oauth_token(code, code_challenge).await)
 }
 
 
@@ -120,45 +208,48 @@ async fn write_token_data() {
 
 // sets the user's settings taken from the settings ui
 #[tauri::command]
-async fn set_user_settings(settings: UserSettings) {
+async fn set_user_settings(state: State<'_, AppState>, settings: UserSettings) -> Result<(), ()> {
 
-    let (scan, media_ids) = GLOBAL_USER_DATA.lock().await.set_user_settings(settings).await;
+    let (scan, media_ids) = state.user_data.lock().await.set_user_settings(settings).await;
 
     if let Some(ids) = media_ids {
         // get anime data from anilist
-        GLOBAL_REFRESH_UI.lock().await.loading_dialog = Some(format!("Downloading Anime Data"));
-        let mut anime_data = GLOBAL_ANIME_DATA.lock().await;
+        state.events.send(UiEvent::Loading(Some(format!("Downloading Anime Data")))).await;
+        let mut user_data = state.user_data.lock().await;
+        let mut anime_data = state.anime_data.lock().await;
         anime_data.get_anime_list_data(ids).await;
         if anime_data.new_anime == true {
-            GLOBAL_USER_DATA.lock().await.set_max_episodes(anime_data.get_anime_episodes());
+            user_data.set_max_episodes(anime_data.get_anime_episodes());
             anime_data.new_anime = false;
         }
-        GLOBAL_REFRESH_UI.lock().await.loading_dialog = Some(format!("Finished Downloading Anime Data"));
+        state.events.send(UiEvent::Loading(Some(format!("Finished Downloading Anime Data")))).await;
     }
 
     if scan {
-        scan_anime_folder();
+        scan_for_new_episodes(&state).await;
     }
 
-    GLOBAL_REFRESH_UI.lock().await.loading_dialog = None;
+    state.events.send(UiEvent::Loading(None)).await;
 
     //file_operations::write_file_user_settings().await;
+    Ok(())
 }
 
 
 
 // retrieves user's settings from a file
 #[tauri::command]
-async fn get_user_settings() -> user_data::UserSettings {
-    GLOBAL_USER_DATA.lock().await.get_user_settings()
+async fn get_user_settings(state: State<'_, AppState>) -> Result<user_data::UserSettings, ()> {
+    Ok(state.user_data.lock().await.get_user_settings())
 }
 
 
 
 // retrieves user's settings from a file
 #[tauri::command]
-async fn set_current_tab(current_tab: String) {
-    GLOBAL_USER_DATA.lock().await.set_current_tab(current_tab);
+async fn set_current_tab(state: State<'_, AppState>, current_tab: String) -> Result<(), ()> {
+    state.user_data.lock().await.set_current_tab(current_tab);
+    Ok(())
 }
 
 
@@ -167,11 +258,11 @@ async fn set_current_tab(current_tab: String) {
 // information is paged, 50 entries are returned per page
 // sorting and ascending are only used for page 0, other pages use the sorting order of the last time page 0 was called
 #[tauri::command]
-async fn get_list_paged(list_name: String, sort: String, ascending: bool, page: usize) -> Result<Vec<(anime_data::AnimeInfo, UserInfo)>, &'static str>{
+async fn get_list_paged(state: State<'_, AppState>, list_name: String, sort: String, ascending: bool, page: usize) -> Result<Vec<(anime_data::AnimeInfo, UserInfo)>, &'static str>{
 
     if page == 0 {
 
-        let mut user_data = GLOBAL_USER_DATA.lock().await;
+        let mut user_data = state.user_data.lock().await;
     
         let list = match user_data.get_list(&list_name).await {
             Ok(list) => { list },
@@ -183,7 +274,7 @@ async fn get_list_paged(list_name: String, sort: String, ascending: bool, page:
             Err(error) => { return Err(error); },
         };
     
-        let mut anime_data = GLOBAL_ANIME_DATA.lock().await;
+        let mut anime_data = state.anime_data.lock().await;
         let anime_list_data = match anime_data.get_anime_list_data(list).await {
             Ok(list) => { 
                 if anime_data.new_anime == true {
@@ -205,7 +296,7 @@ async fn get_list_paged(list_name: String, sort: String, ascending: bool, page:
             combined_list.reverse();
         }
 
-        let mut list_cache = GLOBAL_LIST_DATA_CACHE.lock().await;
+        let mut list_cache = state.list_data_cache.lock().await;
         *list_cache = combined_list;
 
         let end = if 50 >= list_cache.len() {
@@ -217,7 +308,7 @@ async fn get_list_paged(list_name: String, sort: String, ascending: bool, page:
         return Ok(Vec::from_iter(list_cache[0..end].iter().cloned()));
     } else {
 
-        let list_cache = GLOBAL_LIST_DATA_CACHE.lock().await;
+        let list_cache = state.list_data_cache.lock().await;
 
         let start = if page * 50 < list_cache.len() {
             page * 50
@@ -239,13 +330,13 @@ async fn get_list_paged(list_name: String, sort: String, ascending: bool, page:
 
 // get user info for a specific anime
 #[tauri::command]
-async fn get_user_info(id: i32) -> Option<UserInfo> {
+async fn get_user_info(state: State<'_, AppState>, id: i32) -> Result<Option<UserInfo>, ()> {
 
-    match GLOBAL_USER_DATA.lock().await.get_user_data(id) {
+    match state.user_data.lock().await.get_user_data(id) {
         Ok(result) => {
-            return Some(result);
+            return Ok(Some(result));
         },
-        Err(error) => return None,
+        Err(error) => return Ok(None),
     }
 }
 
@@ -253,50 +344,99 @@ async fn get_user_info(id: i32) -> Option<UserInfo> {
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct UpdateDelayInfo {
+    pub media_id: i32,
     pub percent: f64,
+    pub first_episode: i32,
     pub episode: i32,
     pub title: String,
     pub time_remaining: i64,
+    pub runtime: i64, // length of the episode in seconds, 0 if it is unknown
+    pub active_time: i64, // seconds the episode has been playing
+    pub required_time: i64, // seconds that need to be watched before the episode is counted
 }
-// get info on when the currently watching episode will be updated
+
+impl UpdateDelayInfo {
+    fn new(media_id: i32, anime: &WatchingTracking, required: f64) -> UpdateDelayInfo {
+        let watched = anime.watched_time();
+        UpdateDelayInfo {
+            media_id,
+            percent: if required > 0.0 { (watched / required).min(1.0) } else { 1.0 }, 
+            first_episode: anime.episode,
+            episode: anime.episode + (anime.length - 1), 
+            title: anime.title.clone(),
+            time_remaining: (required - watched).max(0.0) as i64,
+            runtime: anime.runtime().unwrap_or_default() as i64,
+            active_time: anime.active_time as i64,
+            required_time: required as i64,
+        }
+    }
+}
+
+// get info on when every episode being watched will be updated, closest to updating first
 #[tauri::command]
-async fn get_delay_info() -> UpdateDelayInfo {
+async fn get_tracked_episodes(state: State<'_, AppState>) -> Result<Vec<UpdateDelayInfo>, ()> {
+    Ok(tracked_episodes(&state).await)
+}
+
+async fn tracked_episodes(state: &AppState) -> Vec<UpdateDelayInfo> {
 
-    let found_anime = WATCHING_TRACKING.lock().await.clone();
+    let user_data = state.user_data.lock().await;
+    let watching_data = state.watching_tracking.lock().await;
+
+    // episodes from a earlier session that aren't open anymore aren't shown
+    let mut tracked: Vec<UpdateDelayInfo> = watching_data.iter()
+        .filter(|(_, anime)| anime.monitoring && anime.cancelled == false)
+        .map(|(media_id, anime)| UpdateDelayInfo::new(*media_id, anime, user_data.get_required_watch_time(anime.runtime())))
+        .collect();
     
-    if found_anime.len() > 0 {
+    tracked.sort_by(|a, b| b.percent.partial_cmp(&a.percent).unwrap_or(std::cmp::Ordering::Equal).then(a.media_id.cmp(&b.media_id)));
+    tracked
+}
 
-        let delay = (GLOBAL_USER_DATA.lock().await.get_update_delay() * constants::SECONDS_IN_MINUTES) as f64;
-        
-        let (_, anime) = found_anime.iter().next().unwrap();
-        
-        return UpdateDelayInfo {
-            percent: anime.timer.elapsed().as_secs_f64() / delay, 
-            episode: anime.episode + (anime.length - 1), 
-            title: anime.title.clone(),
-            time_remaining: (delay as i64) - (anime.timer.elapsed().as_secs() as i64)
-        };
+// get info on when the currently watching episode will be updated
+#[tauri::command]
+async fn get_delay_info(state: State<'_, AppState>) -> Result<UpdateDelayInfo, ()> {
+
+    match tracked_episodes(&state).await.into_iter().next() {
+        Some(info) => Ok(info),
+        None => Ok(UpdateDelayInfo::default()),
     }
+}
 
-    return UpdateDelayInfo::default()
+// stop a episode that is being watched from updating the user's progress
+// the episode won't be tracked again until it's closed and reopened
+#[tauri::command]
+async fn cancel_tracking(state: State<'_, AppState>, media_id: i32) -> Result<bool, ()> {
+
+    let mut watching_data = state.watching_tracking.lock().await;
+    match watching_data.get_mut(&media_id) {
+        Some(entry) => {
+            entry.cancelled = true;
+            file_operations::write_file_watching_tracking(&*state.events, &watching_data).await.ok();
+            state.events.send(UiEvent::TrackingStopped(media_id)).await;
+            Ok(true)
+        },
+        None => Ok(false),
+    }
 }
 
 
 
 // get data for a specific anime
 #[tauri::command]
-async fn get_anime_info(id: i32) -> Option<AnimeInfo> {
+async fn get_anime_info(state: State<'_, AppState>, id: i32) -> Result<Option<AnimeInfo>, ()> {
 
-    let mut anime_data = GLOBAL_ANIME_DATA.lock().await;
+    let mut user_data = state.user_data.lock().await;
+    let mut anime_data = state.anime_data.lock().await;
     match anime_data.get_anime_data(id).await {
         Ok(result) => {
             if anime_data.new_anime == true {
-                GLOBAL_USER_DATA.lock().await.set_max_episodes(anime_data.get_anime_episodes());
+                user_data.set_max_episodes(anime_data.get_anime_episodes());
                 anime_data.new_anime = false;
             }
-            return Some(result);
+            return Ok(Some(result));
         },
-        Err(error) => return None,
+        Err(error) => return Ok(None),
     }
 }
 
@@ -304,15 +444,15 @@ async fn get_anime_info(id: i32) -> Option<AnimeInfo> {
 
 // get data for a specific anime
 #[tauri::command]
-async fn get_manga_info(id: i32) -> Option<MangaInfo> {
+async fn get_manga_info(state: State<'_, AppState>, id: i32) -> Result<Option<MangaInfo>, ()> {
 
-    match api_calls::anilist_get_manga_ln_info(id).await {
+    match api_calls::anilist_get_manga_ln_info(&*graphql_client::default_client(), id).await {
         Ok(result) => {
-            return Some(result);
+            return Ok(Some(result));
         },
         Err(_error) => { 
-            GLOBAL_REFRESH_UI.lock().await.errors.push(String::from("Manga information is missing. No internet connection."));
-            return None; 
+            state.events.send(UiEvent::Error(String::from("Manga information is missing. No internet connection."))).await;
+            return Ok(None); 
         },
     }
 }
@@ -320,7 +460,7 @@ async fn get_manga_info(id: i32) -> Option<MangaInfo> {
 
 // updates a entry on anilist with new information
 #[tauri::command]
-async fn update_user_entry(anime: UserInfo) {
+async fn update_user_entry(state: State<'_, AppState>, anime: UserInfo) -> Result<(), ()> {
 
     println!("main.rs update_user_entry");
 
@@ -328,25 +468,27 @@ async fn update_user_entry(anime: UserInfo) {
         println!("{:?}", anime);
     }
 
-    GLOBAL_USER_DATA.lock().await.set_user_data(anime, true).await;
+    state.user_data.lock().await.set_user_data(anime, true).await;
+    Ok(())
 }
 
 
 
 // changes the custom title of anime with id of anime_id to title
 #[tauri::command]
-async fn set_custom_filename(anime_id: i32, title: String) {
-    GLOBAL_ANIME_DATA.lock().await.set_custom_filename(anime_id, title).await;
+async fn set_custom_filename(state: State<'_, AppState>, anime_id: i32, title: String) -> Result<(), ()> {
+    state.anime_data.lock().await.set_custom_filename(anime_id, title).await;
+    Ok(())
 }
 
 
 
 // returns the custom title set by the user previously, if the custom title or anime doesn't exist a empty string is returned
 #[tauri::command]
-async fn get_custom_filename(anime_id: i32) -> String {
-    match GLOBAL_ANIME_DATA.lock().await.get_custom_filename(anime_id) {
-        Some(title) => return title,
-        None => return String::new(),
+async fn get_custom_filename(state: State<'_, AppState>, anime_id: i32) -> Result<String, ()> {
+    match state.anime_data.lock().await.get_custom_filename(anime_id) {
+        Some(title) => return Ok(title),
+        None => return Ok(String::new()),
     }
 }
 
@@ -354,14 +496,18 @@ async fn get_custom_filename(anime_id: i32) -> String {
 
 // loads data from files and looks for episodes on disk
 #[tauri::command]
-async fn on_startup() {
+async fn on_startup(state: State<'_, AppState>) -> Result<(), ()> {
 
-    let mut user_data = GLOBAL_USER_DATA.lock().await;
+    file_operations::open_database(&*state.events).await;
+
+    let mut user_data = state.user_data.lock().await;
     user_data.read_files().await;
     user_data.pull_updates().await;
 
-    let mut anime_data = GLOBAL_ANIME_DATA.lock().await;
+    let mut anime_data = state.anime_data.lock().await;
     anime_data.read_files().await;
+    file_operations::read_file_watching_tracking(&*state.events, &mut *state.watching_tracking.lock().await).await.ok();
+    state.watch_history.lock().await.read_files().await;
     let episodes = anime_data.get_anime_episodes();
     user_data.set_max_episodes(episodes);
 
@@ -372,40 +518,34 @@ async fn on_startup() {
         }
     }
 
-    *GLOBAL_STARTUP_FINISHED.lock().await = true;
+    *state.startup_finished.lock().await = true;
+    Ok(())
 }
 
 
 
-
-lazy_static! {
-    static ref GLOBAL_SETTINGS_LOADED: Mutex<bool> = Mutex::new(false);
-    static ref GLOBAL_STARTUP_FINISHED: Mutex<bool> = Mutex::new(false);
-}
-
 // opens the file for the next episode in the default program
 #[tauri::command]
-async fn play_next_episode(id: i32) -> Result<(), &'static str> {
+async fn play_next_episode(state: State<'_, AppState>, id: i32) -> Result<(), &'static str> {
     
-    let user_data = GLOBAL_USER_DATA.lock().await;
+    let user_data = state.user_data.lock().await;
     match user_data.get_user_data(id) {
         Ok(user_info) => {
 
             let next_episode = user_info.progress + 1;
 
-            if play_episode(id, next_episode).await == false {
+            if play_episode(&state, id, next_episode).await == false {
                 // if episode location is unknown, search for new episodes and try again
                 let folders = user_data.get_user_settings().folders;
                 // don't interrupt another scan
-                if GLOBAL_REFRESH_UI.lock().await.scan_data.total_folders > 0 {
+                if state.scanning.swap(true, Ordering::SeqCst) {
                     return Ok(());
                 }
-                GLOBAL_REFRESH_UI.lock().await.loading_dialog = Some(String::from("Searching For Episode"));
-                if GLOBAL_ANIME_DATA.lock().await.scan_folders(folders, false, Some(id)).await {
-                    GLOBAL_REFRESH_UI.lock().await.canvas = true;
-                }
-                GLOBAL_REFRESH_UI.lock().await.loading_dialog = None;
-                play_episode(id, next_episode).await;
+                state.events.send(UiEvent::Loading(Some(String::from("Searching For Episode")))).await;
+                state.anime_data.lock().await.scan_folders(folders, false, Some(id)).await;
+                state.scanning.store(false, Ordering::SeqCst);
+                state.events.send(UiEvent::Loading(None)).await;
+                play_episode(&state, id, next_episode).await;
             }
             Ok(())
         },
@@ -417,9 +557,67 @@ async fn play_next_episode(id: i32) -> Result<(), &'static str> {
 
 // play the episode from the anime id
 // returns true if the episode was played
-async fn play_episode(anime_id: i32, episode: i32) -> bool {
+async fn play_episode(state: &AppState, anime_id: i32, episode: i32) -> bool {
     println!("play {} episode {}", anime_id, episode);
-    GLOBAL_ANIME_DATA.lock().await.play_episode(anime_id, episode).await
+    let anime_data = state.anime_data.lock().await;
+    let played = anime_data.play_episode(anime_id, episode).await;
+    if played {
+        // the episode is recorded in the watch history once it's detected as watched
+        if let Some(anime_path) = anime_data.anime_path.get(&anime_id).and_then(|episodes| episodes.get(&episode)) {
+            state.watch_history.lock().await.played_episode(anime_id, episode, anime_path.path.clone());
+        }
+    }
+    played
+}
+
+
+
+// get the episodes watched between two unix times in seconds, newest first
+#[tauri::command]
+async fn get_watch_history(state: State<'_, AppState>, start: u64, end: u64) -> Result<Vec<WatchRecord>, ()> {
+    Ok(state.watch_history.lock().await.get_records_by_date(start, end))
+}
+
+
+
+// get the episodes of a anime that have been watched, newest first
+#[tauri::command]
+async fn get_watch_history_for_anime(state: State<'_, AppState>, media_id: i32) -> Result<Vec<WatchRecord>, ()> {
+    Ok(state.watch_history.lock().await.get_records_by_media(media_id))
+}
+
+
+
+// undo the last episode that was updated from a media player, for when something was detected that wasn't being watched
+#[tauri::command]
+async fn undo_last_auto_credit(state: State<'_, AppState>) -> Result<WatchRecord, &'static str> {
+
+    // user data is locked first, the same order the episode was recorded in
+    let mut user_data = state.user_data.lock().await;
+    let mut watch_history = state.watch_history.lock().await;
+    let record = match watch_history.latest_auto_credit() {
+        Some(record) => record,
+        None => return Err("Nothing to undo"),
+    };
+
+    let mut entry = user_data.get_user_data(record.media_id)?;
+    // the user has changed their progress since, undoing would throw that away
+    if entry.progress != record.last_episode {
+        return Err("Progress has changed since the episode was updated");
+    }
+
+    // restore the entry, including dates and the rewatch count that were set when it was completed
+    entry.progress = record.previous.progress;
+    entry.status = record.previous.status.clone();
+    entry.started_at = record.previous.started_at.clone();
+    entry.completed_at = record.previous.completed_at.clone();
+    entry.repeat = record.previous.repeat;
+    user_data.set_user_data(entry, true).await?;
+    watch_history.remove_record(&record).await;
+
+    state.events.send(UiEvent::ListChanged).await;
+
+    Ok(record)
 }
 
 
@@ -427,52 +625,115 @@ async fn play_episode(anime_id: i32, episode: i32) -> bool {
 // changes the progress for a anime by +-1
 // anilist api call is delayed to prevent spam/locking
 #[tauri::command]
-async fn increment_decrement_episode(anime_id: i32, change: i32) {
+async fn increment_decrement_episode(state: State<'_, AppState>, anime_id: i32, change: i32) -> Result<(), ()> {
+
+    let mut user_data = state.user_data.lock().await;
+    let previous = user_data.get_user_data(anime_id);
+    user_data.increment_episode(anime_id, change).await;
 
-    GLOBAL_USER_DATA.lock().await.increment_episode(anime_id, change).await;
+    // only episodes being watched are recorded, not mistakes being corrected
+    if let Ok(previous) = previous {
+        if change > 0 {
+            state.watch_history.lock().await.add_record(anime_id, previous.progress + 1, previous.progress + change, None, WatchSource::Manual, previous).await;
+        }
+    }
+    Ok(())
 }
 
 
 
 // scan folders for episodes of anime
 #[tauri::command]
-async fn scan_anime_folder() -> bool {
-    if GLOBAL_REFRESH_UI.lock().await.scan_data.total_folders > 0 {
+async fn scan_anime_folder(state: State<'_, AppState>) -> Result<bool, ()> {
+    Ok(scan_for_new_episodes(&state).await)
+}
+
+// returns true if a episode was found
+async fn scan_for_new_episodes(state: &AppState) -> bool {
+    if state.scanning.swap(true, Ordering::SeqCst) {
         return false;
     }
-    let folders = GLOBAL_USER_DATA.lock().await.get_user_settings().folders;
+    let folders = state.user_data.lock().await.get_user_settings().folders;
     // make a copy because scan folders will take a long time
-    let mut anime_data: AnimeData = GLOBAL_ANIME_DATA.lock().await.clone();
+    let mut anime_data: AnimeData = state.anime_data.lock().await.clone();
     anime_data.scan_new_ids(folders.clone()).await;
     let file_found = anime_data.scan_folders(folders, true, None).await;
-    *GLOBAL_ANIME_DATA.lock().await = anime_data;
+    *state.anime_data.lock().await = anime_data;
+    state.scanning.store(false, Ordering::SeqCst);
     file_found
 }
 
 
 
-#[derive(Debug, Clone)]
-struct WatchingTracking {
-    timer: std::time::Instant,
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WatchingTracking {
+    #[serde(skip, default = "Instant::now")]
+    last_check: Instant,
+    active_time: f64, // seconds the file has been playing, or focused when the player can't say if it's playing
+    last_active: u64, // unix time the file was last playing, kept between sessions until it's too old
+    #[serde(default)]
+    cancelled: bool, // the user doesn't want this episode updated
+    #[serde(skip)]
     monitoring: bool,
+    #[serde(skip)]
+    playing: bool,
+    #[serde(skip)]
+    playback: Option<(f64, f64)>, // position and duration of the file in seconds, if the player reports it
+    anime_runtime: Option<f64>, // length of the episodes in the file according to anilist, in seconds
+    #[serde(default)]
+    file_path: String, // full path when a player reported it, otherwise the window title
     episode: i32,
     length: i32,
     title: String,
 }
-lazy_static! {
-    static ref WATCHING_TRACKING: Mutex<HashMap<i32, WatchingTracking>> = Mutex::new(HashMap::new());
+
+impl WatchingTracking {
+
+    // seconds of the file that have been watched
+    fn watched_time(&self) -> f64 {
+        match self.playback {
+            Some((position, _)) => position,
+            None => self.active_time,
+        }
+    }
+
+    // length of the file in seconds, the player knows it better than anilist does
+    fn runtime(&self) -> Option<f64> {
+        self.playback.map(|(_, duration)| duration).or(self.anime_runtime)
+    }
 }
 
 
 
 // get the titles of all active windows
+// empty if the windows can't be listed, like on wayland without x, so media players with a api are still checked
 fn get_titles() -> Vec<String> {
-    let connection = Connection::new();
-    connection.unwrap().window_titles().unwrap()
+    let connection = match Connection::new() {
+        Ok(connection) => connection,
+        Err(_error) => return Vec::new(),
+    };
+    match connection.window_titles() {
+        Ok(titles) => titles,
+        Err(_error) => Vec::new(),
+    }
+}
+
+// get the title of the window the user is looking at
+fn get_focused_title() -> Option<String> {
+    match active_win_pos_rs::get_active_window() {
+        Ok(window) => Some(window.title),
+        Err(_error) => None,
+    }
 }
 
 #[tauri::command]
-async fn anime_update_delay() {
+async fn anime_update_delay(state: State<'_, AppState>) -> Result<(), ()> {
+    detect_playing_episodes(&state).await;
+    Ok(())
+}
+
+// find episodes open in media players and update the user's progress once enough of them was watched
+async fn detect_playing_episodes(state: &AppState) {
 
     let video_files = Regex::new(r"\.mkv|\.avi|\.mp4").unwrap();
 
@@ -482,18 +743,22 @@ async fn anime_update_delay() {
     let mpv_remove = Regex::new(r" - mpv").unwrap();
     let pot_remove = Regex::new(r" - PotPlayer").unwrap();
 
-    let settings = GLOBAL_USER_DATA.lock().await.get_user_settings();
+    let settings = state.user_data.lock().await.get_user_settings();
 
     // get window titles and keep the ones with video files
-    let mut titles: Vec<String> = get_titles(); // for some reason mutex locking has to happen before this function
+    let mut titles: Vec<String> = get_titles();
     titles.retain(|title| video_files.is_match(title));
+    let focused_title = get_focused_title();
 
-    let anime_data = GLOBAL_ANIME_DATA.lock().await;
-    let mut user_data = GLOBAL_USER_DATA.lock().await;
-    let mut watching_data = WATCHING_TRACKING.lock().await;
+    // files open in media players, their path, if they are playing, the playback position, and if the player reported whether it is playing
+    let mut open_files: Vec<(String, String, bool, Option<(f64, f64)>, bool)> = Vec::new();
 
-    // reset monitoring
-    watching_data.iter_mut().for_each(|entry| entry.1.monitoring = false);
+    // media players with a api report the file they have open, how far into it they are, and if it is paused
+    for media in media_players::get_playing_media(&settings).await {
+        if let Some(file_name) = Path::new(&media.file_path).file_name() {
+            open_files.push((file_name.to_string_lossy().to_string(), media.file_path.clone(), media.playing, media.playback(), true));
+        }
+    }
 
     for title in titles {
 
@@ -504,118 +769,331 @@ async fn anime_update_delay() {
         title_edit = mpv_remove.replace(&title_edit, "").to_string();
         title_edit = pot_remove.replace(&title_edit, "").to_string();
 
-        if let Some(identify_info) = anime_data.identify_anime(title_edit, None) {
+        // window titles can't tell if the file is paused, count it as playing while the user is looking at it
+        let focused = focused_title.as_ref() == Some(&title);
+        open_files.push((title_edit.clone(), title_edit, focused, None, false));
+    }
+
+    let mut user_data = state.user_data.lock().await;
+    let anime_data = state.anime_data.lock().await;
+    let mut watching_data = state.watching_tracking.lock().await;
+
+    // reset monitoring
+    let had_entries = watching_data.len() > 0;
+    let was_tracking = tracking_ids(&watching_data);
+    watching_data.iter_mut().for_each(|(_, entry)| {
+        entry.monitoring = false;
+        entry.playing = false;
+        entry.playback = None;
+    });
+
+    // anime a player reported the playback state for, window titles of the same anime are ignored
+    let mut playback_known: HashSet<i32> = HashSet::new();
+    for (file_name, file_path, playing, playback, reports_playback) in open_files {
+
+        if let Some(identify_info) = anime_data.identify_anime(file_name, None) {
+
+            if reports_playback {
+                playback_known.insert(identify_info.media_id);
+            } else if playback_known.contains(&identify_info.media_id) {
+                continue;
+            }
 
             if let Ok(user_entry) = user_data.get_user_data(identify_info.media_id) {
 
                 let next_episode: bool = identify_info.episode > user_entry.progress && identify_info.episode <= user_entry.progress + identify_info.episode_length;
 
-                // if the file is being monitored and the episode is the next episode
-                if let Some(entry) = watching_data.get_mut(&identify_info.media_id) {
-                    if next_episode && entry.episode == identify_info.episode {
-                        entry.monitoring = true;
-                    }
-                // only add if it is in the users list, it is the next episode, and the episode is within range
-                } else if next_episode && identify_info.episode > 0 {
+                let tracked = watching_data.get(&identify_info.media_id).map(|entry| (entry.episode, entry.monitoring));
+                match tracked {
+                    // if the file is being monitored and the episode is the next episode
+                    Some((episode, _)) if episode == identify_info.episode => {
+                        if next_episode {
+                            let entry = watching_data.get_mut(&identify_info.media_id).unwrap();
+                            // a file can be open in a player and have a window title, playing in either counts
+                            entry.playing = entry.playing || playing;
+                            entry.monitoring = true;
+                            if playback.is_some() {
+                                entry.playback = playback;
+                            }
+                            if reports_playback {
+                                entry.file_path = file_path;
+                            }
+                        }
+                    },
+                    // another episode of this anime is being watched right now
+                    Some((_, true)) => {},
+                    // only add if it is in the users list, it is the next episode, and the episode is within range
+                    // this replaces a episode from a earlier session that was never finished
+                    _ => if next_episode && identify_info.episode > 0 {
         
-                    watching_data.insert(identify_info.media_id, WatchingTracking { timer: std::time::Instant::now(), monitoring: true, episode: identify_info.episode, length: identify_info.episode_length, title: identify_info.media_title});
+                        let anime_runtime = anime_data.data.get(&identify_info.media_id)
+                            .and_then(|anime| anime.duration)
+                            .map(|minutes| (minutes * constants::SECONDS_IN_MINUTES * identify_info.episode_length) as f64);
+                        watching_data.insert(identify_info.media_id, WatchingTracking { last_check: Instant::now(), active_time: 0.0, last_active: 0, cancelled: false, monitoring: true, playing, playback, anime_runtime, file_path, episode: identify_info.episode, length: identify_info.episode_length, title: identify_info.media_title});
+                    },
                 }
             }
         }
     }
     
-    let delay = (settings.update_delay * constants::SECONDS_IN_MINUTES)  as u64;
-    // check if media has been playing for long enough to update
+    // only time spent playing counts as watched, the gap is limited so the computer sleeping isn't counted
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    for (_, tracking_info) in watching_data.iter_mut() {
+        if tracking_info.monitoring && tracking_info.playing && tracking_info.cancelled == false {
+            tracking_info.active_time += tracking_info.last_check.elapsed().as_secs_f64().min(constants::MAX_WATCH_TIME_GAP);
+            tracking_info.last_active = now;
+        }
+        tracking_info.last_check = Instant::now();
+    }
+    
+    // check if enough of the episode has been watched to update, the required time depends on the completion policy and the episode's runtime
+    // paused episodes are not counted as watched unless the player reported the position is past the required time
+    let mut finished_ids: Vec<i32> = Vec::new();
     for (media_id, tracking_info) in watching_data.iter_mut() {
-        let seconds = tracking_info.timer.elapsed().as_secs();
-        if seconds >= delay {
+        let required = user_data.get_required_watch_time(tracking_info.runtime());
+        let finished = tracking_info.monitoring && tracking_info.cancelled == false && tracking_info.watched_time() >= required && (tracking_info.playing || tracking_info.playback.is_some());
+        if finished {
             // user progress will be updated to this episode so we no longer want to monitor it
-            tracking_info.monitoring = false;
+            finished_ids.push(*media_id);
             
             // update anime
+            let previous = user_data.get_user_data(*media_id);
             user_data.increment_episode(*media_id, tracking_info.length).await;
-
-            // update ui with episode progress
-            GLOBAL_REFRESH_UI.lock().await.canvas = true;
+            if let Ok(previous) = previous {
+                state.watch_history.lock().await.add_record(*media_id, previous.progress + 1, previous.progress + tracking_info.length, 
+                    Some(tracking_info.file_path.clone()), WatchSource::AutoDetected, previous).await;
+            }
         }
     }
 
-    // remove episodes that are no longer being played or have been played long enough
-    watching_data.retain(|_, v| v.monitoring == true);
+    // remove episodes that have been played long enough, or haven't been played in a long time
+    // episodes that were closed part way through are kept so the timer continues if they are opened again, unless tracking was cancelled
+    watching_data.retain(|media_id, v| finished_ids.contains(media_id) == false && (v.monitoring || (v.cancelled == false && now - v.last_active.min(now) < constants::WATCH_TIME_EXPIRY)));
+
+    // tell the ui which episodes started or stopped being watched so it only shows progress while something is tracked
+    let tracking = tracking_ids(&watching_data);
+    for media_id in tracking.difference(&was_tracking) {
+        state.events.send(UiEvent::TrackingStarted(*media_id)).await;
+    }
+    for media_id in was_tracking.difference(&tracking) {
+        state.events.send(UiEvent::TrackingStopped(*media_id)).await;
+    }
+
+    if had_entries || watching_data.len() > 0 {
+        file_operations::write_file_watching_tracking(&*state.events, &watching_data).await.ok();
+    }
 
 }
 
 
 
-// allows the ui to check if a anime has been updated to determine if the ui will be refreshed
+// anime with a episode open in a media player that will update the user's progress
+fn tracking_ids(watching_data: &HashMap<i32, WatchingTracking>) -> HashSet<i32> {
+    watching_data.iter()
+        .filter(|(_, entry)| entry.monitoring && entry.cancelled == false)
+        .map(|(media_id, _)| *media_id)
+        .collect()
+}
+
+
+
+// what the ui needs to show when it opens, the rest is sent as events
 #[tauri::command]
-async fn refresh_ui() -> RefreshUI {
-    let length = WATCHING_TRACKING.lock().await.len();
-    let mut refresh = GLOBAL_REFRESH_UI.lock().await;
-    let mut refresh_ui = refresh.clone();
-    refresh_ui.tracking_progress = length > 0;
-    refresh.anime_list = false;
-    refresh.canvas = false;
+async fn get_ui_status(state: State<'_, AppState>) -> Result<UiStatus, ()> {
+    Ok(state.ui_events.ui_status().await)
+}
+
+
+
+// send changes that were made while anilist could not be reached
+async fn check_queued_updates(state: &AppState) -> Result<(), &'static str> {
 
-    refresh_ui
+    let mut user_data = state.user_data.lock().await;
+    let sent = user_data.check_queued_updates().await?;
+    if sent > 0 {
+        state.events.send(UiEvent::ListChanged).await;
+    }
+    Ok(())
 }
 
 
 
-#[tauri::command]
-async fn clear_errors() {
-    GLOBAL_REFRESH_UI.lock().await.errors.clear();
+// download changes made on the website since the last sync
+async fn pull_updates(state: &AppState) -> Result<(), &'static str> {
+
+    state.user_data.lock().await.pull_updates().await?;
+    Ok(())
+}
+
+
+
+// remove anime that were deleted from the user's list on the website
+async fn remove_deleted_entries(state: &AppState) -> Result<(), &'static str> {
+
+    let removed = state.user_data.lock().await.remove_deleted_entries().await?;
+    if removed.is_empty() == false {
+        state.events.send(UiEvent::ListChanged).await;
+        state.events.send(UiEvent::Error(format!("{} anime were removed because they were deleted from your list on the website", removed.len()))).await;
+    }
+    Ok(())
+}
+
+
+
+// get airing times for the anime on the user's list that are airing
+async fn refresh_airing_times(state: &AppState) -> Result<(), &'static str> {
+
+    let media_ids = state.user_data.lock().await.all_ids();
+    if state.anime_data.lock().await.refresh_airing_times(&media_ids).await? > 0 {
+        state.events.send(UiEvent::ListChanged).await;
+    }
+    Ok(())
+}
+
+
+
+// download the info of the anime on the user's list again, anime data is unlocked before user data is locked again
+async fn refresh_anime_info(state: &AppState) -> Result<(), &'static str> {
+
+    let media_ids = state.user_data.lock().await.all_ids();
+    let episodes = {
+        let mut anime_data = state.anime_data.lock().await;
+        if anime_data.refresh_anime_info(&media_ids).await? == 0 {
+            return Ok(());
+        }
+        anime_data.get_anime_episodes()
+    };
+    state.user_data.lock().await.set_max_episodes(episodes);
+    state.events.send(UiEvent::ListChanged).await;
+    Ok(())
 }
 
 
-lazy_static! {
-    static ref SCAN_TIMER: Mutex<Instant> = Mutex::new(Instant::now());
-    static ref NO_INTERNET_TIMER: Mutex<Instant> = Mutex::new(Instant::now());
-    static ref STARTUP_SCAN: Mutex<bool> = Mutex::new(false);
+
+// check if the website can be reached after a request couldn't reach it, changes waiting for it are sent right away
+async fn check_connectivity(state: &AppState) -> Result<(), &'static str> {
+
+    if state.ui_events.online().await {
+        return Ok(());
+    }
+    let anilist = state.anime_data.lock().await.anilist.clone();
+    if api_calls::anilist_ping(&*anilist).await == false {
+        return Err("no connection");
+    }
+    state.events.send(UiEvent::Connectivity(true)).await;
+    check_queued_updates(state).await
 }
-// performs periodic tasks like checking for anime in media players, delayed updates that must be sent, scanning folders for files
-// it's expected that this function will be called periodically from the UI, it won't loop on its own
+
+
+
+// returns anime that were changed on this computer and on the website, the user chooses which change to keep
 #[tauri::command]
-async fn background_tasks() {
-    
-    // check for anime in media players
-    anime_update_delay().await;
-    // update anilist with offline updates
-    //check_queued_updates().await;
+async fn get_sync_conflicts(state: State<'_, AppState>) -> Result<Vec<SyncConflict>, ()> {
+    Ok(state.user_data.lock().await.get_conflicts())
+}
 
-    // do a full scan for anime recently added to program
-    //file_name_recognition::parse_file_names(media_id);
 
-    // scan files for new episodes of anime every hour and a short time after startup
-    let mut on_startup_scan_completed = STARTUP_SCAN.lock().await;
-    let mut timer = SCAN_TIMER.lock().await;
-    if timer.elapsed() > Duration::from_secs(constants::ONE_HOUR) || 
-        (timer.elapsed() >= Duration::from_secs(constants::STARTUP_SCAN_DELAY) && *on_startup_scan_completed == false) {
 
-        if scan_anime_folder().await {
+// keep this computer's change or the website's change for a conflicting anime
+#[tauri::command]
+async fn resolve_sync_conflict(state: State<'_, AppState>, media_id: i32, keep_local: bool) -> Result<(), &'static str> {
+    state.user_data.lock().await.resolve_conflict(media_id, keep_local).await
+}
+
+
+
+// run a job on its interval until gekijou is closed, the interval is read again after each run so changed settings are used
+async fn run_job(state: &AppState, job: Job) {
 
-            GLOBAL_REFRESH_UI.lock().await.canvas = true;
+    let mut interval = state.user_data.lock().await.get_job_interval(job);
+    let mut timer = job_timer(job.first_delay(interval.unwrap_or(Duration::from_secs(constants::DISABLED_JOB_CHECK_INTERVAL))), interval);
+    state.scheduler.lock().await.scheduled(job, interval, timer.period());
+    loop {
+        timer.tick().await;
+
+        let current_interval = state.user_data.lock().await.get_job_interval(job);
+        if current_interval != interval {
+            interval = current_interval;
+            timer = job_timer(timer_period(interval), interval);
+            state.scheduler.lock().await.scheduled(job, interval, timer.period());
+            continue;
+        }
+        if interval.is_none() {
+            continue;
         }
-        *on_startup_scan_completed = true;
-        *timer = Instant::now();
+
+        state.scheduler.lock().await.started(job);
+        let result = run_job_once(state, job).await;
+        if let Err(error) = result {
+            println!("{:?}: {}", job, error);
+        }
+        let mut scheduler = state.scheduler.lock().await;
+        scheduler.finished(job, result);
+        scheduler.scheduled(job, interval, timer.period());
     }
 }
 
+// a job that was turned off still wakes up to check if it was turned back on
+fn timer_period(interval: Option<Duration>) -> Duration {
+    interval.unwrap_or(Duration::from_secs(constants::DISABLED_JOB_CHECK_INTERVAL))
+}
+
+// ticks after first_delay then every interval, a run that takes too long delays the next one instead of running twice in a row
+fn job_timer(first_delay: Duration, interval: Option<Duration>) -> tokio::time::Interval {
+    let mut timer = tokio::time::interval_at(tokio::time::Instant::now() + first_delay, timer_period(interval));
+    timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    timer
+}
+
+async fn run_job_once(state: &AppState, job: Job) -> Result<(), &'static str> {
+    match job {
+        Job::PlayerDetection => {
+            detect_playing_episodes(state).await;
+            Ok(())
+        },
+        Job::LibraryRescan => {
+            scan_for_new_episodes(state).await;
+            Ok(())
+        },
+        Job::AiringRefresh => refresh_airing_times(state).await,
+        // the connectivity probe sends them once the website can be reached again
+        Job::QueueFlush => if state.ui_events.online().await {
+            check_queued_updates(state).await
+        } else {
+            Ok(())
+        },
+        Job::ConnectivityProbe => check_connectivity(state).await,
+        Job::CacheRefresh => refresh_anime_info(state).await,
+        // the list is pulled once on startup
+        Job::ListSync => pull_updates(state).await,
+        // pull_updates only sees anime that still exist
+        Job::DeletionCheck => remove_deleted_entries(state).await,
+    }
+}
+
+
+
+// when each background job last ran, how long it took, and if it failed
+#[tauri::command]
+async fn get_job_status(state: State<'_, AppState>) -> Result<Vec<JobStatus>, ()> {
+    Ok(state.scheduler.lock().await.statuses())
+}
+
 
 
 // returns a list of what episodes of what anime exist on disk
 #[tauri::command]
-async fn episodes_exist() -> HashMap<i32, Vec<i32>> {
-    GLOBAL_ANIME_DATA.lock().await.get_existing_files_all_anime()
+async fn episodes_exist(state: State<'_, AppState>) -> Result<HashMap<i32, Vec<i32>>, ()> {
+    Ok(state.anime_data.lock().await.get_existing_files_all_anime())
 }
 
 
 
 // returns a list of all episodes on disk for a anime
 #[tauri::command]
-async fn episodes_exist_single(id: i32) -> Vec<i32> {
+async fn episodes_exist_single(state: State<'_, AppState>, id: i32) -> Result<Vec<i32>, ()> {
     
-    GLOBAL_ANIME_DATA.lock().await.get_existing_files(id)
+    Ok(state.anime_data.lock().await.get_existing_files(id))
 }
 
 
@@ -623,78 +1101,58 @@ async fn episodes_exist_single(id: i32) -> Vec<i32> {
 // returns a list of anime based on filters and sorting order
 // anime in user's list does not matter and user login is not used
 #[tauri::command]
-async fn browse(year: String, season: String, genre: String, format: String, search: String, order: String, page: usize) -> Result<(Vec<AnimeInfo>, bool), &'static str> {
+async fn browse(state: State<'_, AppState>, year: String, season: String, genre: String, format: String, search: String, order: String, page: usize) -> Result<(Vec<AnimeInfo>, bool), &'static str> {
 
-    let mut list: Vec<AnimeInfo> = Vec::new();
-    let mut has_next_page = true;
-
-    
-    match api_calls::anilist_browse_call(page, year.clone(), season.clone(), genre.clone(), format.clone(), search.clone(), order.clone()).await {
-        Ok(response) =>  {
-
-            // add anime to return list and global data for further uses
-            for anime in response["data"]["Page"]["media"].as_array().unwrap() {
-                //println!("{:?}", anime);
-                let anime_entry: AnimeInfo = serde_json::from_value(anime.clone()).unwrap();
-                list.push(anime_entry);
-            }
-    
-            // check if a next page exists
-            if let Some(response_next_page) = response["data"]["Page"]["pageInfo"]["hasNextPage"].as_bool() {
-                has_next_page = response_next_page;
-            } else {
-                has_next_page = false;
-            }
-        },
+    match api_calls::anilist_browse_call(&*graphql_client::default_client(), page, year.clone(), season.clone(), genre.clone(), format.clone(), search.clone(), order.clone()).await {
+        Ok((list, has_next_page)) => Ok((list, has_next_page)),
         Err(error) => { 
-            GLOBAL_REFRESH_UI.lock().await.no_internet = true;
-            GLOBAL_REFRESH_UI.lock().await.errors.push(String::from("Cannot browse anilist. No internet connection."));
-            return Err(error);
+            state.events.send(UiEvent::Connectivity(false)).await;
+            state.events.send(UiEvent::Error(String::from("Cannot browse anilist. No internet connection."))).await;
+            Err(error)
         },
     }
-
-    
-    Ok((list,has_next_page))
 }
 
 
 
 // add anime to the users list (not for moving anime)
 #[tauri::command]
-async fn add_to_list(id: i32, list: String) {
+async fn add_to_list(state: State<'_, AppState>, id: i32, list: String) -> Result<(), ()> {
 
     let mut user_anime = UserInfo::default();
     user_anime.media_id = id;
     user_anime.status = list;
 
-    update_user_entry(user_anime).await;
+    update_user_entry(state.clone(), user_anime).await?;
 
-    GLOBAL_ANIME_DATA.lock().await.add_id_for_scanning(id);
+    state.anime_data.lock().await.add_id_for_scanning(id);
+    Ok(())
 }
 
 
 
 // removes anime from the users list
 #[tauri::command]
-async fn remove_anime(media_id: i32) -> Result<bool, &'static str> {
+async fn remove_anime(state: State<'_, AppState>, media_id: i32) -> Result<bool, &'static str> {
 
-    GLOBAL_USER_DATA.lock().await.remove_anime(media_id).await
+    state.user_data.lock().await.remove_anime(media_id).await
 }
 
 
 
 // sets the highlight color
 #[tauri::command]
-async fn set_highlight(color: String) {
-    GLOBAL_USER_DATA.lock().await.set_highlight(color);
+async fn set_highlight(state: State<'_, AppState>, color: String) -> Result<(), ()> {
+    state.user_data.lock().await.set_highlight(color);
+    Ok(())
 }
 
 
 
 // returns highlight color from user settings
 #[tauri::command]
-async fn get_highlight() -> String {
-    GLOBAL_USER_DATA.lock().await.get_highlight()
+async fn get_highlight(state: State<'_, AppState>) -> Result<String, ()> {
+    Ok(state.user_data.lock().await.get_highlight())
 }
 
 
@@ -702,9 +1160,9 @@ async fn get_highlight() -> String {
 // returns a list of rss entries from nyaa.si for a given anime
 // id is the anilist id of the anime being searched for
 #[tauri::command]
-async fn get_torrents(id: i32) -> Vec<RssEntry> {
+async fn get_torrents(state: State<'_, AppState>, id: i32) -> Result<Vec<RssEntry>, ()> {
 
-    rss_parser::get_rss(id).await
+    Ok(rss_parser::get_rss(&state.anime_data, id).await)
 }
 
 
@@ -713,13 +1171,13 @@ async fn get_torrents(id: i32) -> Vec<RssEntry> {
 // mode changes what anime is used for recommendations, user recommendations will use any anime and other modes will limit anime to types of relations to completed anime
 // filters will remove anime that do not match the filter
 #[tauri::command]
-async fn recommend_anime(mode: String, genre_filter: String, year_min_filter: i32, year_max_filter: i32, format_filter: String) -> Vec<AnimeInfo> {
+async fn recommend_anime(state: State<'_, AppState>, mode: String, genre_filter: String, year_min_filter: i32, year_max_filter: i32, format_filter: String) -> Result<Vec<AnimeInfo>, ()> {
 
-    let mut user_data = GLOBAL_USER_DATA.lock().await;
+    let mut user_data = state.user_data.lock().await;
     let completed_scores = user_data.get_scores_from_list(String::from("COMPLETED"));
     let user_anime = user_data.all_ids();
     let score_format = user_data.get_user_settings().score_format;
-    let mut anime_data = GLOBAL_ANIME_DATA.lock().await;
+    let mut anime_data = state.anime_data.lock().await;
     let ids = anime_data.recommendations(completed_scores, user_anime, score_format, mode, genre_filter, year_min_filter, year_max_filter, format_filter).await;
     let anime = anime_data.get_anime_list_data(ids).await;
 
@@ -730,11 +1188,11 @@ async fn recommend_anime(mode: String, genre_filter: String, year_min_filter: i3
     
     match anime {
         Ok(anime_list) => {
-            return anime_list;
+            return Ok(anime_list);
         },
         Err(error) => { 
             println!("{}", error);
-            return Vec::new();
+            return Ok(Vec::new());
         },
     }
 }
@@ -755,13 +1213,13 @@ async fn open_url(url: String) {
 // returns all ids in the users list
 // None is returned if list does not exist
 #[tauri::command]
-async fn get_list_ids(list: String) -> Option<Vec<i32>> {
+async fn get_list_ids(state: State<'_, AppState>, list: String) -> Result<Option<Vec<i32>>, ()> {
 
-    match GLOBAL_USER_DATA.lock().await.get_list(&list).await {
+    match state.user_data.lock().await.get_list(&list).await {
         Ok(result) => {
-            return Some(result);
+            return Ok(Some(result));
         },
-        Err(error) => return None,
+        Err(error) => return Ok(None),
     }
 }
 
@@ -770,10 +1228,57 @@ async fn get_list_ids(list: String) -> Option<Vec<i32>> {
 // runs tests on recognizing filenames and returns the results
 // returns nothing if the program is not compiled as debug
 #[tauri::command]
-async fn run_filename_tests() -> Vec<FilenameTest> {
+async fn run_filename_tests(state: State<'_, AppState>) -> Result<Vec<FilenameTest>, ()> {
 
     if constants::DEBUG {
-        return file_name_recognition_tests::filename_tests().await;
+        return Ok(file_name_recognition_tests::filename_tests(&state.anime_data).await);
+    }
+    Ok(Vec::new())
+}
+
+
+
+// loads saved files from every previous format and returns if they could be upgraded
+// returns nothing if the program is not compiled as debug
+#[tauri::command]
+async fn run_schema_tests() -> Vec<SchemaTest> {
+
+    if constants::DEBUG {
+        return schema_tests::schema_tests().await;
+    }
+    Vec::new()
+}
+
+
+
+// reads responses recorded from anilist and returns if they were understood, then runs the anilist flows against them
+// returns nothing if the program is not compiled as debug
+#[tauri::command]
+async fn run_response_tests() -> Vec<ResponseTest> {
+
+    if constants::DEBUG {
+        let mut test_results = anilist_response_tests::anilist_response_tests().await;
+        #[cfg(debug_assertions)]
+        test_results.extend(anilist_client_tests::anilist_client_tests().await);
+        test_results.extend(mal_tests::mal_tests().await);
+        return test_results;
+    }
+    Vec::new()
+}
+
+
+
+// checks the code that reads and writes files other programs or computers use against fixtures
+// returns nothing if the program is not compiled as debug
+#[tauri::command]
+async fn run_fixture_tests() -> Vec<ResponseTest> {
+
+    if constants::DEBUG {
+        let mut test_results = data_archive_tests::archive_path_tests();
+        test_results.extend(list_export_tests::list_export_tests());
+        #[cfg(all(debug_assertions, target_os = "linux"))]
+        test_results.extend(mpris_tests::mpris_tests().await);
+        return test_results;
     }
     Vec::new()
 }
@@ -791,44 +1296,246 @@ async fn get_debug() -> bool {
 
 // clears all user data from memory and disk
 #[tauri::command]
-async fn delete_data() -> bool {
+async fn delete_data(state: State<'_, AppState>) -> Result<bool, ()> {
+
+    state.user_data.lock().await.clear();
+    state.anime_data.lock().await.clear();
+
+    state.watching_tracking.lock().await.clear();
+    state.watch_history.lock().await.clear();
+
+    Ok(file_operations::delete_data())
+}
+
+
+
+// returns true if the user hasn't logged in and their list is only kept on this computer
+#[tauri::command]
+async fn is_local_list(state: State<'_, AppState>) -> Result<bool, ()> {
+    Ok(state.user_data.lock().await.is_local())
+}
+
+
+
+// uploads the list kept without a account to the website the user logged into
+#[tauri::command]
+async fn attach_account(state: State<'_, AppState>, username: String) -> Result<AttachResult, &'static str> {
 
-    GLOBAL_USER_DATA.lock().await.clear();
-    GLOBAL_ANIME_DATA.lock().await.clear();
+    let mut user_data = state.user_data.lock().await;
+    let result = user_data.attach_account(username).await?;
 
-    GLOBAL_REFRESH_UI.lock().await.clear();
-    GLOBAL_UPDATE_ANIME_DELAYED.lock().await.clear();
-    WATCHING_TRACKING.lock().await.clear();
+    // anime from the website's list may not be cached yet
+    let media_ids: Vec<i32> = user_data.all_ids().into_iter().collect();
+    let mut anime_data = state.anime_data.lock().await;
+    if let Err(error) = anime_data.get_anime_list_data(media_ids).await {
+        println!("attach_account: {}", error);
+    }
+    if anime_data.new_anime == true {
+        user_data.set_max_episodes(anime_data.get_anime_episodes());
+        anime_data.new_anime = false;
+    }
+
+    Ok(result)
+}
+
+
+
+// saves the user's settings, list, episode locations, custom titles, and watch history to a single file
+// the login is only saved if include_token is true
+#[tauri::command]
+async fn export_data(state: State<'_, AppState>, path: String, include_token: bool) -> Result<(), String> {
+
+    let mut archive = DataArchive::default();
+    archive.exported_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+    let folders = {
+        let user_data = state.user_data.lock().await;
+        user_data.export_archive(&mut archive, include_token);
+        user_data.get_user_settings().folders
+    };
+    state.anime_data.lock().await.export_archive(&mut archive, &folders);
+    archive.watch_history = state.watch_history.lock().await.get_records_by_date(0, u64::MAX);
 
-    file_operations::delete_data()
+    file_operations::write_archive(&archive, &path).map_err(|error| error.to_string())
+}
+
+
+
+// adds the data from a file made by export_data, conflict decides which data is kept when both have it
+#[tauri::command]
+async fn import_data(state: State<'_, AppState>, path: String, conflict: ImportConflict) -> Result<ImportSummary, String> {
+
+    let archive = file_operations::read_archive(&path).map_err(|error| error.to_string())?;
+    let mut summary = ImportSummary::default();
+
+    state.events.send(UiEvent::Loading(Some(String::from("Importing Data")))).await;
+
+    {
+        let mut user_data = state.user_data.lock().await;
+        let media_ids = user_data.import_archive(&archive, conflict, &mut summary).await;
+        let folders = user_data.get_user_settings().folders;
+
+        // anime from the archive may not be cached on this computer
+        let mut anime_data = state.anime_data.lock().await;
+        if let Err(error) = anime_data.get_anime_list_data(media_ids).await {
+            println!("import_data: {}", error);
+        }
+        user_data.set_max_episodes(anime_data.get_anime_episodes());
+        anime_data.new_anime = false;
+        anime_data.import_archive(&archive, &folders, conflict, &mut summary).await;
+    }
+
+    summary.watch_records = state.watch_history.lock().await.import_records(&archive.watch_history).await;
+
+    state.events.send(UiEvent::Loading(None)).await;
+    Ok(summary)
+}
+
+
+
+// saves the user's list in myanimelist's xml export format, as csv, or as json shaped like anilist's list entries
+// returns the number of anime saved, myanimelist's format leaves out anime that aren't on myanimelist
+#[tauri::command]
+async fn export_list(state: State<'_, AppState>, path: String, format: String) -> Result<usize, String> {
+
+    if list_export::EXPORT_FORMATS.contains(&format.as_str()) == false {
+        return Err(String::from("invalid format"));
+    }
+
+    let (user_entries, settings) = {
+        let user_data = state.user_data.lock().await;
+        let media_ids: Vec<i32> = user_data.all_ids().into_iter().collect();
+        (user_data.get_data(&media_ids).await.unwrap_or_default(), user_data.get_user_settings())
+    };
+
+    let mut entries: Vec<(UserInfo, Option<AnimeInfo>)> = {
+        let mut anime_data = state.anime_data.lock().await;
+        // myanimelist ids and titles come from the anime's data
+        if let Err(error) = anime_data.get_anime_list_data(user_entries.iter().map(|entry| entry.media_id).collect()).await {
+            println!("export_list: {}", error);
+        }
+        user_entries.into_iter().map(|entry| {
+            let anime = anime_data.data.get(&entry.media_id).cloned();
+            (entry, anime)
+        }).collect()
+    };
+    entries.sort_by_key(|(entry, _)| entry.media_id);
+
+    let (contents, count) = match format.as_str() {
+        "MAL_XML" => {
+            let count = entries.iter().filter(|(_, anime)| anime.as_ref().map_or(false, |anime| anime.id_mal.is_some())).count();
+            (list_export::to_mal_xml(&entries, &settings.username, &settings.score_format), count)
+        },
+        "CSV" => (list_export::to_csv(&entries), entries.len()),
+        _ => (list_export::to_anilist_json(&entries).to_string(), entries.len()),
+    };
+
+    file_operations::write_export(&path, &contents).map_err(|error| error.to_string())?;
+    Ok(count)
+}
+
+
+
+// adds the anime from a myanimelist xml export to the user's list, they're matched to anilist using their myanimelist id
+// anime already on the list are only replaced if overwrite is true
+#[tauri::command]
+async fn import_mal_list(state: State<'_, AppState>, path: String, overwrite: bool) -> Result<ListImportSummary, String> {
+
+    let contents = file_operations::read_export(&path).map_err(|error| error.to_string())?;
+    let mal_entries = list_export::parse_mal_xml(contents)?;
+    let mut summary = ListImportSummary::default();
+
+    state.events.send(UiEvent::Loading(Some(String::from("Importing List")))).await;
+
+    let mut user_data = state.user_data.lock().await;
+    let mut anime_data = state.anime_data.lock().await;
+
+    // anime in the cache already know their myanimelist id
+    let mut id_map: HashMap<i32, i32> = anime_data.data.values()
+        .filter_map(|anime| anime.id_mal.map(|mal_id| (mal_id, anime.id)))
+        .collect();
+    let unknown_ids: Vec<i32> = mal_entries.iter().map(|entry| entry.mal_id).filter(|mal_id| id_map.contains_key(mal_id) == false).collect();
+    if unknown_ids.is_empty() == false {
+        match api_calls::anilist_ids_from_mal_ids(&*anime_data.anilist, unknown_ids).await {
+            Ok(ids) => id_map.extend(ids),
+            Err(error) => {
+                state.events.send(UiEvent::Loading(None)).await;
+                return Err(error.to_string());
+            },
+        }
+    }
+
+    let score_format = user_data.get_user_settings().score_format;
+    let mut entries: Vec<UserInfo> = Vec::new();
+    for mal_entry in mal_entries {
+        let media_id = match id_map.get(&mal_entry.mal_id) {
+            Some(media_id) => *media_id,
+            None => {
+                summary.not_found += 1;
+                continue;
+            },
+        };
+        entries.push(UserInfo {
+            id: 0,
+            media_id,
+            status: mal_entry.status,
+            score: list_export::score_from_mal(mal_entry.score, &score_format),
+            progress: mal_entry.progress,
+            started_at: mal_entry.started_at,
+            completed_at: mal_entry.completed_at,
+            notes: mal_entry.notes,
+            updated_at: 0,
+            repeat: mal_entry.repeat,
+            private: false,
+            hidden_from_status_lists: false,
+            custom_lists: Vec::new(),
+            advanced_scores: HashMap::new(),
+        });
+    }
+
+    let media_ids: Vec<i32> = entries.iter().map(|entry| entry.media_id).collect();
+    user_data.import_entries(entries, overwrite, &mut summary).await;
+
+    if let Err(error) = anime_data.get_anime_list_data(media_ids).await {
+        println!("import_mal_list: {}", error);
+    }
+    if anime_data.new_anime == true {
+        user_data.set_max_episodes(anime_data.get_anime_episodes());
+        anime_data.new_anime = false;
+    }
+
+    state.events.send(UiEvent::Loading(None)).await;
+    Ok(summary)
 }
 
 
 
 // returns if startup tasks are finished.  Data will be missing if startup is not completed
 #[tauri::command]
-async fn startup_finished() -> bool {
-    return *GLOBAL_STARTUP_FINISHED.lock().await;
+async fn startup_finished(state: State<'_, AppState>) -> Result<bool, ()> {
+    return Ok(*state.startup_finished.lock().await);
 }
 
 
 
 #[tauri::command]
-async fn manual_scan() {
-    if GLOBAL_REFRESH_UI.lock().await.scan_data.total_folders > 0 {
-        return;
+async fn manual_scan(state: State<'_, AppState>) -> Result<(), ()> {
+    if state.scanning.swap(true, Ordering::SeqCst) {
+        return Ok(());
     }
-    let folders = GLOBAL_USER_DATA.lock().await.get_user_settings().folders;
+    let folders = state.user_data.lock().await.get_user_settings().folders;
     // make a copy because scan folders will take a long time
-    let mut anime_data: AnimeData = GLOBAL_ANIME_DATA.lock().await.clone();
+    let mut anime_data: AnimeData = state.anime_data.lock().await.clone();
     anime_data.scan_folders(folders, false, None).await;
-    *GLOBAL_ANIME_DATA.lock().await = anime_data;
+    *state.anime_data.lock().await = anime_data;
+    state.scanning.store(false, Ordering::SeqCst);
+    Ok(())
 }
 
 
 
 #[tauri::command]
-async fn generate_code_challenge() -> String {
+async fn generate_code_challenge(state: State<'_, AppState>) -> Result<String, ()> {
     
     let random_string: String = {
         const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ-_.~";
@@ -840,9 +1547,9 @@ async fn generate_code_challenge() -> String {
         }).collect()
     };
 
-    *GLOBAL_MAL_CODE_CHALLENGE.lock().await = random_string.clone();
+    *state.mal_code_challenge.lock().await = random_string.clone();
 
-    random_string
+    Ok(random_string)
 }
 
 
@@ -853,21 +1560,31 @@ fn main() {
     .setup(|app| {
         let splashscreen_window = app.get_window("splashscreen").unwrap();
         let main_window = app.get_window("main").unwrap();
+        let app_handle = app.handle();
+        // events need the app handle so the state is made here instead of before the app is built
+        app.manage(AppState::new(app_handle.clone()));
 
         tauri::async_runtime::spawn(async move {
 
-            on_startup().await;
+            on_startup(app_handle.state::<AppState>()).await.ok();
 
             splashscreen_window.close().unwrap();
             main_window.show().unwrap();
+
+            for job in Job::ALL {
+                let app_handle = app_handle.clone();
+                tauri::async_runtime::spawn(async move {
+                    run_job(&app_handle.state::<AppState>(), job).await;
+                });
+            }
         });
         Ok(())
     })
     .invoke_handler(tauri::generate_handler![manual_scan,set_highlight,get_highlight,anilist_oauth_token,write_token_data,set_user_settings,
-        get_user_settings,get_anime_info,get_manga_info,get_user_info,update_user_entry,on_startup,scan_anime_folder,
-        play_next_episode,anime_update_delay,refresh_ui,clear_errors,increment_decrement_episode,episodes_exist,browse,
-        add_to_list,remove_anime,episodes_exist_single,get_delay_info,get_list_paged,set_current_tab,get_torrents,recommend_anime,
-        open_url,get_list_ids,run_filename_tests,get_debug,delete_data,background_tasks,startup_finished,get_custom_filename,set_custom_filename,generate_code_challenge])
+        mal_oauth_token,get_user_settings,get_anime_info,get_manga_info,get_user_info,update_user_entry,on_startup,scan_anime_folder,
+        play_next_episode,anime_update_delay,get_ui_status,increment_decrement_episode,episodes_exist,browse,
+        add_to_list,remove_anime,episodes_exist_single,get_delay_info,get_tracked_episodes,cancel_tracking,get_watch_history,get_watch_history_for_anime,undo_last_auto_credit,get_list_paged,set_current_tab,get_torrents,recommend_anime,
+        open_url,get_list_ids,run_filename_tests,run_schema_tests,run_response_tests,run_fixture_tests,get_debug,delete_data,startup_finished,get_job_status,is_local_list,get_sync_conflicts,resolve_sync_conflict,attach_account,export_data,import_data,export_list,import_mal_list,get_custom_filename,set_custom_filename,generate_code_challenge])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
 }
\ No newline at end of file
@@ -1,61 +1,425 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use reqwest::Client;
+use chrono::DateTime;
+use serde_json::Value;
 
-use crate::{api_calls::TokenData, secrets::MAL_CLIENT_ID, constants::MAL_USER_STATUSES, user_data::UserInfo};
+use crate::{api_calls::{TokenData, Website, self}, graphql_client::GraphQlClient, http_client::{self, ApiError}, secrets::MAL_CLIENT_ID, constants::{MAL_USER_STATUSES, USER_LISTS}, user_data::{UserInfo, Date}};
 
 
 
+// fields of the users list status requested from myanimelist
+const MAL_LIST_STATUS_FIELDS: &str = "status,score,num_episodes_watched,is_rewatching,start_date,finish_date,num_times_rewatched,comments,updated_at";
 
-pub async fn mal_get_access_token(code: &str, code_verifier: &str) -> TokenData {
 
-    let client = Client::new();
+
+// exchanges the code the user pastes in for a access token, the code verifier is the code challenge sent when the user logged in
+// returns the reason myanimelist gave if it refused the code
+pub async fn mal_get_access_token(code: &str, code_verifier: &str) -> Result<TokenData, String> {
 
     let body = format!("client_id={}&grant_type=authorization_code&code={}&code_verifier={}", MAL_CLIENT_ID, code, code_verifier);
-    
-    let response = client.post("https://myanimelist.net/v1/oauth2/token")
+
+    let response = http_client::CLIENT.post("https://myanimelist.net/v1/oauth2/token")
         .basic_auth(MAL_CLIENT_ID, Some(""))
         .header("Content-Type", "application/x-www-form-urlencoded")
         .body(body)
         .send()
-        .await
-        .unwrap()
-        .text()
         .await;
 
-    let response_string = response.unwrap();
+    let response_string = match response {
+        Ok(result) => result.text().await.map_err(|_error| String::from("Value was not returned"))?,
+        Err(_error) => return Err(String::from("no connection")),
+    };
+
+    mal_token_from_response(&response_string).map_err(|error| mal_error_message(&response_string).unwrap_or(String::from(error)))
+}
+
+// the message myanimelist sends with a refused request
+pub fn mal_error_message(response_string: &String) -> Option<String> {
+    let value: Value = serde_json::from_str(response_string).ok()?;
+    value["message"].as_str().or(value["error"].as_str()).map(String::from)
+}
+
+
 
-    println!("{}", response_string);
+// exchange the refresh token for a new access token, myanimelist access tokens expire after a month
+pub async fn mal_refresh_token(refresh_token: &str) -> Result<TokenData, &'static str> {
 
-    let token: TokenData = serde_json::from_str(&response_string).unwrap();
+    let body = format!("client_id={}&grant_type=refresh_token&refresh_token={}", MAL_CLIENT_ID, refresh_token);
 
-    token
+    let response = http_client::CLIENT.post("https://myanimelist.net/v1/oauth2/token")
+        .basic_auth(MAL_CLIENT_ID, Some(""))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(body)
+        .send()
+        .await;
+
+    match response {
+        Ok(result) => {
+            if result.status().is_success() == false {
+                // refresh token has expired or was revoked, the user needs to log in again
+                println!("myanimelist refresh returned {}", result.status());
+                return Err("unauthorized");
+            }
+            match result.text().await {
+                Ok(response_string) => mal_token_from_response(&response_string),
+                Err(_error) => Err("Value was not returned"),
+            }
+        },
+        Err(error) => Err(mal_request_error(error)),
+    }
 }
 
-pub async fn mal_get_list(username: String, status: String, access_token: String, anime_user_data: &mut HashMap<i32, UserInfo>, anime_user_list_lock: &mut HashMap<String, Vec<i32>>) -> Option<String> {
 
-    let client = Client::new();
 
-    for status in MAL_USER_STATUSES {
-        
-        let response: Result<reqwest::Response, reqwest::Error> = client.get("https://api.myanimelist.net/v2/users/@me/animelist")
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .header("Authorization", format!("Bearer {}", access_token))
-            .query(&[("fields", "my_list_status{status,score,num_episodes_watched,is_rewatching,start_date,finish_date,num_times_rewatched,comments,updated_at}"), 
-                ("status", status)])
-            .send().await;
+// read the token returned by myanimelist, myanimelist doesn't say which website the token is for
+pub fn mal_token_from_response(response_string: &String) -> Result<TokenData, &'static str> {
 
-        match response {
-            Ok(result) => {
-                //let lists: serde_json::Value = serde_json::from_str::<serde_json::Value>(&result.text().await).unwrap()["data"]["MediaListCollection"]["lists"].take();
-            },
-            Err(error) => { println!("{}", error)},
+    let value: Value = match serde_json::from_str(response_string) {
+        Ok(value) => value,
+        Err(_error) => return Err("Value was not returned"),
+    };
+
+    match (value["token_type"].as_str(), value["expires_in"].as_i64(), value["access_token"].as_str(), value["refresh_token"].as_str()) {
+        (Some(token_type), Some(expires_in), Some(access_token), Some(refresh_token)) => Ok(TokenData {
+            website: Website::MyAnimeList,
+            token_type: token_type.to_string(),
+            expires_in: expires_in as i32,
+            access_token: access_token.to_string(),
+            refresh_token: refresh_token.to_string(),
+            issued_at: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs(),
+        }),
+        _ => Err("token was not returned"),
+    }
+}
+
+
+
+// retrieve a list of the users anime from myanimelist and add it to the user's data
+// status is the gekijou list name, not the myanimelist status
+// returns a message if a error occurred
+pub async fn mal_get_list(anilist: &dyn GraphQlClient, status: String, access_token: String, anime_user_data: &mut HashMap<i32, UserInfo>, anime_user_list_lock: &mut HashMap<String, Vec<i32>>) -> Option<String> {
+
+    let mal_status = match USER_LISTS.iter().position(|list| *list == status) {
+        Some(index) => MAL_USER_STATUSES[index],
+        None => return Some(String::from("invalid list")),
+    };
+
+    let list_fields = format!("list_status{{{}}}", MAL_LIST_STATUS_FIELDS);
+    let mut entries: Vec<Value> = Vec::new();
+    let mut next_page = Some(String::from("https://api.myanimelist.net/v2/users/@me/animelist"));
+    let mut first_page = true;
+    // myanimelist returns the list in pages, follow the next page links until the end of the list
+    while let Some(url) = next_page {
+
+        let mut request = http_client::CLIENT.get(url)
+            .header("Authorization", format!("Bearer {}", access_token));
+        if first_page {
+            request = request.query(&[("fields", list_fields.as_str()), ("status", mal_status), ("limit", "1000"), ("nsfw", "true")]);
+            first_page = false;
         }
 
+        let mut response_value = match mal_send(request).await {
+            Ok(value) => value,
+            Err(error) => return Some(String::from(error)),
+        };
+
+        if let Some(page_entries) = response_value["data"].as_array() {
+            entries.extend(page_entries.iter().cloned());
+        }
+        next_page = response_value["paging"]["next"].take().as_str().map(|next| next.to_string());
     }
 
+    // myanimelist ids need to be converted to anilist ids
+    let mal_ids: Vec<i32> = entries.iter().filter_map(|entry| entry["node"]["id"].as_i64()).map(|id| id as i32).collect();
+    let id_map = match api_calls::anilist_ids_from_mal_ids(anilist, mal_ids).await {
+        Ok(map) => map,
+        Err(error) => return Some(String::from(error)),
+    };
+
+    let anime_user_list = anime_user_list_lock.entry(status).or_default();
+    anime_user_list.clear();
+    for entry in entries {
 
+        let mal_id = entry["node"]["id"].as_i64().unwrap_or_default() as i32;
+        let media_id = match id_map.get(&mal_id) {
+            Some(id) => *id,
+            None => {
+                println!("myanimelist id {} is not on anilist", mal_id);
+                continue;
+            },
+        };
+
+        let user_info = mal_list_status_to_user_info(&entry["list_status"], mal_id, media_id);
+        anime_user_data.insert(user_info.media_id, user_info);
 
+        if anime_user_list.contains(&media_id) == false {
+            anime_user_list.push(media_id);
+        }
+    }
 
     None
-}
\ No newline at end of file
+}
+
+
+
+// get the users list status for a single anime, None if the anime is not on their list
+pub async fn mal_get_list_entry(mal_id: i32, media_id: i32, access_token: String) -> Result<Option<UserInfo>, &'static str> {
+
+    let request = http_client::CLIENT.get(format!("https://api.myanimelist.net/v2/anime/{}", mal_id))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .query(&[("fields", format!("my_list_status{{{}}}", MAL_LIST_STATUS_FIELDS))]);
+
+    let response_value = mal_send(request).await?;
+    if response_value["my_list_status"].is_object() == false {
+        return Ok(None);
+    }
+
+    Ok(Some(mal_list_status_to_user_info(&response_value["my_list_status"], mal_id, media_id)))
+}
+
+
+
+// change the users list status for a anime on myanimelist, this will add the anime if it is not on their list
+pub async fn mal_update_list_entry(mal_id: i32, anime: &UserInfo, access_token: String) -> Result<(), &'static str> {
+
+    let status_index = match USER_LISTS.iter().position(|list| *list == anime.status) {
+        Some(index) => index,
+        // rewatching is watching with a flag on myanimelist
+        None if anime.status == "REPEATING" => 0,
+        None => return Err("invalid status"),
+    };
+
+    let mut form: Vec<(&str, String)> = vec![
+        ("status", MAL_USER_STATUSES[status_index].to_string()),
+        ("is_rewatching", (anime.status == "REPEATING").to_string()),
+        ("score", (anime.score.round() as i32).clamp(0, 10).to_string()),
+        ("num_watched_episodes", anime.progress.to_string()),
+        ("num_times_rewatched", anime.repeat.to_string()),
+    ];
+    if let Some(notes) = &anime.notes {
+        form.push(("comments", notes.clone()));
+    }
+    if let Some(start_date) = date_to_mal_date(&anime.started_at) {
+        form.push(("start_date", start_date));
+    }
+    if let Some(finish_date) = date_to_mal_date(&anime.completed_at) {
+        form.push(("finish_date", finish_date));
+    }
+
+    let request = http_client::CLIENT.patch(format!("https://api.myanimelist.net/v2/anime/{}/my_list_status", mal_id))
+        .header("Authorization", format!("Bearer {}", access_token))
+        .form(&form);
+
+    mal_send(request).await?;
+    Ok(())
+}
+
+
+
+// remove a anime from the users myanimelist list
+pub async fn mal_remove_list_entry(mal_id: i32, access_token: String) -> Result<bool, &'static str> {
+
+    let request = http_client::CLIENT.delete(format!("https://api.myanimelist.net/v2/anime/{}/my_list_status", mal_id))
+        .header("Authorization", format!("Bearer {}", access_token));
+
+    let result = http_client::send_with_retry(&http_client::MAL_LIMITER, request).await?;
+    // myanimelist returns 404 if the anime was not on the list, it's gone either way
+    Ok(result.status().is_success() || result.status() == reqwest::StatusCode::NOT_FOUND)
+}
+
+
+
+// get the anilist ids of the anime on each of the user's lists
+pub async fn mal_get_list_ids(anilist: &dyn GraphQlClient, access_token: String) -> Result<HashMap<String, HashSet<i32>>, &'static str> {
+
+    let mut entries: Vec<(i32, String)> = Vec::new();
+    let mut next_page = Some(String::from("https://api.myanimelist.net/v2/users/@me/animelist"));
+    let mut first_page = true;
+    while let Some(url) = next_page {
+
+        let mut request = http_client::CLIENT.get(url)
+            .header("Authorization", format!("Bearer {}", access_token));
+        if first_page {
+            request = request.query(&[("fields", "list_status{status}"), ("limit", "1000"), ("nsfw", "true")]);
+            first_page = false;
+        }
+
+        let mut response_value = mal_send(request).await?;
+        if let Some(page_entries) = response_value["data"].as_array() {
+            for entry in page_entries {
+                let mal_id = entry["node"]["id"].as_i64().unwrap_or_default() as i32;
+                let status = entry["list_status"]["status"].as_str().unwrap_or_default();
+                if let Some(index) = MAL_USER_STATUSES.iter().position(|mal_status| *mal_status == status) {
+                    entries.push((mal_id, USER_LISTS[index].to_string()));
+                }
+            }
+        }
+        next_page = response_value["paging"]["next"].take().as_str().map(|next| next.to_string());
+    }
+
+    let mal_ids: Vec<i32> = entries.iter().map(|(mal_id, _)| *mal_id).collect();
+    let id_map = api_calls::anilist_ids_from_mal_ids(anilist, mal_ids).await?;
+
+    let mut list_ids: HashMap<String, HashSet<i32>> = HashMap::new();
+    for (mal_id, list) in entries {
+        if let Some(media_id) = id_map.get(&mal_id) {
+            list_ids.entry(list).or_default().insert(*media_id);
+        }
+    }
+
+    Ok(list_ids)
+}
+
+
+
+// returns the most recently changed entries on the users list
+pub async fn mal_get_updated_entries(anilist: &dyn GraphQlClient, access_token: String, since: u64) -> Result<Vec<UserInfo>, &'static str> {
+
+    let list_fields = format!("list_status{{{}}}", MAL_LIST_STATUS_FIELDS);
+    let mut entries: Vec<Value> = Vec::new();
+    let mut next_page = Some(String::from("https://api.myanimelist.net/v2/users/@me/animelist"));
+    let mut first_page = true;
+    // follow the next page links until a entry older than the time is found
+    'pages: while let Some(url) = next_page {
+
+        let mut request = http_client::CLIENT.get(url)
+            .header("Authorization", format!("Bearer {}", access_token));
+        if first_page {
+            request = request.query(&[("fields", list_fields.as_str()), ("sort", "list_updated_at"), ("limit", "100"), ("nsfw", "true")]);
+            first_page = false;
+        }
+
+        let mut response_value = mal_send(request).await?;
+        if let Some(page_entries) = response_value["data"].as_array() {
+            for entry in page_entries {
+                let updated_at = entry["list_status"]["updated_at"].as_str()
+                    .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+                    .map(|date| date.timestamp() as u64)
+                    .unwrap_or_default();
+                // everything after this was synced already
+                if updated_at <= since {
+                    break 'pages;
+                }
+                entries.push(entry.clone());
+            }
+        }
+        next_page = response_value["paging"]["next"].take().as_str().map(|next| next.to_string());
+    }
+
+    if entries.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mal_ids: Vec<i32> = entries.iter().filter_map(|entry| entry["node"]["id"].as_i64()).map(|id| id as i32).collect();
+    let id_map = api_calls::anilist_ids_from_mal_ids(anilist, mal_ids).await?;
+
+    let mut updated_entries: Vec<UserInfo> = Vec::new();
+    for entry in entries {
+        let mal_id = entry["node"]["id"].as_i64().unwrap_or_default() as i32;
+        if let Some(media_id) = id_map.get(&mal_id) {
+            updated_entries.push(mal_list_status_to_user_info(&entry["list_status"], mal_id, *media_id));
+        }
+    }
+
+    Ok(updated_entries)
+}
+
+
+
+// convert a myanimelist list status into gekijou's user info
+// myanimelist has no list entry id, the myanimelist anime id is used in its place
+fn mal_list_status_to_user_info(list_status: &Value, mal_id: i32, media_id: i32) -> UserInfo {
+
+    let mal_status = list_status["status"].as_str().unwrap_or_default();
+    let mut status = match MAL_USER_STATUSES.iter().position(|mal_list| *mal_list == mal_status) {
+        Some(index) => USER_LISTS[index].to_string(),
+        None => USER_LISTS[4].to_string(), // planning
+    };
+    if list_status["is_rewatching"].as_bool() == Some(true) {
+        status = String::from("REPEATING");
+    }
+
+    let updated_at = match list_status["updated_at"].as_str() {
+        Some(time) => DateTime::parse_from_rfc3339(time).map(|date| date.timestamp() as u64).unwrap_or_default(),
+        None => 0,
+    };
+
+    let notes = list_status["comments"].as_str().filter(|comments| comments.is_empty() == false).map(|comments| comments.to_string());
+
+    UserInfo {
+        id: mal_id,
+        media_id,
+        status,
+        score: list_status["score"].as_f64().unwrap_or_default() as f32,
+        progress: list_status["num_episodes_watched"].as_i64().unwrap_or_default() as i32,
+        started_at: mal_date_to_date(list_status["start_date"].as_str()),
+        completed_at: mal_date_to_date(list_status["finish_date"].as_str()),
+        notes,
+        updated_at,
+        repeat: list_status["num_times_rewatched"].as_i64().unwrap_or_default() as i32,
+        // myanimelist doesn't have these
+        private: false,
+        hidden_from_status_lists: false,
+        custom_lists: Vec::new(),
+        advanced_scores: HashMap::new(),
+    }
+}
+
+
+
+// myanimelist dates are formatted as YYYY-MM-DD, YYYY-MM, or YYYY
+fn mal_date_to_date(mal_date: Option<&str>) -> Option<Date> {
+
+    let mut date = Date::new();
+    if let Some(mal_date) = mal_date {
+        let mut parts = mal_date.split('-').map(|part| part.parse::<i32>().ok());
+        date.year = parts.next().flatten();
+        date.month = parts.next().flatten();
+        date.day = parts.next().flatten();
+    }
+    Some(date)
+}
+
+
+
+fn date_to_mal_date(date: &Option<Date>) -> Option<String> {
+
+    let date = date.as_ref()?;
+    match (date.year, date.month, date.day) {
+        (Some(year), Some(month), Some(day)) => Some(format!("{:04}-{:02}-{:02}", year, month, day)),
+        (Some(year), Some(month), None) => Some(format!("{:04}-{:02}", year, month)),
+        (Some(year), None, None) => Some(format!("{:04}", year)),
+        _ => None,
+    }
+}
+
+
+
+// send a request to the myanimelist api and return the json response
+async fn mal_send(request: reqwest::RequestBuilder) -> Result<Value, &'static str> {
+
+    let response = http_client::send_with_retry(&http_client::MAL_LIMITER, request).await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Err(ApiError::NotFound.into());
+    }
+    if response.status().is_success() == false {
+        println!("myanimelist returned {}", response.status());
+        return Err("myanimelist returned error");
+    }
+    match response.json::<Value>().await {
+        Ok(value) => Ok(value),
+        Err(_error) => Err("Value was not returned"),
+    }
+}
+
+
+
+fn mal_request_error(error: reqwest::Error) -> &'static str {
+    if error.is_connect() { // no internet
+        println!("connect error");
+        "no connection"
+    } else {
+        println!("{:?}", error);
+        "error"
+    }
+}
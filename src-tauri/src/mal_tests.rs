@@ -0,0 +1,54 @@
+use std::{fs, path::Path};
+
+use crate::{anilist_response_tests::{expect, ResponseTest}, api_calls::Website, mal_api_calls};
+
+
+
+// runs the myanimelist login against responses recorded in mal_fixtures
+pub async fn mal_tests() -> Vec<ResponseTest> {
+
+    let mut test_results: Vec<ResponseTest> = Vec::new();
+    for (name, result) in [
+        ("mal login", login()),
+        ("mal login refused", login_refused()),
+    ] {
+        test_results.push(ResponseTest {
+            fixture: String::from(name),
+            passed: result.is_ok(),
+            error: result.err().unwrap_or_default(),
+        });
+    }
+
+    test_results
+}
+
+pub fn read_fixture(fixture: &str) -> Result<String, String> {
+    let path = Path::new("mal_fixtures").join(fixture);
+    fs::read_to_string(&path).map_err(|error| format!("{} {}", fixture, error))
+}
+
+
+
+fn login() -> Result<(), String> {
+
+    let token = mal_api_calls::mal_token_from_response(&read_fixture("token.json")?)?;
+    expect("website", &matches!(token.website, Website::MyAnimeList), &true)?;
+    expect("access token", &token.access_token.as_str(), &"eyJ0eXAiOiJKV1QiLCJhbGciOiJSUzI1NiJ9.access")?;
+    expect("refresh token", &token.refresh_token.as_str(), &"def50200a1b2c3d4e5f6.refresh")?;
+    expect("expires in", &token.expires_in, &2678400)?;
+    // myanimelist tokens last a month, one that was just issued shouldn't need refreshing
+    expect("expired", &token.is_expired(), &false)?;
+    Ok(())
+}
+
+fn login_refused() -> Result<(), String> {
+
+    let response = read_fixture("token_refused.json")?;
+    if mal_api_calls::mal_token_from_response(&response).is_ok() {
+        return Err(String::from("a refused code returned a token"));
+    }
+    // the user is shown why the code was refused
+    let message = mal_api_calls::mal_error_message(&response).unwrap_or_default();
+    expect("message", &message.starts_with("The provided authorization grant"), &true)?;
+    Ok(())
+}
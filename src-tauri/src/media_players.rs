@@ -0,0 +1,321 @@
+use std::{io::{BufRead, BufReader, Read, Write}, time::Duration};
+
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::Client;
+use serde_json::{json, Value};
+
+use crate::user_data::UserSettings;
+
+
+
+// a file that is open in a media player
+#[derive(Debug, Clone, Default)]
+pub struct PlayingMedia {
+    pub player: String,
+    pub file_path: String,
+    pub position: Option<f64>, // seconds
+    pub duration: Option<f64>, // seconds
+    pub playing: bool,
+}
+
+impl PlayingMedia {
+
+    // position and duration in seconds, None if the player didn't report both
+    pub fn playback(&self) -> Option<(f64, f64)> {
+        match (self.position, self.duration) {
+            (Some(position), Some(duration)) if duration > 0.0 => Some((position.clamp(0.0, duration), duration)),
+            _ => None,
+        }
+    }
+}
+
+
+
+// a media player that can be asked what file it is playing
+#[async_trait]
+pub trait PlayerBackend: Send + Sync {
+
+    fn name(&self) -> &'static str;
+
+    // returns the files open in the player, nothing if the player isn't running
+    async fn get_playing_media(&self) -> Vec<PlayingMedia>;
+}
+
+
+
+// creates the player backends that are enabled in the user's settings
+pub fn get_backends(settings: &UserSettings) -> Vec<Box<dyn PlayerBackend>> {
+
+    let mut backends: Vec<Box<dyn PlayerBackend>> = Vec::new();
+
+    #[cfg(target_os = "linux")]
+    backends.push(Box::new(crate::mpris::MprisBackend));
+
+    if let Some(socket_path) = &settings.mpv_socket {
+        if socket_path.is_empty() == false {
+            backends.push(Box::new(MpvBackend { socket_path: socket_path.clone() }));
+        }
+    }
+    if let Some(port) = settings.vlc_port {
+        backends.push(Box::new(VlcBackend { port, password: settings.vlc_password.clone().unwrap_or_default() }));
+    }
+    if let Some(port) = settings.mpc_hc_port {
+        backends.push(Box::new(MpcHcBackend { port }));
+    }
+
+    backends
+}
+
+
+
+// get the files open in every enabled media player
+pub async fn get_playing_media(settings: &UserSettings) -> Vec<PlayingMedia> {
+
+    let mut media: Vec<PlayingMedia> = Vec::new();
+    for backend in get_backends(settings) {
+        media.extend(backend.get_playing_media().await);
+    }
+    media
+}
+
+
+
+// players are on the same computer, they either answer quickly or aren't running
+fn local_client() -> Client {
+    Client::builder().timeout(Duration::from_secs(1)).build().unwrap_or_default()
+}
+
+
+
+// mpv started with --input-ipc-server, it takes json commands over a unix socket or named pipe
+pub struct MpvBackend {
+    pub socket_path: String,
+}
+
+#[async_trait]
+impl PlayerBackend for MpvBackend {
+
+    fn name(&self) -> &'static str {
+        "mpv"
+    }
+
+    async fn get_playing_media(&self) -> Vec<PlayingMedia> {
+
+        let socket_path = self.socket_path.clone();
+        // the socket is blocking io, keep it off the async threads
+        match tauri::async_runtime::spawn_blocking(move || mpv_get_playing_media(&socket_path)).await {
+            Ok(Ok(media)) => media.into_iter().collect(),
+            Ok(Err(_error)) => Vec::new(), // mpv isn't running
+            Err(error) => {
+                println!("mpv: {}", error);
+                Vec::new()
+            },
+        }
+    }
+}
+
+fn mpv_get_playing_media(socket_path: &String) -> std::io::Result<Option<PlayingMedia>> {
+
+    let (reader, mut writer) = mpv_connect(socket_path)?;
+    let mut reader = BufReader::new(reader);
+
+    let properties = ["path", "time-pos", "duration", "pause"];
+    for (request_id, property) in properties.iter().enumerate() {
+        let command = json!({"command": ["get_property", property], "request_id": request_id});
+        writer.write_all(format!("{}\n", command).as_bytes())?;
+    }
+    writer.flush()?;
+
+    // responses can be mixed with event messages, match them to the requests by id
+    let mut values: Vec<Value> = vec![Value::Null; properties.len()];
+    let mut received = 0;
+    let mut line = String::new();
+    while received < properties.len() {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let response: Value = match serde_json::from_str(&line) {
+            Ok(response) => response,
+            Err(_error) => continue,
+        };
+        if let Some(request_id) = response["request_id"].as_u64() {
+            if let Some(value) = values.get_mut(request_id as usize) {
+                *value = response["data"].clone();
+                received += 1;
+            }
+        }
+    }
+
+    // nothing is loaded
+    let file_path = match values[0].as_str() {
+        Some(path) => path.to_string(),
+        None => return Ok(None),
+    };
+
+    Ok(Some(PlayingMedia {
+        player: String::from("mpv"),
+        file_path,
+        position: values[1].as_f64(),
+        duration: values[2].as_f64(),
+        playing: values[3].as_bool() == Some(false),
+    }))
+}
+
+#[cfg(unix)]
+fn mpv_connect(socket_path: &String) -> std::io::Result<(Box<dyn Read + Send>, Box<dyn Write + Send>)> {
+    let stream = std::os::unix::net::UnixStream::connect(socket_path)?;
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+    Ok((Box::new(stream.try_clone()?), Box::new(stream)))
+}
+
+#[cfg(windows)]
+fn mpv_connect(socket_path: &String) -> std::io::Result<(Box<dyn Read + Send>, Box<dyn Write + Send>)> {
+    let pipe = std::fs::OpenOptions::new().read(true).write(true).open(socket_path)?;
+    Ok((Box::new(pipe.try_clone()?), Box::new(pipe)))
+}
+
+
+
+// vlc with the web interface enabled, it requires a password
+pub struct VlcBackend {
+    pub port: i32,
+    pub password: String,
+}
+
+#[async_trait]
+impl PlayerBackend for VlcBackend {
+
+    fn name(&self) -> &'static str {
+        "VLC"
+    }
+
+    async fn get_playing_media(&self) -> Vec<PlayingMedia> {
+
+        let client = local_client();
+        let status = match vlc_request(&client, self.port, &self.password, "status.json").await {
+            Some(status) => status,
+            None => return Vec::new(),
+        };
+
+        let state = status["state"].as_str().unwrap_or_default();
+        if state == "stopped" {
+            return Vec::new();
+        }
+
+        // the status only has the filename, the full path is in the playlist
+        let mut file_path = status["information"]["category"]["meta"]["filename"].as_str().unwrap_or_default().to_string();
+        if let Some(playlist) = vlc_request(&client, self.port, &self.password, "playlist.json").await {
+            if let Some(uri) = vlc_current_uri(&playlist) {
+                if let Some(path) = url::Url::parse(&uri).ok().and_then(|url| url.to_file_path().ok()) {
+                    file_path = path.to_string_lossy().to_string();
+                }
+            }
+        }
+        if file_path.is_empty() {
+            return Vec::new();
+        }
+
+        vec![PlayingMedia {
+            player: String::from("VLC"),
+            file_path,
+            position: status["time"].as_f64(),
+            duration: status["length"].as_f64().filter(|length| *length > 0.0),
+            playing: state == "playing",
+        }]
+    }
+}
+
+async fn vlc_request(client: &Client, port: i32, password: &String, page: &str) -> Option<Value> {
+
+    let response = client.get(format!("http://127.0.0.1:{}/requests/{}", port, page))
+        .basic_auth("", Some(password))
+        .send()
+        .await
+        .ok()?;
+
+    if response.status().is_success() == false {
+        println!("vlc returned {}", response.status());
+        return None;
+    }
+    response.json::<Value>().await.ok()
+}
+
+// find the playlist item vlc is currently playing
+fn vlc_current_uri(node: &Value) -> Option<String> {
+
+    if node.get("current").is_some() {
+        if let Some(uri) = node["uri"].as_str() {
+            return Some(uri.to_string());
+        }
+    }
+    node["children"].as_array()?.iter().find_map(vlc_current_uri)
+}
+
+
+
+// media player classic home cinema with the web interface enabled
+pub struct MpcHcBackend {
+    pub port: i32,
+}
+
+lazy_static! {
+    static ref MPC_HC_VARIABLE: Regex = Regex::new(r#"<p id="([^"]+)">([^<]*)</p>"#).unwrap();
+}
+
+#[async_trait]
+impl PlayerBackend for MpcHcBackend {
+
+    fn name(&self) -> &'static str {
+        "MPC-HC"
+    }
+
+    async fn get_playing_media(&self) -> Vec<PlayingMedia> {
+
+        let page = match local_client().get(format!("http://127.0.0.1:{}/variables.html", self.port)).send().await {
+            Ok(response) => match response.text().await {
+                Ok(page) => page,
+                Err(_error) => return Vec::new(),
+            },
+            Err(_error) => return Vec::new(), // mpc-hc isn't running
+        };
+
+        let mut file_path = String::new();
+        let mut state = 0;
+        let mut position: Option<f64> = None;
+        let mut duration: Option<f64> = None;
+        for capture in MPC_HC_VARIABLE.captures_iter(&page) {
+            let value = &capture[2];
+            match &capture[1] {
+                "filepath" => file_path = decode_html(value),
+                "state" => state = value.parse().unwrap_or_default(),
+                "position" => position = value.parse::<f64>().ok().map(|milliseconds| milliseconds / 1000.0),
+                "duration" => duration = value.parse::<f64>().ok().map(|milliseconds| milliseconds / 1000.0),
+                _ => {},
+            }
+        }
+
+        // -1 is nothing loaded, 0 is stopped
+        if file_path.is_empty() || state <= 0 {
+            return Vec::new();
+        }
+
+        vec![PlayingMedia {
+            player: String::from("MPC-HC"),
+            file_path,
+            position,
+            duration: duration.filter(|length| *length > 0.0),
+            playing: state == 2,
+        }]
+    }
+}
+
+fn decode_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
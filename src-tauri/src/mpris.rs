@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use zbus::{Connection, ProxyBuilder, Proxy, CacheProperties, fdo::DBusProxy, zvariant::OwnedValue};
+
+use crate::media_players::{PlayerBackend, PlayingMedia};
+
+
+
+// media players on linux announce themselves on the session bus with names starting with this
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const MPRIS_PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+// replaces the session bus, used to point gekijou at a test bus with mock players on it
+#[cfg(debug_assertions)]
+const BUS_ADDRESS_VARIABLE: &str = "GEKIJOU_DBUS_ADDRESS";
+
+
+
+// every media player on the session bus that supports mpris
+pub struct MprisBackend;
+
+#[async_trait]
+impl PlayerBackend for MprisBackend {
+
+    fn name(&self) -> &'static str {
+        "MPRIS"
+    }
+
+    async fn get_playing_media(&self) -> Vec<PlayingMedia> {
+        get_playing_media().await
+    }
+}
+
+
+
+// get the files that are open in media players that support mpris
+// players that are not playing a local file are skipped
+async fn get_playing_media() -> Vec<PlayingMedia> {
+
+    let connection = match connect().await {
+        Ok(connection) => connection,
+        Err(error) => {
+            println!("mpris connection: {}", error);
+            return Vec::new();
+        },
+    };
+
+    get_bus_media(&connection).await
+}
+
+// get the files that are open in the media players on the connection's bus
+pub async fn get_bus_media(connection: &Connection) -> Vec<PlayingMedia> {
+
+    let names = match DBusProxy::new(connection).await {
+        Ok(proxy) => match proxy.list_names().await {
+            Ok(names) => names,
+            Err(error) => {
+                println!("mpris list_names: {}", error);
+                return Vec::new();
+            },
+        },
+        Err(error) => {
+            println!("mpris dbus proxy: {}", error);
+            return Vec::new();
+        },
+    };
+
+    let mut media: Vec<PlayingMedia> = Vec::new();
+    for name in names {
+        if name.starts_with(MPRIS_PREFIX) == false {
+            continue;
+        }
+        match get_player_media(connection, name.as_str()).await {
+            Ok(Some(player_media)) => media.push(player_media),
+            Ok(None) => {},
+            Err(error) => println!("mpris {}: {}", name.as_str(), error),
+        }
+    }
+
+    media
+}
+
+
+
+async fn connect() -> zbus::Result<Connection> {
+    #[cfg(debug_assertions)]
+    if let Ok(address) = std::env::var(BUS_ADDRESS_VARIABLE) {
+        return zbus::ConnectionBuilder::address(address.as_str())?.build().await;
+    }
+    Connection::session().await
+}
+
+
+
+// read the file, position, and playback state of a single player
+async fn get_player_media(connection: &Connection, name: &str) -> zbus::Result<Option<PlayingMedia>> {
+
+    let proxy: Proxy = ProxyBuilder::new_bare(connection)
+        .destination(name)?
+        .path(MPRIS_PATH)?
+        .interface(MPRIS_PLAYER_INTERFACE)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+
+    let metadata: HashMap<String, OwnedValue> = proxy.get_property("Metadata").await?;
+
+    let url: String = match metadata.get("xesam:url").and_then(|value| String::try_from(value.clone()).ok()) {
+        Some(url) => url,
+        None => return Ok(None),
+    };
+    let file_path = match url::Url::parse(&url).ok().and_then(|url| url.to_file_path().ok()) {
+        Some(path) => path.to_string_lossy().to_string(),
+        None => return Ok(None), // streams and other non file urls
+    };
+
+    // length is supposed to be a i64 but some players send a u64
+    let duration = metadata.get("mpris:length").and_then(|value| {
+        i64::try_from(value.clone()).ok().or_else(|| u64::try_from(value.clone()).ok().map(|length| length as i64))
+    }).map(|microseconds| microseconds as f64 / 1_000_000.0);
+
+    // position is optional for players
+    let position = proxy.get_property::<i64>("Position").await.ok().map(|microseconds| microseconds as f64 / 1_000_000.0);
+
+    let playback_status: String = proxy.get_property("PlaybackStatus").await?;
+
+    Ok(Some(PlayingMedia {
+        player: name.trim_start_matches(MPRIS_PREFIX).to_string(),
+        file_path,
+        position,
+        duration,
+        playing: playback_status == "Playing",
+    }))
+}
@@ -0,0 +1,116 @@
+use std::{collections::HashMap, io::{BufRead, BufReader}, process::{Child, Command, Stdio}};
+use zbus::{dbus_interface, Connection, ConnectionBuilder, zvariant::{OwnedValue, Value}};
+
+use crate::{anilist_response_tests::{expect, ResponseTest}, mpris};
+
+
+
+// a media player with the parts of org.mpris.MediaPlayer2.Player that gekijou reads
+struct MockPlayer {
+    url: String,
+    length: OwnedValue, // players don't agree on the type
+    position: i64, // microseconds
+    playback_status: String,
+}
+
+#[dbus_interface(name = "org.mpris.MediaPlayer2.Player")]
+impl MockPlayer {
+
+    #[dbus_interface(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let mut metadata = HashMap::new();
+        metadata.insert(String::from("xesam:url"), OwnedValue::from(Value::from(self.url.clone())));
+        metadata.insert(String::from("xesam:title"), OwnedValue::from(Value::from("Episode 3")));
+        metadata.insert(String::from("mpris:length"), self.length.clone());
+        metadata
+    }
+
+    #[dbus_interface(property)]
+    fn position(&self) -> i64 {
+        self.position
+    }
+
+    #[dbus_interface(property)]
+    fn playback_status(&self) -> String {
+        self.playback_status.clone()
+    }
+}
+
+
+
+// starts mock players on a bus of their own and checks what is read from them
+// dbus-daemon has to be installed, the players can't be seen from the session bus
+pub async fn mpris_tests() -> Vec<ResponseTest> {
+
+    let (mut daemon, address) = match start_bus() {
+        Ok(bus) => bus,
+        Err(error) => return vec![ResponseTest { fixture: String::from("mpris bus"), passed: false, error }],
+    };
+
+    let result = playing_media(&address).await;
+    daemon.kill().ok();
+    daemon.wait().ok();
+
+    vec![ResponseTest { fixture: String::from("mpris players"), passed: result.is_ok(), error: result.err().unwrap_or_default() }]
+}
+
+// a new bus, returns the daemon and the address to connect to
+fn start_bus() -> Result<(Child, String), String> {
+
+    let mut daemon = Command::new("dbus-daemon")
+        .args(["--session", "--nofork", "--print-address"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("dbus-daemon {}", error))?;
+
+    let mut address = String::new();
+    let read = match daemon.stdout.take() {
+        Some(stdout) => BufReader::new(stdout).read_line(&mut address).map_err(|error| error.to_string()),
+        None => Err(String::from("no output")),
+    };
+    if let Err(error) = read {
+        daemon.kill().ok();
+        return Err(format!("dbus-daemon {}", error));
+    }
+    Ok((daemon, address.trim().to_string()))
+}
+
+// a player with a local file and a player with a stream, only the file is a episode that can be recognized
+async fn playing_media(address: &str) -> Result<(), String> {
+
+    let file_player = MockPlayer {
+        url: String::from("file:///home/user/Anime/%5BSubs%5D%20Show%20-%2003.mkv"),
+        length: OwnedValue::from(1_440_000_000u64),
+        position: 90_000_000,
+        playback_status: String::from("Paused"),
+    };
+    let stream_player = MockPlayer {
+        url: String::from("https://example.com/stream.m3u8"),
+        length: OwnedValue::from(1_440_000_000i64),
+        position: 0,
+        playback_status: String::from("Playing"),
+    };
+    let _file_player = serve_player(address, "org.mpris.MediaPlayer2.mockplayer", file_player).await.map_err(|error| error.to_string())?;
+    let _stream_player = serve_player(address, "org.mpris.MediaPlayer2.mockstream", stream_player).await.map_err(|error| error.to_string())?;
+
+    let connection = ConnectionBuilder::address(address).map_err(|error| error.to_string())?
+        .build().await.map_err(|error| error.to_string())?;
+    let media = mpris::get_bus_media(&connection).await;
+
+    expect("players", &media.len(), &1)?;
+    expect("player", &media[0].player, &String::from("mockplayer"))?;
+    expect("file path", &media[0].file_path, &String::from("/home/user/Anime/[Subs] Show - 03.mkv"))?;
+    expect("position", &media[0].position, &Some(90.0))?;
+    expect("duration", &media[0].duration, &Some(1440.0))?;
+    expect("playing", &media[0].playing, &false)?;
+    Ok(())
+}
+
+// the player stays on the bus until the connection is dropped
+async fn serve_player(address: &str, name: &str, player: MockPlayer) -> zbus::Result<Connection> {
+    ConnectionBuilder::address(address)?
+        .name(name)?
+        .serve_at("/org/mpris/MediaPlayer2", player)?
+        .build()
+        .await
+}
@@ -2,8 +2,9 @@ use std::io::Cursor;
 use regex::Regex;
 use reqwest;
 use serde::{Deserialize, Serialize};
+use tauri::async_runtime::Mutex;
 use xml;
-use crate::{anime_data::AnimeInfo, GLOBAL_ANIME_DATA};
+use crate::anime_data::AnimeData;
 
 
 
@@ -38,9 +39,9 @@ pub struct DerivedValues {
 
 
 // search nyaa.si for a anime and returns all entries in a struct format
-pub async fn get_rss(anime_id: i32) -> Vec<RssEntry> {
+pub async fn get_rss(anime_data: &Mutex<AnimeData>, anime_id: i32) -> Vec<RssEntry> {
 
-    let media = match GLOBAL_ANIME_DATA.lock().await.get_anime_data(anime_id).await {
+    let media = match anime_data.lock().await.get_anime_data(anime_id).await {
         Ok(anime) => anime,
         Err(error) => {
             println!("{}", error);
@@ -103,7 +104,7 @@ pub async fn get_rss(anime_id: i32) -> Vec<RssEntry> {
 
     let valid_file_extensions = Regex::new(r"[_ ]?(\.mkv|\.avi|\.mp4)").unwrap();
     let file_size = Regex::new(r"(\d{1,3}\.\d?)").unwrap();
-    let anime_data = GLOBAL_ANIME_DATA.lock().await;
+    let anime_data = anime_data.lock().await;
     for e in entrys.iter_mut() {
 
         let title = e.title.clone();
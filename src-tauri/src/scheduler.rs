@@ -0,0 +1,107 @@
+use std::{collections::HashMap, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+use serde::{Deserialize, Serialize};
+use crate::constants;
+
+
+
+// work the backend does on its own while gekijou is open, each job runs on its own timer
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Job {
+    PlayerDetection, // check media players for episodes being watched
+    LibraryRescan, // scan the user's folders for new episodes
+    AiringRefresh, // get the airing time of the next episode of airing anime
+    QueueFlush, // send changes that were made while the website couldn't be reached
+    ConnectivityProbe, // check if the website can be reached again after it couldn't be
+    CacheRefresh, // download the info of anime on the user's list again so changes on anilist are seen
+    ListSync, // get changes made to the list on the website
+    DeletionCheck, // find anime removed from the list on the website
+}
+
+impl Job {
+
+    pub const ALL: [Job; 8] = [Job::PlayerDetection, Job::LibraryRescan, Job::AiringRefresh, Job::QueueFlush,
+        Job::ConnectivityProbe, Job::CacheRefresh, Job::ListSync, Job::DeletionCheck];
+
+    // wait before the first run, folders are scanned soon after startup instead of after a full interval
+    pub fn first_delay(&self, interval: Duration) -> Duration {
+        match self {
+            Job::LibraryRescan => interval.min(Duration::from_secs(constants::STARTUP_SCAN_DELAY)),
+            _ => interval,
+        }
+    }
+}
+
+
+
+// what a job last did, shown to the user so they can see if something is stuck or failing
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JobStatus {
+    pub job: Job,
+    pub interval: Option<u64>, // seconds between runs, None if the user turned the job off
+    pub running: bool,
+    pub runs: u32, // times the job has run since gekijou was opened
+    pub last_run: Option<u64>, // unix time in seconds the last run started
+    pub last_duration: Option<u64>, // milliseconds the last run took
+    pub last_error: Option<String>, // None if the last run succeeded
+    pub next_run: Option<u64>, // unix time in seconds
+}
+
+impl JobStatus {
+    pub fn new(job: Job) -> JobStatus {
+        JobStatus { job, interval: None, running: false, runs: 0, last_run: None, last_duration: None, last_error: None, next_run: None }
+    }
+}
+
+
+
+// status of every job, it's only locked to record a change so jobs never wait on each other
+pub struct Scheduler {
+    statuses: HashMap<Job, JobStatus>,
+    started: HashMap<Job, Instant>,
+}
+
+impl Scheduler {
+
+    pub fn new() -> Scheduler {
+        Scheduler { statuses: Job::ALL.iter().map(|job| (*job, JobStatus::new(*job))).collect(), started: HashMap::new() }
+    }
+
+    // the job will run again after next_run, or won't run if interval is None
+    pub fn scheduled(&mut self, job: Job, interval: Option<Duration>, next_run: Duration) {
+        let status = self.status(job);
+        status.interval = interval.map(|interval| interval.as_secs());
+        status.next_run = interval.map(|_| unix_time() + next_run.as_secs());
+    }
+
+    pub fn started(&mut self, job: Job) {
+        self.started.insert(job, Instant::now());
+        let status = self.status(job);
+        status.running = true;
+        status.last_run = Some(unix_time());
+    }
+
+    pub fn finished(&mut self, job: Job, result: Result<(), &'static str>) {
+        let duration = self.started.remove(&job).map(|started| started.elapsed().as_millis() as u64);
+        let status = self.status(job);
+        status.running = false;
+        status.runs += 1;
+        status.last_duration = duration;
+        status.last_error = result.err().map(String::from);
+    }
+
+    // every job in the order they're declared
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        Job::ALL.iter().filter_map(|job| self.statuses.get(job).cloned()).collect()
+    }
+
+    fn status(&mut self, job: Job) -> &mut JobStatus {
+        self.statuses.entry(job).or_insert_with(|| JobStatus::new(job))
+    }
+}
+
+
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
@@ -0,0 +1,178 @@
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+
+use crate::constants::DEFAULT_HIGHLIGHT_COLOR;
+use crate::file_operations::PersistError;
+
+
+
+// version of the format saved files are written in, increase it and add a migration when a persisted struct changes
+// files written before versions were added don't have a header and are version 0
+pub const SCHEMA_VERSION: u64 = 3;
+
+// MIGRATIONS[n] upgrades a file from version n to version n + 1
+const MIGRATIONS: [fn(&str, &mut Value); SCHEMA_VERSION as usize] = [migrate_v0_to_v1, migrate_v1_to_v2, migrate_v2_to_v3];
+
+
+
+// the header every saved file is wrapped in
+#[derive(Serialize)]
+struct VersionedFile<'a, T: Serialize> {
+    version: u64,
+    data: &'a T,
+}
+
+// serialize data with the current schema version header
+pub fn to_versioned_json<T: Serialize>(data: &T) -> Result<Vec<u8>, PersistError> {
+    Ok(serde_json::to_vec(&VersionedFile { version: SCHEMA_VERSION, data })?)
+}
+
+// read a saved file of any version, older files are upgraded to the current format before being deserialized
+// filename is the name of the file without the extension and decides which migrations apply to the data
+pub fn from_versioned_json<T: DeserializeOwned>(filename: &str, contents: &str) -> Result<T, PersistError> {
+
+    let (version, mut data) = split_header(serde_json::from_str(contents)?);
+
+    // written by a newer version of gekijou, guessing at the format could lose data
+    if version > SCHEMA_VERSION {
+        return Err(PersistError::NewerVersion(version));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(filename, &mut data);
+    }
+
+    Ok(serde_json::from_value(data)?)
+}
+
+// the version of the file and the data inside the header
+fn split_header(value: Value) -> (u64, Value) {
+    match value {
+        Value::Object(mut object) if object.len() == 2 && object.contains_key("data") && object.get("version").map_or(false, Value::is_u64) => {
+            let version = object["version"].as_u64().unwrap_or_default();
+            (version, object.remove("data").unwrap_or_default())
+        },
+        value => (0, value),
+    }
+}
+
+
+
+// fill in a field that older versions didn't save
+fn insert_missing(object: &mut Value, field: &str, default: Value) {
+    if let Some(object) = object.as_object_mut() {
+        object.entry(field).or_insert(default);
+    }
+}
+
+// the values of a file that stores a map or list of entries
+fn entries_mut(data: &mut Value) -> Vec<&mut Value> {
+    match data {
+        Value::Object(object) => object.values_mut().collect(),
+        Value::Array(array) => array.iter_mut().collect(),
+        _ => Vec::new(),
+    }
+}
+
+
+
+// versions before the header was added, fields were added over time without defaults
+fn migrate_v0_to_v1(filename: &str, data: &mut Value) {
+
+    match filename {
+        "token" => {
+            // only anilist was supported before the website was saved
+            insert_missing(data, "website", json!("Anilist"));
+            insert_missing(data, "issued_at", json!(0));
+        },
+        "user_settings" => {
+            insert_missing(data, "username", json!(""));
+            insert_missing(data, "title_language", json!("romaji"));
+            insert_missing(data, "show_adult", json!(false));
+            insert_missing(data, "folders", json!([]));
+            insert_missing(data, "update_delay", json!(0));
+            insert_missing(data, "highlight_color", json!(DEFAULT_HIGHLIGHT_COLOR));
+            insert_missing(data, "current_tab", json!(""));
+            // the settings are only saved after the first time setup
+            insert_missing(data, "first_time_setup", json!(false));
+            insert_missing(data, "show_airing_time", json!(true));
+            insert_missing(data, "theme", json!(0));
+        },
+        "user_data" | "update_queue" => {
+            for entry in entries_mut(data) {
+                insert_missing(entry, "id", json!(0));
+                insert_missing(entry, "score", json!(0.0));
+                insert_missing(entry, "updated_at", json!(0));
+            }
+        },
+        "anime_cache" => {
+            for anime in entries_mut(data) {
+                insert_missing(anime, "genres", json!([]));
+                insert_missing(anime, "is_adult", json!(false));
+                insert_missing(anime, "popularity", json!(0));
+                insert_missing(anime, "media_type", json!("ANIME"));
+                insert_missing(anime, "relations", json!({"edges": []}));
+                insert_missing(anime, "tags", json!([]));
+                insert_missing(anime, "trending", json!(0));
+                insert_missing(anime, "studios", json!({"nodes": []}));
+                insert_missing(anime, "synonyms", json!([]));
+            }
+        },
+        _ => {},
+    }
+}
+
+
+
+// the list entries saved in a file, files that don't store list entries have none
+fn list_entries_mut<'a>(filename: &str, data: &'a mut Value) -> Vec<&'a mut Value> {
+
+    match filename {
+        "user_data" | "update_queue" => entries_mut(data),
+        "sync_state" => match data.get_mut("conflicts") {
+            Some(conflicts) => entries_mut(conflicts).into_iter()
+                .flat_map(|conflict| match conflict.as_object_mut() {
+                    Some(conflict) => conflict.iter_mut().filter(|(key, _)| *key == "local" || *key == "remote").map(|(_, entry)| entry).collect(),
+                    None => Vec::new(),
+                })
+                .collect(),
+            None => Vec::new(),
+        },
+        "watch_history" => previous_entries_mut(data),
+        "archive" => match data.as_object_mut() {
+            Some(archive) => archive.iter_mut()
+                .flat_map(|(key, value)| match key.as_str() {
+                    "user_data" => entries_mut(value),
+                    "watch_history" => previous_entries_mut(value),
+                    _ => Vec::new(),
+                })
+                .collect(),
+            None => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+// the entries watch records saved from before they were updated
+fn previous_entries_mut(records: &mut Value) -> Vec<&mut Value> {
+    entries_mut(records).into_iter().filter_map(|record| record.get_mut("previous")).collect()
+}
+
+// list entries got a rewatch count
+fn migrate_v1_to_v2(filename: &str, data: &mut Value) {
+
+    for entry in list_entries_mut(filename, data) {
+        insert_missing(entry, "repeat", json!(0));
+    }
+}
+
+// list entries got the rest of anilist's list entry fields
+fn migrate_v2_to_v3(filename: &str, data: &mut Value) {
+
+    for entry in list_entries_mut(filename, data) {
+        insert_missing(entry, "private", json!(false));
+        insert_missing(entry, "hidden_from_status_lists", json!(false));
+        insert_missing(entry, "custom_lists", json!([]));
+        insert_missing(entry, "advanced_scores", json!({}));
+    }
+}
@@ -0,0 +1,127 @@
+use std::{collections::{HashMap, HashSet}, fs, path::Path};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+
+use crate::{anilist_response_tests::expect, anime_data::{AnimeInfo, AnimePath}, api_calls::{TokenData, Website}, data_archive::DataArchive, schema, user_data::{SyncState, UserInfo, UserSettings}, watch_history::WatchRecord, WatchingTracking};
+
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SchemaTest {
+    pub fixture: String,
+    pub filename: String,
+    pub version: String,
+    pub passed: bool,
+    pub error: String,
+}
+
+
+
+// loads every fixture in schema_fixtures, they're saved files from each previous format named <filename>_v<version>.json
+// a fixture passes if it can be upgraded to the current format and read back after it's saved again
+// the files from before versions were saved also have the values they were upgraded to checked
+pub async fn schema_tests() -> Vec<SchemaTest> {
+
+    let folder = Path::new("schema_fixtures");
+
+    let mut fixtures: Vec<String> = match fs::read_dir(folder) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".json"))
+            .collect(),
+        Err(_error) => return Vec::new(),
+    };
+    fixtures.sort();
+
+    let mut test_results: Vec<SchemaTest> = Vec::new();
+    for fixture in fixtures {
+
+        let (filename, version) = match fixture.trim_end_matches(".json").rsplit_once("_v") {
+            Some((filename, version)) => (filename.to_string(), version.to_string()),
+            None => (fixture.trim_end_matches(".json").to_string(), String::new()),
+        };
+
+        let result = match fs::read_to_string(folder.join(&fixture)) {
+            Ok(contents) => load_fixture(&filename, &version, &contents),
+            Err(error) => Err(error.to_string()),
+        };
+
+        test_results.push(SchemaTest {
+            passed: result.is_ok(),
+            error: result.err().unwrap_or_default(),
+            fixture,
+            filename,
+            version,
+        });
+    }
+
+    test_results
+}
+
+// read the fixture as the type the file is loaded into
+fn load_fixture(filename: &str, version: &str, contents: &str) -> Result<(), String> {
+    match filename {
+        "token" => check_token(version, round_trip::<TokenData>(filename, contents)?),
+        "user_settings" => check_user_settings(version, round_trip::<UserSettings>(filename, contents)?),
+        "user_data" => check_user_data(version, round_trip::<HashMap<i32, UserInfo>>(filename, contents)?),
+        "user_lists" => round_trip::<HashMap<String, Vec<i32>>>(filename, contents).map(|_data| ()),
+        "update_queue" => round_trip::<Vec<UserInfo>>(filename, contents).map(|_data| ()),
+        "anime_cache" => round_trip::<HashMap<i32, AnimeInfo>>(filename, contents).map(|_data| ()),
+        "404_ids" => round_trip::<HashSet<i32>>(filename, contents).map(|_data| ()),
+        "episode_path" => round_trip::<HashMap<i32, HashMap<i32, AnimePath>>>(filename, contents).map(|_data| ()),
+        "known_files" => round_trip::<HashSet<u64>>(filename, contents).map(|_data| ()),
+        "watching_tracking" => round_trip::<HashMap<i32, WatchingTracking>>(filename, contents).map(|_data| ()),
+        "watch_history" => round_trip::<Vec<WatchRecord>>(filename, contents).map(|_data| ()),
+        "sync_state" => round_trip::<SyncState>(filename, contents).map(|_data| ()),
+        "archive" => round_trip::<DataArchive>(filename, contents).map(|_data| ()),
+        _ => Err(String::from("no file with this name is saved")),
+    }
+}
+
+// returns the data as it's read after being saved again
+fn round_trip<T: Serialize + DeserializeOwned>(filename: &str, contents: &str) -> Result<T, String> {
+
+    let data: T = schema::from_versioned_json(filename, contents).map_err(|error| error.to_string())?;
+    let saved = schema::to_versioned_json(&data).map_err(|error| error.to_string())?;
+    let saved = String::from_utf8(saved).map_err(|error| error.to_string())?;
+    schema::from_versioned_json::<T>(filename, &saved).map_err(|error| format!("saved again, {}", error))
+}
+
+
+
+// only anilist tokens were saved before the website was
+fn check_token(version: &str, token: TokenData) -> Result<(), String> {
+    if version == "0" {
+        expect("website", &matches!(token.website, Website::Anilist), &true)?;
+        expect("access token", &token.access_token, &String::from("access"))?;
+        expect("issued at", &token.issued_at, &0)?;
+    }
+    Ok(())
+}
+
+fn check_user_settings(version: &str, settings: UserSettings) -> Result<(), String> {
+    if version == "0" {
+        expect("first time setup", &settings.first_time_setup, &false)?;
+        expect("username", &settings.username, &String::from("gekijou"))?;
+        expect("show airing time", &settings.show_airing_time, &Some(true))?;
+    }
+    Ok(())
+}
+
+// the fields added to list entries start empty and the ones that were saved are kept
+fn check_user_data(version: &str, user_data: HashMap<i32, UserInfo>) -> Result<(), String> {
+    if version == "0" {
+        let entry = user_data.get(&1535).ok_or("1535 is missing")?;
+        expect("status", &entry.status, &String::from("COMPLETED"))?;
+        expect("progress", &entry.progress, &37)?;
+        expect("score", &entry.score, &90.0)?;
+        expect("notes", &entry.notes, &Some(String::from("rewatch soon")))?;
+        expect("updated at", &entry.updated_at, &0)?;
+        expect("repeat", &entry.repeat, &0)?;
+        expect("private", &entry.private, &false)?;
+        expect("hidden", &entry.hidden_from_status_lists, &false)?;
+        expect("custom lists", &entry.custom_lists.is_empty(), &true)?;
+        expect("advanced scores", &entry.advanced_scores.is_empty(), &true)?;
+        expect("progress 21", &user_data.get(&21).map(|entry| entry.progress), &Some(1050))?;
+    }
+    Ok(())
+}
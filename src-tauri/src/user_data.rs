@@ -1,10 +1,10 @@
-use std::{collections::{HashMap, HashSet}, cmp::Ordering};
+use std::{collections::{HashMap, HashSet}, cmp::Ordering, sync::Arc, time::Duration};
 
 use chrono::{DateTime, Local, Datelike};
 use serde::{Deserialize, Serialize};
 use tauri::async_runtime::Mutex;
 
-use crate::{constants::{USER_STATUSES, USER_LISTS, self}, GLOBAL_REFRESH_UI, api_calls::{self, TokenData}, mal_api_calls, file_operations};
+use crate::{constants::{USER_STATUSES, USER_LISTS, self}, events::{EventSink, UiEvent}, api_calls::{self, TokenData, Website}, graphql_client::{self, GraphQlClient}, mal_api_calls, file_operations, list_tracker::{self, ListTracker}, data_archive::{DataArchive, ImportConflict, ImportSummary}, list_export::ListImportSummary, scheduler::Job};
 
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -18,11 +18,74 @@ pub struct UserInfo {
     pub completed_at: Option<Date>,
     pub notes: Option<String>,
     pub updated_at: u64,
+    #[serde(default)]
+    pub repeat: i32, // times the anime was rewatched
+    #[serde(default)]
+    pub private: bool, // only the user can see the entry on the website
+    #[serde(default)]
+    pub hidden_from_status_lists: bool, // only shown in the custom lists it's on
+    #[serde(default)]
+    pub custom_lists: Vec<String>, // names of the user's custom lists the entry is on
+    #[serde(default)]
+    pub advanced_scores: HashMap<String, f32>, // score for each of the user's advanced scoring categories
 }
 
 impl UserInfo {
-    pub const fn new() -> UserInfo {
-        UserInfo { id: 0, media_id: 0, status: String::new(), score: 0.0, progress: 0, started_at: None, completed_at: None, notes: None, updated_at: 0 }
+    pub fn new() -> UserInfo {
+        UserInfo { id: 0, media_id: 0, status: String::new(), score: 0.0, progress: 0, started_at: None, completed_at: None, notes: None, updated_at: 0, repeat: 0, private: false, hidden_from_status_lists: false, custom_lists: Vec::new(), advanced_scores: HashMap::new() }
+    }
+
+    // true if both entries show the same thing on the user's list, ids and update times are ignored
+    pub fn same_list_values(&self, other: &UserInfo) -> bool {
+        self.status == other.status && self.score == other.score && self.progress == other.progress &&
+            self.started_at == other.started_at && self.completed_at == other.completed_at && self.notes == other.notes && self.repeat == other.repeat &&
+            self.private == other.private && self.hidden_from_status_lists == other.hidden_from_status_lists &&
+            self.custom_lists == other.custom_lists && self.advanced_scores == other.advanced_scores
+    }
+}
+
+// a anime that was changed on this computer and on the website before either change was synced
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SyncConflict {
+    pub media_id: i32,
+    pub local: UserInfo,
+    pub remote: UserInfo,
+    pub detected_at: u64, // unix time in seconds
+}
+
+// what has been synced with the website, saved so only newer changes are downloaded
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SyncState {
+    pub last_sync: u64, // unix time in seconds of the last successful pull, 0 if the list was never synced
+    pub conflicts: Vec<SyncConflict>, // waiting for the user to choose which change to keep
+}
+
+// how much of a local list was uploaded when a account was attached
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AttachResult {
+    pub uploaded: usize,
+    pub pending: usize, // couldn't be sent yet, they're sent with the other queued changes
+}
+
+// a score out of 10, used to move scores between score formats
+pub fn score_to_ten(score: f32, score_format: &Option<String>) -> f32 {
+    match score_format.as_deref() {
+        Some("POINT_100") => score / 10.0,
+        Some("POINT_5") => score * 2.0,
+        Some("POINT_3") => score * 10.0 / 3.0,
+        _ => score,
+    }
+}
+
+// convert a score out of 10 into a score format
+pub fn score_from_ten(score: f32, score_format: &Option<String>) -> f32 {
+    match score_format.as_deref() {
+        Some("POINT_100") => (score * 10.0).round(),
+        Some("POINT_10_DECIMAL") => (score * 10.0).round() / 10.0,
+        Some("POINT_10") => score.round(),
+        Some("POINT_5") => (score / 2.0).round(),
+        Some("POINT_3") => (score * 3.0 / 10.0).round(),
+        _ => score,
     }
 }
 
@@ -79,6 +142,14 @@ impl Date {
     pub const fn new() -> Date {
         Date { year: None, month: None, day: None }
     }
+
+    // true if the date is missing or none of its parts are known
+    pub fn is_unset(date: &Option<Date>) -> bool {
+        match date {
+            Some(date) => date.year.is_none() && date.month.is_none() && date.day.is_none(),
+            None => true,
+        }
+    }
 }
 
 
@@ -97,11 +168,26 @@ pub struct UserSettings {
     pub show_airing_time: Option<bool>,
     pub theme: Option<i32>,
     pub user_id: Option<i32>,
+    pub watched_percent: Option<i32>, // percent of a episode that must be played before it's counted as watched
+    pub completion_policy: Option<String>, // one of COMPLETION_POLICIES
+    pub mpv_socket: Option<String>,
+    pub vlc_port: Option<i32>,
+    pub vlc_password: Option<String>,
+    pub mpc_hc_port: Option<i32>,
+    pub deletion_check_interval: Option<i32>, // minutes between checking for anime removed on the website, 0 never checks
+    pub player_detection_interval: Option<i32>, // seconds between checking media players, 0 never checks
+    pub queue_flush_interval: Option<i32>, // seconds between sending changes made while the website couldn't be reached
+    pub connectivity_check_interval: Option<i32>, // seconds between checking if the website can be reached again
+    pub library_scan_interval: Option<i32>, // minutes between scanning folders for new episodes, 0 never scans
+    pub airing_refresh_interval: Option<i32>, // minutes between getting airing times, 0 never gets them
+    pub cache_refresh_interval: Option<i32>, // minutes between downloading anime info again, 0 never downloads it
+    pub custom_lists: Option<Vec<String>>, // names of the user's custom lists on the website
+    pub advanced_scoring: Option<Vec<String>>, // the user's advanced scoring categories in the order the website expects them
 }
 
 impl UserSettings {
     pub const fn new() -> UserSettings {
-        UserSettings { username: String::new(), title_language: String::new(), show_adult: false, folders: Vec::new(), update_delay: 0, score_format: None, highlight_color: String::new(), current_tab: String::new(), first_time_setup: true, show_airing_time: Some(true), theme: Some(0), user_id: None }
+        UserSettings { username: String::new(), title_language: String::new(), show_adult: false, folders: Vec::new(), update_delay: 0, score_format: None, highlight_color: String::new(), current_tab: String::new(), first_time_setup: true, show_airing_time: Some(true), theme: Some(0), user_id: None, watched_percent: None, completion_policy: None, mpv_socket: None, vlc_port: None, vlc_password: None, mpc_hc_port: None, deletion_check_interval: None, player_detection_interval: None, queue_flush_interval: None, connectivity_check_interval: None, library_scan_interval: None, airing_refresh_interval: None, cache_refresh_interval: None, custom_lists: None, advanced_scoring: None }
     }
     
     pub fn clear(&mut self) {
@@ -117,6 +203,21 @@ impl UserSettings {
         self.show_airing_time = Some(true);
         self.theme = Some(0);
         self.user_id = None;
+        self.watched_percent = None;
+        self.completion_policy = None;
+        self.mpv_socket = None;
+        self.vlc_port = None;
+        self.vlc_password = None;
+        self.mpc_hc_port = None;
+        self.deletion_check_interval = None;
+        self.player_detection_interval = None;
+        self.queue_flush_interval = None;
+        self.connectivity_check_interval = None;
+        self.library_scan_interval = None;
+        self.airing_refresh_interval = None;
+        self.cache_refresh_interval = None;
+        self.custom_lists = None;
+        self.advanced_scoring = None;
     }
 }
 
@@ -128,6 +229,9 @@ pub struct UserData {
     user_lists: HashMap<String, Vec<i32>>,
     max_episodes: HashMap<i32, Option<i32>>,
     update_queue: Vec<UserInfo>,
+    sync_state: SyncState,
+    anilist: Arc<dyn GraphQlClient>, // anilist queries are sent through this, myanimelist lists use it to look up ids
+    events: Arc<dyn EventSink>, // told when the list changes, the website can't be reached, or files can't be saved
 }
 
 
@@ -136,8 +240,8 @@ impl UserData {
 
 
 
-    pub fn new() -> UserData {
-        UserData { setting: UserSettings::new(), token: TokenData::new(), user_data: HashMap::new(), user_lists: HashMap::new(), max_episodes: HashMap::new(), update_queue: Vec::new() }
+    pub fn new(events: Arc<dyn EventSink>) -> UserData {
+        UserData { setting: UserSettings::new(), token: TokenData::new(), user_data: HashMap::new(), user_lists: HashMap::new(), max_episodes: HashMap::new(), update_queue: Vec::new(), sync_state: SyncState::default(), anilist: graphql_client::default_client(), events }
     }
     
 
@@ -145,17 +249,22 @@ impl UserData {
     pub fn clear(&mut self) {
         self.user_data.clear();
         self.user_lists.clear();
+        self.update_queue.clear();
+        self.sync_state = SyncState::default();
     }
 
 
 
     pub async fn read_files(&mut self) {
 
-        file_operations::read_file_user_settings(&mut self.setting).await;
-        file_operations::read_file_token_data(&mut self.token).await;
-        file_operations::read_file_user_data(&mut self.user_data).await;
-        file_operations::read_file_user_lists(&mut self.user_lists).await;
-        file_operations::read_file_update_queue(&mut self.update_queue).await;
+        file_operations::read_file_user_settings(&*self.events, &mut self.setting).await.ok();
+        file_operations::read_file_token_data(&*self.events, &mut self.token).await.ok();
+        file_operations::read_file_user_data(&*self.events, &mut self.user_data).await.ok();
+        file_operations::read_file_user_lists(&*self.events, &mut self.user_lists).await.ok();
+        file_operations::read_file_update_queue(&*self.events, &mut self.update_queue).await.ok();
+        file_operations::read_file_sync_state(&*self.events, &mut self.sync_state).await.ok();
+        self.events.send(UiEvent::QueuedUpdates(self.update_queue.len())).await;
+        self.events.send(UiEvent::SyncConflicts(self.sync_state.conflicts.len())).await;
     }
 
 
@@ -172,10 +281,10 @@ impl UserData {
 
     async fn check_correct_list(&mut self, data: &UserInfo) {
         
-        let old_status = if let Some(old_data) = self.user_data.get(&data.media_id) {
-            old_data.status.clone()
+        let (old_status, old_hidden) = if let Some(old_data) = self.user_data.get(&data.media_id) {
+            (old_data.status.clone(), old_data.hidden_from_status_lists)
         } else {
-            String::new()
+            (String::new(), false)
         };
 
         let mut change_list = false;
@@ -191,7 +300,7 @@ impl UserData {
         }
 
         // status has changed, move to new list
-        if change_list == true {
+        if change_list == true || old_hidden != data.hidden_from_status_lists {
             
             self.user_lists.iter_mut().for_each(|(_, list)| list.retain(|id| *id != data.media_id));
             let list = if data.status == "REPEATING" {
@@ -199,8 +308,11 @@ impl UserData {
             } else {
                 data.status.clone()
             };
-            self.user_lists.entry(list).and_modify(|entry| entry.push(data.media_id));
-            file_operations::write_file_user_lists(&self.user_lists).await;
+            // hidden entries are only shown in their custom lists
+            if data.hidden_from_status_lists == false {
+                self.user_lists.entry(list).and_modify(|entry| entry.push(data.media_id));
+            }
+            file_operations::write_file_user_lists(&*self.events, &self.user_lists).await.ok();
         }
     }
 
@@ -226,6 +338,26 @@ impl UserData {
             -1
         };
 
+        // rewatching keeps the dates from the first time the anime was watched
+        if data.status == "REPEATING" || (data.status == "COMPLETED" && old_status == "REPEATING") {
+            if let Some(old_data) = self.user_data.get(&data.media_id) {
+                if Date::is_unset(&data.started_at) {
+                    data.started_at = old_data.started_at.clone();
+                }
+                if Date::is_unset(&data.completed_at) {
+                    data.completed_at = old_data.completed_at.clone();
+                }
+            }
+        }
+
+        // finishing a rewatch counts it, changes from the website already include it
+        if update_website && data.status == "COMPLETED" && old_status == "REPEATING" {
+            let old_repeat = self.user_data.get(&data.media_id).map_or(0, |old_data| old_data.repeat);
+            if data.repeat <= old_repeat {
+                data.repeat = old_repeat + 1;
+            }
+        }
+
         self.check_correct_list(&data).await;
 
         if data.status == "COMPLETED" {
@@ -291,39 +423,270 @@ impl UserData {
             }
         }
 
+        // set the time when user data was updated to prevent being overwritten by anilist
+        data.updated_at = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+
         // update anilist
         if update_website == true {
-            api_calls::update_user_entry(self.token.access_token.clone(), data.clone()).await;
-        }
 
-        // set the time when user data was updated to prevent being overwritten by anilist
-        data.updated_at = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+            // this change replaces any change for the same anime that is still waiting to be sent
+            let queue_length = self.update_queue.len();
+            self.update_queue.retain(|entry| entry.media_id != data.media_id);
+
+            // don't send changes with a token the website will reject
+            let save_result = if self.check_token().await {
+                self.tracker().save_entry(&data).await
+            } else {
+                Err("unauthorized")
+            };
+
+            match save_result {
+                Ok(entry_id) => {
+                    data.id = entry_id;
+                    if queue_length != self.update_queue.len() {
+                        self.write_update_queue().await;
+                    }
+                },
+                Err(error) => {
+                    println!("update_user_entry: {}", error);
+                    if error == "no connection" {
+                        self.events.send(UiEvent::Connectivity(false)).await;
+                    } else if error == "unauthorized" && self.token.is_expired() == false {
+                        // website rejected a token that should still be valid
+                        self.refresh_token().await;
+                    }
+                    // keep the change so it can be sent when anilist can be reached
+                    self.update_queue.push(data.clone());
+                    self.write_update_queue().await;
+                },
+            }
+        }
 
         // save new data
         let old_data = self.user_data.insert(data.media_id, data.clone());
 
-        // write to file for the next time gekijou is opened
-        file_operations::write_file_user_data(&self.user_data).await;
+        // save for the next time gekijou is opened
+        file_operations::write_user_entry(&*self.events, &data).await.ok();
+        self.events.send(UiEvent::EntryUpdated(data)).await;
 
         Ok(old_data)
     }
 
 
 
+    // send changes that could not be sent when they were made, oldest first
+    // each change is checked against anilist first so a newer change made elsewhere is not overwritten
+    // returns the number of changes that were sent
+    pub async fn check_queued_updates(&mut self) -> Result<usize, &'static str> {
+
+        if self.update_queue.is_empty() {
+            return Ok(0);
+        }
+
+        // changes stay in the queue until the user logs in again
+        if self.check_token().await == false {
+            return Err("unauthorized");
+        }
+
+        let mut sent = 0;
+        let mut result = Ok(0);
+        while let Some(queued) = self.update_queue.first().cloned() {
+
+            // check if the entry was changed on anilist after the queued change was made
+            match self.tracker().get_entry(&queued).await {
+                Ok(Some(remote)) => {
+                    // changed in both places, the user has to choose which change is kept
+                    if remote.updated_at > queued.updated_at {
+                        self.update_queue.remove(0);
+                        if remote.same_list_values(&queued) == false {
+                            self.add_conflict(queued, remote).await;
+                        }
+                        continue;
+                    }
+                },
+                Ok(None) => {},
+                Err(error) => {
+                    result = Err(error);
+                    break;
+                },
+            }
+
+            match self.tracker().save_entry(&queued).await {
+                Ok(entry_id) => {
+                    self.update_queue.remove(0);
+                    sent += 1;
+                    // new entries get their list entry id from the website
+                    if let Some(entry) = self.user_data.get_mut(&queued.media_id) {
+                        entry.id = entry_id;
+                    }
+                },
+                Err(list_tracker::ENTRY_REJECTED) => {
+                    // the website rejected the change, sending it again won't help
+                    self.update_queue.remove(0);
+                    self.events.send(UiEvent::Error(format!("A offline change to anime {} was rejected by {}", queued.media_id, self.token.website.to_string()))).await;
+                },
+                Err(error) => {
+                    result = Err(error);
+                    break;
+                },
+            }
+        }
+
+        if result == Err("no connection") {
+            self.events.send(UiEvent::Connectivity(false)).await;
+        } else if result == Err("unauthorized") {
+            self.refresh_token().await;
+        } else if result.is_ok() {
+            self.events.send(UiEvent::Connectivity(true)).await;
+            result = Ok(sent);
+        }
+
+        if sent > 0 {
+            file_operations::write_file_user_data(&*self.events, &self.user_data).await.ok();
+        }
+        self.write_update_queue().await;
+        self.write_sync_state().await;
+
+        result
+    }
+
+
+
+    // keep both versions of a entry until the user chooses one, a existing conflict for the anime gets the newer website version
+    async fn add_conflict(&mut self, local: UserInfo, remote: UserInfo) {
+
+        if let Some(conflict) = self.sync_state.conflicts.iter_mut().find(|conflict| conflict.media_id == local.media_id) {
+            conflict.remote = remote;
+            return;
+        }
+
+        self.events.send(UiEvent::Error(format!("Anime {} was changed on this computer and on {}, choose which change to keep in Settings", local.media_id, self.token.website.to_string()))).await;
+        self.sync_state.conflicts.push(SyncConflict {
+            media_id: local.media_id,
+            local,
+            remote,
+            detected_at: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs(),
+        });
+    }
+
+
+
+    // save the sync state and show the number of conflicts waiting for the user
+    async fn write_sync_state(&self) {
+        file_operations::write_file_sync_state(&*self.events, &self.sync_state).await.ok();
+        self.events.send(UiEvent::SyncConflicts(self.sync_state.conflicts.len())).await;
+    }
+
+
+
+    pub fn get_conflicts(&self) -> Vec<SyncConflict> {
+        self.sync_state.conflicts.clone()
+    }
+
+
+
+    // keep one side of a conflict, the local change is sent to the website or the website's change replaces the local entry
+    pub async fn resolve_conflict(&mut self, media_id: i32, keep_local: bool) -> Result<(), &'static str> {
+
+        let index = match self.sync_state.conflicts.iter().position(|conflict| conflict.media_id == media_id) {
+            Some(index) => index,
+            None => return Err("no conflict for this anime"),
+        };
+        let conflict = self.sync_state.conflicts.remove(index);
+
+        let result = if keep_local {
+            let mut local = conflict.local;
+            local.id = conflict.remote.id; // the entry may have been created on the website
+            self.set_user_data(local, true).await
+        } else {
+            self.set_user_data(conflict.remote, false).await
+        };
+        self.write_sync_state().await;
+        self.events.send(UiEvent::ListChanged).await;
+
+        result.map(|_old_data| ())
+    }
+
+
+
+    // save the update queue and show the number of changes waiting to be sent
+    async fn write_update_queue(&self) {
+        file_operations::write_file_update_queue(&*self.events, &self.update_queue).await.ok();
+        self.events.send(UiEvent::QueuedUpdates(self.update_queue.len())).await;
+    }
+
+
+
+    // send anilist queries through a different client, like a fake one that answers without a connection
+    pub fn set_anilist_client(&mut self, anilist: Arc<dyn GraphQlClient>) {
+        self.anilist = anilist;
+    }
+
+
+
+    // the website the user's list is kept on
+    fn tracker(&self) -> Box<dyn ListTracker> {
+        list_tracker::get_tracker(&self.token, &self.setting.advanced_scoring.clone().unwrap_or_default(), self.anilist.clone())
+    }
+
+
+
+    // makes sure the access token can still be used before the user's list is changed
+    // returns true if the token can be used
+    async fn check_token(&mut self) -> bool {
+        if self.token.is_expired() == false {
+            return true;
+        }
+        self.refresh_token().await
+    }
+
+
+
+    // get a new access token after the old one expired or was rejected
+    // myanimelist tokens can be refreshed, anilist tokens can't so the user is asked to log in again
+    // returns true if a new token was received
+    async fn refresh_token(&mut self) -> bool {
+
+        if let Website::MyAnimeList = self.token.website {
+            match mal_api_calls::mal_refresh_token(&self.token.refresh_token).await {
+                Ok(token) => {
+                    self.token = token;
+                    file_operations::write_file_token_data(&*self.events, &self.token).await.ok();
+                    return true;
+                },
+                Err("no connection") => {
+                    // can't tell if the refresh token is still valid, try again later
+                    self.events.send(UiEvent::Connectivity(false)).await;
+                    return false;
+                },
+                Err(error) => println!("mal_refresh_token: {}", error),
+            }
+        }
+
+        self.events.send(UiEvent::Reauthorize(true)).await;
+        false
+    }
+
+
+
     pub async fn remove_anime(&mut self, media_id: i32) ->Result<bool, &'static str> {
 
+        if self.check_token().await == false {
+            return Err("login expired");
+        }
+
         if let Some(anime) = self.user_data.get(&media_id){
 
-            // remove from users anilist
-            match api_calls::anilist_remove_entry(anime.id, self.token.access_token.clone()).await {
+            // remove from the users list on the website
+            match self.tracker().delete_entry(anime).await {
                 Ok(removed) => {
                     if removed == true {
                 
                         self.user_lists.iter_mut().for_each(|(_,list)| list.retain(|list_id| *list_id != media_id));
                         self.user_data.remove(&media_id);
 
-                        file_operations::write_file_user_data(&mut self.user_data).await;
-                        file_operations::write_file_user_lists(&mut self.user_lists).await;
+                        file_operations::write_file_user_data(&*self.events, &mut self.user_data).await.ok();
+                        file_operations::write_file_user_lists(&*self.events, &mut self.user_lists).await.ok();
                     }
                     return Ok(removed)
                 },
@@ -336,17 +699,47 @@ impl UserData {
 
 
 
+    // the user's custom list names, custom lists aren't downloaded separately, they're built from the entries on them
+    pub fn is_custom_list(&self, name: &String) -> bool {
+        USER_LISTS.contains(&name.as_str()) == false && self.setting.custom_lists.as_ref().map_or(false, |lists| lists.contains(name))
+    }
+
+    // ids of the anime on a custom list, ordered by the status list they're in
+    fn get_custom_list(&self, name: &String) -> Vec<i32> {
+
+        let mut list: Vec<i32> = Vec::new();
+        for status_list in USER_LISTS {
+            if let Some(ids) = self.user_lists.get(status_list) {
+                list.extend(ids.iter().filter(|id| self.user_data.get(id).map_or(false, |entry| entry.custom_lists.contains(name))));
+            }
+        }
+        // hidden entries aren't in any status list
+        let mut hidden: Vec<i32> = self.user_data.values()
+            .filter(|entry| entry.hidden_from_status_lists && entry.custom_lists.contains(name))
+            .map(|entry| entry.media_id)
+            .collect();
+        hidden.sort();
+        list.extend(hidden);
+        list
+    }
+
+
+
     pub async fn get_list(&mut self, name: &String) -> Result<Vec<i32>, &'static str> {
         
+        if self.is_custom_list(name) {
+            return Ok(self.get_custom_list(name));
+        }
+
         if USER_LISTS.contains(&name.as_str()) == false {
             return Err("invalid list");
         }
 
         if self.user_lists.contains_key(name) == false {
             // list is missing, get it
-            api_calls::anilist_get_list(self.setting.username.clone(), String::from(name), self.token.access_token.clone(), &mut self.user_data, &mut self.user_lists).await;
-            file_operations::write_file_user_lists(&self.user_lists).await;
-            file_operations::write_file_user_data(&self.user_data).await;
+            self.tracker().get_list(&self.setting.username, name, &mut self.user_data, &mut self.user_lists).await;
+            file_operations::write_file_user_lists(&*self.events, &self.user_lists).await.ok();
+            file_operations::write_file_user_data(&*self.events, &self.user_data).await.ok();
         }
 
         let list = self.user_lists.get(name).unwrap().clone();
@@ -358,18 +751,23 @@ impl UserData {
 
     pub async fn get_list_paged(&mut self, name: &String, page: usize, entries_per_page: usize) -> Result<Vec<i32>, &'static str> {
         
-        if USER_LISTS.contains(&name.as_str()) == false {
+        let custom_list = self.is_custom_list(name);
+        if custom_list == false && USER_LISTS.contains(&name.as_str()) == false {
             return Err("invalid list");
         }
 
-        if self.user_lists.contains_key(name) == false {
+        if custom_list == false && self.user_lists.contains_key(name) == false {
             // list is missing, get it
-            api_calls::anilist_get_list(self.setting.username.clone(), String::from(name), self.token.access_token.clone(), &mut self.user_data, &mut self.user_lists).await;
-            file_operations::write_file_user_lists(&self.user_lists).await;
-            file_operations::write_file_user_data(&self.user_data).await;
+            self.tracker().get_list(&self.setting.username, name, &mut self.user_data, &mut self.user_lists).await;
+            file_operations::write_file_user_lists(&*self.events, &self.user_lists).await.ok();
+            file_operations::write_file_user_data(&*self.events, &self.user_data).await.ok();
         }
 
-        let user_list = self.user_lists.get(name).unwrap();
+        let user_list = if custom_list {
+            self.get_custom_list(name)
+        } else {
+            self.user_lists.get(name).unwrap().clone()
+        };
         let start = if page * entries_per_page < user_list.len() {
             page * entries_per_page
         } else {
@@ -391,15 +789,7 @@ impl UserData {
 
     pub async fn get_list_with_data(&mut self, name: &String) -> Result<Vec<UserInfo>, &'static str> {
         
-        if USER_LISTS.contains(&name.as_str()) == false {
-            return Err("invalid list");
-        }
-
-        if self.user_lists.contains_key(name) == false {
-            // list is missing, get it
-        }
-
-        let list = self.user_lists.get(name).unwrap().clone();
+        let list = self.get_list(name).await?;
         let mut data_list: Vec<UserInfo> = Vec::new();
         for id in list {
             if let Some(data) = self.user_data.get(&id) {
@@ -429,31 +819,168 @@ impl UserData {
 
 
 
+    // compare the anime on each list with the website's list and remove anime that were deleted on the website
+    // anime with changes waiting to be sent or unresolved conflicts are kept
+    // returns the ids of the anime that were removed
+    pub async fn remove_deleted_entries(&mut self) -> Result<Vec<i32>, &'static str> {
+
+        // this computer has the only copy of the list
+        if self.is_local() {
+            return Ok(Vec::new());
+        }
+
+        if self.setting.username.is_empty() {
+            return Err("No username");
+        }
+
+        let remote_lists = match self.tracker().get_list_ids(&self.setting.username).await {
+            Ok(remote_lists) => remote_lists,
+            Err(error) => {
+                if error == "no connection" {
+                    self.events.send(UiEvent::Connectivity(false)).await;
+                }
+                return Err(error);
+            },
+        };
+
+        // a empty response for a list that isn't empty is more likely a failed request than the user deleting everything
+        if remote_lists.values().all(|ids| ids.is_empty()) && self.user_data.is_empty() == false {
+            return Err("website list is empty");
+        }
+
+        let remote_ids: HashSet<i32> = remote_lists.values().flatten().cloned().collect();
+        let mut removed: Vec<i32> = Vec::new();
+        for list in USER_LISTS {
+
+            // lists that haven't been downloaded yet have nothing to compare
+            let local_ids = match self.user_lists.get(list) {
+                Some(local_ids) => local_ids.clone(),
+                None => continue,
+            };
+            let remote_list = remote_lists.get(list);
+
+            for media_id in local_ids {
+                if remote_list.map_or(false, |ids| ids.contains(&media_id)) {
+                    continue;
+                }
+                // moved to a different list, pull_updates will move it here too
+                if remote_ids.contains(&media_id) {
+                    continue;
+                }
+                // added or changed on this computer and not on the website yet
+                if self.update_queue.iter().any(|queued| queued.media_id == media_id) ||
+                    self.sync_state.conflicts.iter().any(|conflict| conflict.media_id == media_id) {
+                    continue;
+                }
+                removed.push(media_id);
+            }
+        }
+
+        // hidden entries are only on custom lists so they're not in any of the lists above
+        let listed: HashSet<i32> = self.user_lists.values().flatten().cloned().collect();
+        let mut unlisted: Vec<i32> = self.user_data.values()
+            .filter(|entry| listed.contains(&entry.media_id) == false && remote_ids.contains(&entry.media_id) == false)
+            .filter(|entry| {
+                // lists that haven't been downloaded yet have nothing to compare
+                let list = if entry.status == "REPEATING" { "CURRENT" } else { entry.status.as_str() };
+                self.user_lists.contains_key(list)
+            })
+            .filter(|entry| self.update_queue.iter().any(|queued| queued.media_id == entry.media_id) == false &&
+                self.sync_state.conflicts.iter().any(|conflict| conflict.media_id == entry.media_id) == false)
+            .map(|entry| entry.media_id)
+            .collect();
+        unlisted.sort();
+        removed.extend(unlisted);
+
+        if removed.is_empty() == false {
+            self.user_lists.iter_mut().for_each(|(_, list)| list.retain(|media_id| removed.contains(media_id) == false));
+            self.user_data.retain(|media_id, _| removed.contains(media_id) == false);
+            file_operations::write_file_user_data(&*self.events, &self.user_data).await.ok();
+            file_operations::write_file_user_lists(&*self.events, &self.user_lists).await.ok();
+        }
+        self.events.send(UiEvent::Connectivity(true)).await;
+
+        Ok(removed)
+    }
+
+
+
+    // download changes made on the website since the last sync
+    // entries that were also changed here before the change was sent are kept as conflicts for the user to resolve
+    // returns true if the user's list was changed
     pub async fn pull_updates(&mut self) -> Result<bool, &'static str> {
+
+        // nothing to pull from
+        if self.is_local() {
+            return Ok(false);
+        }
         
         if self.setting.username.is_empty() {
             return Err("No username");
         }
 
-        match api_calls::get_updated_media_ids(self.setting.username.clone(), 0).await {
-            Ok(list) => {
-                GLOBAL_REFRESH_UI.lock().await.no_internet = false;
-                
-                for entry in list {
-                    if self.user_data.contains_key(&entry.media_id) {
-                        let client_updated_at = self.user_data.get(&entry.media_id).unwrap().updated_at;
-                        if entry.updated_at > client_updated_at {
-                            self.set_user_data(entry, false).await;
-                        }
-                    } else {
-                        self.set_user_data(entry, false).await;
-                    }
+        // custom lists and scoring categories can be changed on the website at any time
+        if let Err(error) = self.refresh_list_options().await {
+            println!("refresh_list_options: {}", error);
+        }
+
+        let sync_started = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let since = self.sync_state.last_sync.saturating_sub(constants::SYNC_OVERLAP);
+        let list = match self.tracker().get_updates_since(&self.setting.username, since).await {
+            Ok(list) => list,
+            Err(error) => {
+                if error == "no connection" {
+                    self.events.send(UiEvent::Connectivity(false)).await;
                 }
+                return Err(error);
             },
-            Err(_error) => GLOBAL_REFRESH_UI.lock().await.no_internet = true,
+        };
+        self.events.send(UiEvent::Connectivity(true)).await;
+
+        let queue_length = self.update_queue.len();
+        let mut changed = false;
+        // oldest first so the newest change is the one kept
+        for remote in list.into_iter().rev() {
+
+            // the user hasn't chosen yet, show them the newest website change
+            if self.sync_state.conflicts.iter().any(|conflict| conflict.media_id == remote.media_id) {
+                if let Some(local) = self.user_data.get(&remote.media_id).cloned() {
+                    self.add_conflict(local, remote).await;
+                }
+                continue;
+            }
+
+            // changed here and on the website before the change here was sent
+            if let Some(index) = self.update_queue.iter().position(|queued| queued.media_id == remote.media_id) {
+                let queued = self.update_queue.remove(index);
+                if queued.same_list_values(&remote) == false {
+                    self.add_conflict(queued, remote).await;
+                }
+                continue;
+            }
+
+            let unchanged = match self.user_data.get(&remote.media_id) {
+                Some(local) => local.same_list_values(&remote),
+                None => false,
+            };
+            if unchanged == false {
+                if let Err(error) = self.set_user_data(remote, false).await {
+                    println!("pull_updates: {}", error);
+                }
+                changed = true;
+            }
+        }
+
+        self.sync_state.last_sync = sync_started;
+        self.write_sync_state().await;
+        if queue_length != self.update_queue.len() {
+            self.write_update_queue().await;
+        }
+        if changed {
+            self.events.send(UiEvent::ListChanged).await;
         }
     
-        Ok(true)
+        Ok(changed)
     }
 
 
@@ -464,7 +991,7 @@ impl UserData {
 
             if media.progress == 0 && media.status == constants::USER_STATUSES[4] /* Planning */ {
                 media.status = constants::USER_STATUSES[0].to_string(); // current
-                GLOBAL_REFRESH_UI.lock().await.anime_list = true;
+                self.events.send(UiEvent::ListChanged).await;
             }
 
             media.progress += length;
@@ -472,9 +999,10 @@ impl UserData {
             if let Some(episodes) = self.max_episodes.get(&media_id).cloned() {
                 if let Some(episodes_unwrapped) = episodes {
 
+                    // a finished rewatch is counted when the entry is saved
                     if media.progress >= episodes_unwrapped {
                         media.status = constants::USER_STATUSES[1].to_string(); // completed
-                        GLOBAL_REFRESH_UI.lock().await.anime_list = true;
+                        self.events.send(UiEvent::ListChanged).await;
                     }
                 }
             }
@@ -496,17 +1024,13 @@ impl UserData {
     // takes a oauth code from the user and exchanges it for a oauth access token
     pub async fn anilist_oauth_token(&mut self, code: String) -> (bool, String) {
         
-        let token = api_calls::anilist_get_access_token2(code).await;
-        let combine = format!("{}\n{}", token.token_type, token.access_token);
-
-        if token.access_token.len() == 0 {
-            return (false, combine);
-        }
-        else {
-            self.token = token;
+        match api_calls::anilist_get_access_token2(code).await {
+            Ok(token) => self.token = token,
+            Err(error) => return (false, String::from(error)),
         }
 
-        file_operations::write_file_token_data(&self.token).await;
+        file_operations::write_file_token_data(&*self.events, &self.token).await.ok();
+        self.events.send(UiEvent::Reauthorize(false)).await;
         
         (true, String::new())
     }
@@ -516,17 +1040,13 @@ impl UserData {
     // takes a oauth code from the user and exchanges it for a oauth access token
     pub async fn mal_oauth_token(&mut self, code: String, code_verifier: String) -> (bool, String) {
         
-        let token = mal_api_calls::mal_get_access_token(code.as_str(), code_verifier.as_str()).await;
-        let combine = format!("{}\n{}", token.token_type, token.access_token);
-
-        if token.access_token.len() == 0 {
-            return (false, combine);
-        }
-        else {
-            self.token = token;
+        match mal_api_calls::mal_get_access_token(code.as_str(), code_verifier.as_str()).await {
+            Ok(token) => self.token = token,
+            Err(error) => return (false, error),
         }
 
-        file_operations::write_file_token_data(&self.token).await;
+        file_operations::write_file_token_data(&*self.events, &self.token).await.ok();
+        self.events.send(UiEvent::Reauthorize(false)).await;
         
         (true, String::new())
     }
@@ -558,38 +1078,52 @@ impl UserData {
 
         if self.setting.show_adult != new_user_settings.show_adult ||
             self.setting.title_language != new_user_settings.title_language {
-            GLOBAL_REFRESH_UI.lock().await.anime_list = true;
+            self.events.send(UiEvent::ListChanged).await;
         }
 
         let old_current_tab = self.setting.current_tab.clone(); // don't change this value
         let score_format = self.setting.score_format.clone(); // don't change this value
+        let custom_lists = self.setting.custom_lists.clone(); // set by the website
+        let advanced_scoring = self.setting.advanced_scoring.clone(); // set by the website
         let old_username = self.setting.username.clone();
         self.setting = new_user_settings;
         self.setting.score_format = score_format;
+        self.setting.custom_lists = custom_lists;
+        self.setting.advanced_scoring = advanced_scoring;
         self.setting.current_tab = old_current_tab;
+        if let Some(policy) = &self.setting.completion_policy {
+            if constants::COMPLETION_POLICIES.contains(&policy.as_str()) == false {
+                self.setting.completion_policy = None;
+            }
+        }
+
+        // a list without a account isn't tied to a username
+        if self.is_local() {
+            if self.setting.score_format.is_none() {
+                self.setting.score_format = Some(String::from(constants::LOCAL_SCORE_FORMAT));
+            }
+            file_operations::write_file_user_settings(&*self.events, &self.setting).await.ok();
+            return (scan, None);
+        }
 
         // user is different, their list and score format will be different
         if old_username != self.setting.username {
 
             self.user_data.clear();
             self.user_lists.clear();
+            self.sync_state = SyncState::default();
 
-            match api_calls::get_user_score_format(self.setting.username.clone()).await {
+            match self.tracker().get_score_format(&self.setting.username).await {
                 Ok(result) => {
                     self.setting.score_format = Some(result);
                 },
                 Err(_error) => self.setting.score_format = None,
             }
+            self.setting.custom_lists = None;
+            self.setting.advanced_scoring = None;
+            self.refresh_list_options().await.ok();
             
-            let lists = vec!["CURRENT","COMPLETED","PAUSED","DROPPED","PLANNING"];
-            let mut list_count = 0;
-            for list in lists {
-                list_count += 1;
-                GLOBAL_REFRESH_UI.lock().await.loading_dialog = Some(format!("Downloading User Lists ({} of 5)", list_count));
-                api_calls::anilist_get_list(self.setting.username.clone(), String::from(list), self.token.access_token.clone(), &mut self.user_data, &mut self.user_lists).await;
-            }
-            file_operations::write_file_user_data(&self.user_data).await;
-            file_operations::write_file_user_lists(&self.user_lists).await;
+            self.download_lists().await;
 
             let user_media_ids: Vec<i32> = {
                 let mut media_ids: Vec<i32> = Vec::new();
@@ -597,11 +1131,11 @@ impl UserData {
                 media_ids
             };
 
-            file_operations::write_file_user_settings(&self.setting).await;
+            file_operations::write_file_user_settings(&*self.events, &self.setting).await.ok();
             return (scan, Some(user_media_ids));
         } else {
             
-            file_operations::write_file_user_settings(&self.setting).await;
+            file_operations::write_file_user_settings(&*self.events, &self.setting).await.ok();
             return (scan, None);
         }
 
@@ -609,6 +1143,106 @@ impl UserData {
 
 
 
+    // download all of the user's lists from the website, entries waiting to be sent are kept over the downloaded ones
+    async fn download_lists(&mut self) {
+
+        let sync_started = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        let tracker = self.tracker();
+        let mut list_count = 0;
+        for list in USER_LISTS {
+            list_count += 1;
+            self.events.send(UiEvent::Loading(Some(format!("Downloading User Lists ({} of {})", list_count, USER_LISTS.len())))).await;
+            tracker.get_list(&self.setting.username, &String::from(list), &mut self.user_data, &mut self.user_lists).await;
+        }
+
+        for queued in self.update_queue.clone() {
+            self.move_to_status_list(&queued);
+            self.user_data.insert(queued.media_id, queued);
+        }
+
+        file_operations::write_file_user_data(&*self.events, &self.user_data).await.ok();
+        file_operations::write_file_user_lists(&*self.events, &self.user_lists).await.ok();
+
+        // the whole list was downloaded, only later changes need to be pulled
+        self.sync_state.last_sync = sync_started;
+        self.write_sync_state().await;
+    }
+
+
+
+    // get the user's custom lists and advanced scoring categories from the website
+    async fn refresh_list_options(&mut self) -> Result<(), &'static str> {
+
+        if self.is_local() || self.setting.username.is_empty() {
+            return Ok(());
+        }
+
+        let options = self.tracker().get_list_options(&self.setting.username).await?;
+        let custom_lists = Some(options.custom_lists);
+        let advanced_scoring = Some(options.advanced_scoring);
+        if self.setting.custom_lists != custom_lists || self.setting.advanced_scoring != advanced_scoring {
+            self.setting.custom_lists = custom_lists;
+            self.setting.advanced_scoring = advanced_scoring;
+            file_operations::write_file_user_settings(&*self.events, &self.setting).await.ok();
+        }
+        Ok(())
+    }
+
+
+
+    // true if the user hasn't logged into a website and their list is only kept on this computer
+    pub fn is_local(&self) -> bool {
+        matches!(self.token.website, Website::None)
+    }
+
+
+
+    // upload a list that was kept without a account to the website the user just logged into
+    // the user's list on the website is downloaded afterwards, anything that couldn't be sent stays queued
+    pub async fn attach_account(&mut self, username: String) -> Result<AttachResult, &'static str> {
+
+        if self.is_local() {
+            return Err("log in first");
+        }
+
+        let local_score_format = self.setting.score_format.clone();
+        self.setting.username = username;
+        let score_format = self.tracker().get_score_format(&self.setting.username).await?;
+        self.setting.score_format = Some(score_format);
+
+        // every local entry is sent like a change made while offline
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        for entry in self.user_data.values() {
+            let mut queued = entry.clone();
+            queued.id = 0; // the website gives entries their id
+            queued.score = score_from_ten(score_to_ten(entry.score, &local_score_format), &self.setting.score_format);
+            queued.updated_at = now;
+            self.update_queue.retain(|existing| existing.media_id != queued.media_id);
+            self.update_queue.push(queued);
+        }
+        let total = self.update_queue.len();
+        self.events.send(UiEvent::Loading(Some(format!("Uploading {} Anime", total)))).await;
+
+        let uploaded = match self.check_queued_updates().await {
+            Ok(sent) => sent,
+            Err(error) => {
+                println!("attach_account: {}", error);
+                total - self.update_queue.len()
+            },
+        };
+
+        self.user_data.clear();
+        self.user_lists.clear();
+        self.download_lists().await;
+        file_operations::write_file_user_settings(&*self.events, &self.setting).await.ok();
+        self.events.send(UiEvent::Loading(None)).await;
+        self.events.send(UiEvent::ListChanged).await;
+
+        Ok(AttachResult { uploaded, pending: self.update_queue.len() })
+    }
+
+
+
     pub fn set_current_tab(&mut self, tab: String) {
         self.setting.current_tab = tab;
         //file_operations::write_file_user_settings
@@ -623,6 +1257,63 @@ impl UserData {
 
 
 
+    // time between checks for anime removed from the list on the website, None if the user turned the checks off
+    pub fn get_deletion_check_interval(&self) -> Option<Duration> {
+
+        match self.setting.deletion_check_interval.unwrap_or(constants::DEFAULT_DELETION_CHECK_INTERVAL) {
+            minutes if minutes > 0 => Some(Duration::from_secs(minutes as u64 * constants::SECONDS_IN_MINUTES as u64)),
+            _ => None,
+        }
+    }
+
+
+
+    // time between runs of a background job, None if the user turned it off
+    pub fn get_job_interval(&self, job: Job) -> Option<Duration> {
+
+        let seconds = |setting: Option<i32>, default: i32| match setting.unwrap_or(default) {
+            seconds if seconds > 0 => Some(Duration::from_secs(seconds as u64)),
+            _ => None,
+        };
+        let minutes = |setting: Option<i32>, default: i32| seconds(setting.map(|minutes| minutes * constants::SECONDS_IN_MINUTES), default * constants::SECONDS_IN_MINUTES);
+
+        match job {
+            // longer gaps between checks aren't counted as watching
+            Job::PlayerDetection => seconds(self.setting.player_detection_interval, constants::DEFAULT_PLAYER_DETECTION_INTERVAL)
+                .map(|interval| interval.min(Duration::from_secs_f64(constants::MAX_WATCH_TIME_GAP))),
+            Job::QueueFlush => seconds(self.setting.queue_flush_interval, constants::DEFAULT_QUEUE_FLUSH_INTERVAL),
+            Job::ConnectivityProbe => seconds(self.setting.connectivity_check_interval, constants::NO_INTERNET_UPDATE_INTERVAL),
+            Job::LibraryRescan => minutes(self.setting.library_scan_interval, constants::DEFAULT_LIBRARY_SCAN_INTERVAL),
+            Job::AiringRefresh => minutes(self.setting.airing_refresh_interval, constants::DEFAULT_AIRING_REFRESH_INTERVAL),
+            Job::CacheRefresh => minutes(self.setting.cache_refresh_interval, constants::DEFAULT_CACHE_REFRESH_INTERVAL),
+            Job::ListSync => Some(Duration::from_secs(constants::SYNC_INTERVAL)),
+            Job::DeletionCheck => self.get_deletion_check_interval(),
+        }
+    }
+
+
+
+    // how many seconds of a episode must be watched before it's counted, runtime is the length of the episode in seconds if it's known
+    pub fn get_required_watch_time(&self, runtime: Option<f64>) -> f64 {
+
+        let delay = (self.setting.update_delay * constants::SECONDS_IN_MINUTES) as f64;
+        let runtime = match runtime {
+            Some(runtime) if runtime > 0.0 => runtime,
+            _ => return delay, // nothing to compare against, fall back to a flat delay
+        };
+        let watched_percent = self.setting.watched_percent.unwrap_or(constants::DEFAULT_WATCHED_PERCENT).clamp(1, 100) as f64 / 100.0;
+
+        match self.setting.completion_policy.as_deref() {
+            // flat delay, shorts that end before the delay are counted near their end instead
+            Some("MINUTES") => delay.min(runtime * watched_percent),
+            // everything up to the ending song, short episodes don't have much of one
+            Some("CREDITS") => runtime - constants::CREDITS_LENGTH.min(runtime * 0.25),
+            _ => runtime * watched_percent,
+        }
+    }
+
+
+
     pub fn set_highlight(&mut self, color: String) {
         self.setting.highlight_color = color;
     }
@@ -669,4 +1360,134 @@ impl UserData {
     pub fn set_max_episodes(&mut self, max_episodes: HashMap<i32, Option<i32>>) {
         self.max_episodes = max_episodes;
     }
+
+
+
+    // copy the settings and list into a archive, the token is only included if the user asked for it
+    pub fn export_archive(&self, archive: &mut DataArchive, include_token: bool) {
+
+        archive.settings = Some(self.setting.clone());
+        if include_token && self.token.access_token.is_empty() == false {
+            archive.token = Some(self.token.clone());
+        }
+        archive.user_data = self.user_data.clone();
+        archive.user_lists = self.user_lists.clone();
+    }
+
+
+
+    // add the settings and list from a archive, changes aren't sent to the website because they came from it
+    // returns the ids of every anime on the list afterwards
+    pub async fn import_archive(&mut self, archive: &DataArchive, conflict: ImportConflict, summary: &mut ImportSummary) -> Vec<i32> {
+
+        if let Some(settings) = &archive.settings {
+            // a new install has no settings worth keeping
+            if self.setting.first_time_setup || conflict != ImportConflict::KeepLocal {
+                let current_tab = self.setting.current_tab.clone();
+                // folders are where this computer keeps its files, episode paths are rebuilt from them
+                // the archive's folders are only used when none are set, like restoring onto the same computer
+                let folders = self.setting.folders.clone();
+                self.setting = settings.clone();
+                self.setting.current_tab = current_tab;
+                if folders.iter().any(|folder| folder.is_empty() == false) {
+                    self.setting.folders = folders;
+                }
+                self.setting.first_time_setup = false;
+                summary.settings = true;
+                file_operations::write_file_user_settings(&*self.events, &self.setting).await.ok();
+            }
+        }
+
+        if let Some(token) = &archive.token {
+            if self.token.access_token.is_empty() || conflict != ImportConflict::KeepLocal {
+                self.token = token.clone();
+                summary.token = true;
+                file_operations::write_file_token_data(&*self.events, &self.token).await.ok();
+                self.events.send(UiEvent::Reauthorize(false)).await;
+            }
+        }
+
+        let empty_lists = self.user_lists.is_empty();
+        for (media_id, entry) in &archive.user_data {
+
+            if let Some(local) = self.user_data.get(media_id) {
+                if conflict.use_archive(local.updated_at, entry.updated_at) == false {
+                    summary.kept += 1;
+                    continue;
+                }
+                summary.replaced += 1;
+            } else {
+                summary.added += 1;
+            }
+
+            if empty_lists == false {
+                self.move_to_status_list(entry);
+            }
+            self.user_data.insert(*media_id, entry.clone());
+        }
+
+        // nothing to merge with, keep the order the lists were in
+        if empty_lists {
+            for (name, list) in &archive.user_lists {
+                self.user_lists.insert(name.clone(), list.iter().filter(|id| self.user_data.contains_key(id)).cloned().collect());
+            }
+        }
+
+        file_operations::write_file_user_data(&*self.events, &self.user_data).await.ok();
+        file_operations::write_file_user_lists(&*self.events, &self.user_lists).await.ok();
+        self.events.send(UiEvent::ListChanged).await;
+
+        self.user_data.keys().cloned().collect()
+    }
+
+
+
+    // add entries imported from another tracker's export, anime already on the list are only replaced if overwrite is true
+    // the entries are queued so they're sent to the website like changes made while offline
+    pub async fn import_entries(&mut self, entries: Vec<UserInfo>, overwrite: bool, summary: &mut ListImportSummary) {
+
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        for mut entry in entries {
+
+            if let Some(local) = self.user_data.get(&entry.media_id) {
+                if overwrite == false {
+                    summary.skipped += 1;
+                    continue;
+                }
+                entry.id = local.id;
+                summary.replaced += 1;
+            } else {
+                summary.added += 1;
+            }
+            entry.updated_at = now;
+
+            self.move_to_status_list(&entry);
+            self.update_queue.retain(|queued| queued.media_id != entry.media_id);
+            self.update_queue.push(entry.clone());
+            self.user_data.insert(entry.media_id, entry);
+        }
+
+        file_operations::write_file_user_data(&*self.events, &self.user_data).await.ok();
+        file_operations::write_file_user_lists(&*self.events, &self.user_lists).await.ok();
+        self.write_update_queue().await;
+        self.events.send(UiEvent::ListChanged).await;
+    }
+
+
+
+    // put a entry in the list for its status, repeating anime are in the current list
+    // entries hidden from status lists are only shown in their custom lists
+    fn move_to_status_list(&mut self, data: &UserInfo) {
+
+        self.user_lists.iter_mut().for_each(|(_, list)| list.retain(|id| *id != data.media_id));
+        if data.hidden_from_status_lists {
+            return;
+        }
+        let list = if data.status == "REPEATING" {
+            String::from("CURRENT")
+        } else {
+            data.status.clone()
+        };
+        self.user_lists.entry(list).or_default().push(data.media_id);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,144 @@
+use std::{collections::HashMap, sync::Arc, time::{SystemTime, UNIX_EPOCH}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{events::EventSink, file_operations, user_data::UserInfo};
+
+
+
+// how the episode came to be counted as watched
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum WatchSource {
+    AutoDetected, // found playing in a media player
+    Manual, // the user changed their progress
+    PlayButton, // opened from gekijou and found playing in a media player
+}
+
+// a episode or range of episodes the user's progress was updated for
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WatchRecord {
+    pub media_id: i32,
+    pub first_episode: i32,
+    pub last_episode: i32,
+    pub timestamp: u64, // unix time in seconds
+    pub file_path: Option<String>,
+    pub source: WatchSource,
+    pub previous: UserInfo, // the user's entry before it was updated, used to undo the record
+}
+
+#[derive(Clone)]
+pub struct WatchHistory {
+    records: Vec<WatchRecord>, // oldest first
+    play_button: HashMap<i32, (i32, String)>, // episodes opened from gekijou, media id to episode and file path
+    events: Arc<dyn EventSink>, // told when the history can't be saved
+}
+
+impl WatchHistory {
+
+    pub fn new(events: Arc<dyn EventSink>) -> WatchHistory {
+        WatchHistory { records: Vec::new(), play_button: HashMap::new(), events }
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+        self.play_button.clear();
+    }
+
+    pub async fn read_files(&mut self) {
+        file_operations::read_file_watch_history(&*self.events, &mut self.records).await.ok();
+    }
+
+
+
+    // add a record of episodes that were watched
+    pub async fn add_record(&mut self, media_id: i32, first_episode: i32, last_episode: i32, mut file_path: Option<String>, mut source: WatchSource, previous: UserInfo) {
+
+        // a detected episode that was opened with the play button is credited to the play button
+        if let Some((episode, path)) = self.play_button.get(&media_id).cloned() {
+            if source == WatchSource::AutoDetected && episode >= first_episode && episode <= last_episode {
+                source = WatchSource::PlayButton;
+                if file_path.is_none() {
+                    file_path = Some(path);
+                }
+            }
+            // the opened episode has been counted
+            if episode <= last_episode {
+                self.play_button.remove(&media_id);
+            }
+        }
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.records.push(WatchRecord { media_id, first_episode, last_episode, timestamp, file_path, source, previous });
+        file_operations::write_file_watch_history(&*self.events, &self.records).await.ok();
+    }
+
+
+
+    // remember a episode was opened from gekijou so it can be credited to the play button when it's detected
+    pub fn played_episode(&mut self, media_id: i32, episode: i32, file_path: String) {
+        self.play_button.insert(media_id, (episode, file_path));
+    }
+
+
+
+    // records between two unix times in seconds, newest first
+    pub fn get_records_by_date(&self, start: u64, end: u64) -> Vec<WatchRecord> {
+        self.records.iter()
+            .rev()
+            .filter(|record| record.timestamp >= start && record.timestamp <= end)
+            .cloned()
+            .collect()
+    }
+
+
+
+    // records for a single anime, newest first
+    pub fn get_records_by_media(&self, media_id: i32) -> Vec<WatchRecord> {
+        self.records.iter()
+            .rev()
+            .filter(|record| record.media_id == media_id)
+            .cloned()
+            .collect()
+    }
+
+
+
+    // the most recent episode that was found playing in a media player
+    pub fn latest_auto_credit(&self) -> Option<WatchRecord> {
+        self.records.iter()
+            .rev()
+            .find(|record| record.source != WatchSource::Manual)
+            .cloned()
+    }
+
+
+
+    // add records from another computer, records that are already in the history are skipped
+    // returns the number of records that were added
+    pub async fn import_records(&mut self, records: &Vec<WatchRecord>) -> usize {
+
+        let existing = self.records.len();
+        for record in records {
+            if self.records.iter().any(|local| local.media_id == record.media_id && local.timestamp == record.timestamp && local.last_episode == record.last_episode) == false {
+                self.records.push(record.clone());
+            }
+        }
+
+        let added = self.records.len() - existing;
+        if added > 0 {
+            self.records.sort_by_key(|record| record.timestamp);
+            file_operations::write_file_watch_history(&*self.events, &self.records).await.ok();
+        }
+        added
+    }
+
+
+
+    // remove a record after the progress it added was undone
+    pub async fn remove_record(&mut self, record: &WatchRecord) {
+        if let Some(index) = self.records.iter().rposition(|existing| existing.media_id == record.media_id && existing.timestamp == record.timestamp) {
+            self.records.remove(index);
+            file_operations::write_file_watch_history(&*self.events, &self.records).await.ok();
+        }
+    }
+}
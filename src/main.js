@@ -40,6 +40,9 @@ window.addEventListener("DOMContentLoaded", async () => {
   var debug = await invoke("get_debug");
   if (debug) {
     document.getElementById("test_button").style.display = "block"
+    document.getElementById("schema_test_button").style.display = "block"
+    document.getElementById("response_test_button").style.display = "block"
+    document.getElementById("fixture_test_button").style.display = "block"
   }
 
   add_adult_genres(user_settings.show_adult);
@@ -125,95 +128,229 @@ async function populate_year_dropdown() {
 
 
 
-// check if rust has detected a episode and increased the users progress
-var refresh_ui_interval = setInterval(refresh_ui, 1000);
-async function refresh_ui() {
+// the backend sends events when something changes, the ui only redraws the parts that changed
+var ui_status = { no_internet: false, queued_updates: 0, sync_conflicts: 0 };
+var reauthorize_requested = false;
+var tracking_ids = new Set();
+var delay_progress_interval = null;
+var list_refresh_timeout = null;
+var canvas_refresh_timeout = null;
 
-  clearInterval(refresh_ui_interval);
+listen("list-changed", event => {
+  // many anime can change at once, only refresh after the last one
+  clearTimeout(list_refresh_timeout);
+  list_refresh_timeout = setTimeout(refresh_anime_list, 100);
+});
 
-  var refresh = await invoke("refresh_ui");
-  if (refresh.anime_list == true) {
-    if (current_tab == "RECOMMENDED") {
-      await show_recommended_anime_list();
-    } else {
-      
-      if (current_tab == "CURRENT" || current_tab == "COMPLETED" || current_tab == "PAUSED" || current_tab == "DROPPED" || current_tab == "PLANNING") {
-        await show_anime_list(current_tab);
-      }
-    }
+listen("entry-updated", event => {
+  queue_canvas_redraw();
+});
+
+listen("scan-progress", event => {
+  show_scan_progress(event.payload);
+  // the scan finished, episodes on disk may have changed
+  if (event.payload.total_folders == 0) {
+    queue_canvas_redraw();
   }
+});
+
+listen("tracking-started", event => {
+  tracking_ids.add(event.payload);
+  update_delay_progress_timer();
+});
+
+listen("tracking-stopped", event => {
+  tracking_ids.delete(event.payload);
+  update_delay_progress_timer();
+});
+
+listen("error", event => {
+  show_errors([event.payload]);
+});
+
+listen("connectivity-changed", event => {
+  ui_status.no_internet = event.payload == false;
+  show_internet_icon();
+});
+
+listen("queued-updates", event => {
+  ui_status.queued_updates = event.payload;
+  show_internet_icon();
+});
+
+listen("sync-conflicts", event => {
+  ui_status.sync_conflicts = event.payload;
+  show_internet_icon();
+});
+
+listen("reauthorize", event => {
+  show_reauthorize(event.payload);
+});
+
+listen("loading", event => {
+  show_loading_dialog(event.payload);
+});
+
+listen("files-recovered", event => {
+  show_recovery_report(event.payload);
+});
+
+// get what happened before the ui was listening
+get_ui_status();
+async function get_ui_status() {
+
+  var status = await invoke("get_ui_status");
+  ui_status.no_internet = status.no_internet;
+  ui_status.queued_updates = status.queued_updates;
+  ui_status.sync_conflicts = status.sync_conflicts;
+  show_internet_icon();
+  show_reauthorize(status.reauthorize);
+  show_errors(status.errors);
+  show_recovery_report(status.recovery);
 
-  if (refresh.canvas == true && current_tab != "BROWSE") {
-    redraw_episode_canvas();
+  var tracked_episodes = await invoke("get_tracked_episodes");
+  for (var i = 0; i < tracked_episodes.length; i++) {
+    tracking_ids.add(tracked_episodes[i].media_id);
   }
+  update_delay_progress_timer();
+}
 
-  if (refresh.no_internet == true) {
-    document.getElementById("internet_icon").style.display = "block";
-  } else {
-    document.getElementById("internet_icon").style.display = "none";
+
+
+async function refresh_anime_list() {
+  if (current_tab == "RECOMMENDED") {
+    await show_recommended_anime_list();
+  } else if (current_tab == "CURRENT" || current_tab == "COMPLETED" || current_tab == "PAUSED" || current_tab == "DROPPED" || current_tab == "PLANNING") {
+    await show_anime_list(current_tab);
   }
+}
 
-  if(refresh.errors.length != 0) {
 
-    var table = document.getElementById("error_table");
-    /*for(var i = 0; i < table.rows.length; i++) {
-      table.deleteRow(0);
-    }*/
 
-    // don't show the same error twice
-    for(var i = 0; i < refresh.errors.length; i++) {
-      for(var j = 0; j < table.rows.length; j++) {
-        if(table.rows[j].innerHTML == refresh.errors[i]) {
-          refresh.errors.splice(i, 1);
-          i--;
-          break;
-        }
-      }
+function queue_canvas_redraw() {
+  clearTimeout(canvas_refresh_timeout);
+  canvas_refresh_timeout = setTimeout(function() {
+    if (current_tab != "BROWSE") {
+      redraw_episode_canvas();
     }
+  }, 100);
+}
+
 
-    for(var i = 0; i < refresh.errors.length; i++) {
-      var row = table.insertRow(i);
-      row.innerHTML = refresh.errors[i];
-    }
 
-    document.getElementById("error_panel").style.visibility = "visible";
-    await invoke("clear_errors");
+// the progress of episodes being watched is only redrawn while there are any
+function update_delay_progress_timer() {
+  if (tracking_ids.size > 0 && delay_progress_interval == null) {
+    delay_progress_interval = setInterval(draw_delay_progress, 1000);
+  } else if (tracking_ids.size == 0 && delay_progress_interval != null) {
+    clearInterval(delay_progress_interval);
+    delay_progress_interval = null;
   }
-  
   draw_delay_progress();
+}
+
+
+
+function show_internet_icon() {
+  var internet_icon = document.getElementById("internet_icon");
+  if (ui_status.no_internet == true || ui_status.queued_updates > 0) {
+    internet_icon.style.display = "block";
+    internet_icon.title = "You are not connected to the internet. Changes cannot by synced to anilist.";
+    if (ui_status.queued_updates > 0) {
+      internet_icon.title += " " + ui_status.queued_updates + " change(s) waiting to be sent.";
+    }
+  } else if (ui_status.sync_conflicts > 0) {
+    internet_icon.style.display = "block";
+    internet_icon.title = ui_status.sync_conflicts + " change(s) conflict with the website. Review them in Settings.";
+  } else {
+    internet_icon.style.display = "none";
+  }
+}
+
+
+
+// the website rejected the login, ask the user to log in again
+function show_reauthorize(reauthorize) {
+  if (reauthorize == true && reauthorize_requested == false) {
+    reauthorize_requested = true;
+    show_errors(["Your login has expired. Log in again so changes can be synced."]);
+    show_setting_window();
+  } else if (reauthorize == false) {
+    reauthorize_requested = false;
+  }
+}
+
+
 
-  if (refresh.scan_data.current_folder > 0) {
-    var percent = ((refresh.scan_data.completed_chunks / refresh.scan_data.total_chunks) * 100).toFixed(0);
+// saved files that were damaged when they were read
+function show_recovery_report(report) {
+  var messages = [];
+  for (var i = 0; i < report.recovered.length; i++) {
+    messages.push(report.recovered[i] + " was damaged and has been restored from a backup");
+  }
+  for (var i = 0; i < report.lost.length; i++) {
+    messages.push(report.lost[i] + " was damaged and couldn't be restored from a backup");
+  }
+  show_errors(messages);
+}
+
+
+
+function show_errors(errors) {
+
+  if(errors.length == 0) {
+    return;
+  }
+
+  var table = document.getElementById("error_table");
+
+  // don't show the same error twice
+  for(var i = 0; i < errors.length; i++) {
+    for(var j = 0; j < table.rows.length; j++) {
+      if(table.rows[j].innerHTML == errors[i]) {
+        errors.splice(i, 1);
+        i--;
+        break;
+      }
+    }
+  }
+
+  for(var i = 0; i < errors.length; i++) {
+    var row = table.insertRow(i);
+    row.innerHTML = errors[i];
+  }
+
+  document.getElementById("error_panel").style.visibility = "visible";
+}
+
+
+
+function show_scan_progress(scan_data) {
+  if (scan_data.current_folder > 0) {
+    var percent = ((scan_data.completed_chunks / scan_data.total_chunks) * 100).toFixed(0);
     if (percent == "NaN") {
       percent = "0";
     }
     document.getElementById("cover_panel_id").style.maxHeight = "calc(100vh - 77px)";
-    document.getElementById("bottom_info_bar").textContent = "Scanning folder " + refresh.scan_data.current_folder + " of " + refresh.scan_data.total_folders + " " + percent + "%";
+    document.getElementById("bottom_info_bar").textContent = "Scanning folder " + scan_data.current_folder + " of " + scan_data.total_folders + " " + percent + "%";
   } else {
     document.getElementById("cover_panel_id").style.maxHeight = "calc(100vh - 53px)";
     document.getElementById("bottom_info_bar").textContent = "";
   }
+}
+
+
 
-  if (refresh.loading_dialog != null) {
-    document.getElementById("loading_dialog_text").innerText = refresh.loading_dialog;
-    var loading_dialog = document.getElementById("loading_dialog");
+function show_loading_dialog(message) {
+  var loading_dialog = document.getElementById("loading_dialog");
+  if (message != null) {
+    document.getElementById("loading_dialog_text").innerText = message;
     if (loading_dialog.open == false){
       loading_dialog.showModal();
     }
   } else {
-    document.getElementById("loading_dialog").close();
+    loading_dialog.close();
   }
-
-  refresh_ui_interval = setInterval(refresh_ui, 1000);
-}
-
-
-
-var scan_interval = setInterval(background_tasks, 10000);
-async function background_tasks() {
-  clearInterval(scan_interval);
-  await invoke("background_tasks");
-  scan_interval = setInterval(background_tasks, 10000);
 }
 
 
@@ -244,6 +381,7 @@ async function confirm_delete_entry(media_id) {
       }
       document.getElementById("status_select").value = "";
       document.getElementById("episode_number").value = 0;
+      document.getElementById("repeat_number").value = 0;
       document.getElementById("score_dropdown").value = 0;
       document.getElementById("started_date").value = "";
       document.getElementById("finished_date").value = "";
@@ -404,28 +542,54 @@ function populate_sort_dropdown(browse) {
   document.getElementById("sort_order").selectedIndex = index;
 }
 
+// format seconds remaining as minutes and seconds
+function format_time_remaining(seconds) {
+  if (seconds >= 60) {
+    return Math.floor(seconds / 60) + "m " + (seconds % 60) + "s";
+  } else {
+    return seconds + "s";
+  }
+}
+
+// stop the episode shown in the progress bar from updating the user's progress
+window.cancel_tracking = cancel_tracking;
+async function cancel_tracking() {
+
+  var media_id = parseInt(document.getElementById("recognition_delay").getAttribute("media_id"));
+  if (media_id > 0 && await confirm("Stop tracking this episode? Your progress won't be updated.") == true) {
+    await invoke("cancel_tracking", { mediaId: media_id });
+    draw_delay_progress();
+  }
+}
+
 // draw progress bar for recognizing anime being played by media players
 window.draw_delay_progress = draw_delay_progress;
 async function draw_delay_progress() {
 
-  var delay_update_info = await invoke("get_delay_info");
+  // every episode being watched, the one closest to updating is drawn
+  var tracked_episodes = await invoke("get_tracked_episodes");
+  var delay_update_info = { media_id: 0, percent: 0.0, episode: 0 };
+  if (tracked_episodes.length > 0) {
+    delay_update_info = tracked_episodes[0];
+  }
   var ctx = document.getElementById("recognition_delay").getContext("2d");
+  document.getElementById("recognition_delay").setAttribute("media_id", delay_update_info.media_id);
 
   if (delay_update_info.percent == 0.0 || delay_update_info.percent >= 0.995) {
     // no anime being tracked or anime is about to update anyway so don't track it
     ctx.clearRect(0,0,52,52);
     document.getElementById("recognition_delay").title = "";
   } else {
-    // format seconds remaining as minutes and seconds
-    var time_remaining = "";
-    if (delay_update_info.time_remaining >= 60) {
-      time_remaining = Math.floor(delay_update_info.time_remaining / 60) + "m " + (delay_update_info.time_remaining % 60) + "s";
-    } else {
-      time_remaining = delay_update_info.time_remaining + "s";
+    // full description tooltip text, one line for each episode being watched
+    var tooltip = "";
+    for (var i = 0; i < tracked_episodes.length; i++) {
+      var episodes = tracked_episodes[i].episode;
+      if (tracked_episodes[i].first_episode != tracked_episodes[i].episode) {
+        episodes = tracked_episodes[i].first_episode + "-" + tracked_episodes[i].episode;
+      }
+      tooltip += "Updating " + tracked_episodes[i].title + " to episode " + episodes + " in " + format_time_remaining(tracked_episodes[i].time_remaining) + "\n";
     }
-    
-    // full description tooltip text
-    document.getElementById("recognition_delay").title = "Updating " + delay_update_info.title + " to episode " + delay_update_info.episode + " in " + time_remaining;
+    document.getElementById("recognition_delay").title = tooltip + "Right click to cancel";
 
     ctx.clearRect(0,0,52,52);
     
@@ -1357,6 +1521,92 @@ async function run_tests() {
 
 
 
+window.run_schema_tests = run_schema_tests;
+async function run_schema_tests() {
+
+  var results = await invoke("run_schema_tests");
+  removeChildren(document.getElementById("cover_panel_grid"));
+
+  document.getElementById("cover_panel_grid").innerHTML = 
+  "<table id=\"schema_tests_table\" style=\"width:1600px;\">" + 
+    "<tbody>" + 
+      "<tr>" + 
+        "<th>Fixture</th>" + 
+        "<th>File</th>" + 
+        "<th>Version</th>" + 
+        "<th>Result</th>" + 
+      "</tr>" + 
+    "</tbody>" + 
+  "</table>";
+
+  var table = document.getElementById("schema_tests_table");
+
+  for(var i = 0; i < results.length; i++) {
+
+    var result_color = "red";
+    var result_text = results[i].error;
+    if (results[i].passed) {
+      result_color = "lightgreen";
+      result_text = "Passed";
+    }
+
+    var row = table.insertRow(i + 1);
+    row.insertCell(0).innerHTML = results[i].fixture;
+    row.insertCell(1).innerHTML = "<p>" + results[i].filename + "</p>";
+    row.insertCell(2).innerHTML = "<p>" + results[i].version + "</p>";
+    row.insertCell(3).innerHTML = "<p style=\"color:" + result_color + ";\">" + result_text + "</p>";
+  }
+}
+
+
+
+window.run_response_tests = run_response_tests;
+async function run_response_tests() {
+  show_test_results(await invoke("run_response_tests"));
+}
+
+
+
+window.run_fixture_tests = run_fixture_tests;
+async function run_fixture_tests() {
+  show_test_results(await invoke("run_fixture_tests"));
+}
+
+
+
+// a table with a row for each fixture and if it passed
+function show_test_results(results) {
+
+  removeChildren(document.getElementById("cover_panel_grid"));
+
+  document.getElementById("cover_panel_grid").innerHTML = 
+  "<table id=\"response_tests_table\" style=\"width:1600px;\">" + 
+    "<tbody>" + 
+      "<tr>" + 
+        "<th>Fixture</th>" + 
+        "<th>Result</th>" + 
+      "</tr>" + 
+    "</tbody>" + 
+  "</table>";
+
+  var table = document.getElementById("response_tests_table");
+
+  for(var i = 0; i < results.length; i++) {
+
+    var result_color = "red";
+    var result_text = results[i].error;
+    if (results[i].passed) {
+      result_color = "lightgreen";
+      result_text = "Passed";
+    }
+
+    var row = table.insertRow(i + 1);
+    row.insertCell(0).innerHTML = results[i].fixture;
+    row.insertCell(1).innerHTML = "<p style=\"color:" + result_color + ";\">" + result_text + "</p>";
+  }
+}
+
+
 var snow_counter = 0;
 document.addEventListener('keyup', (e) => {
   if (e.code === "F1") {
@@ -1376,6 +1626,121 @@ async function status_change() {
 }
 
 
+window.export_data = export_data;
+async function export_data() {
+
+  var path = await window.__TAURI__.dialog.save({ defaultPath: "gekijou_backup.json", filters: [{ name: "Gekijou Backup", extensions: ["json"] }] });
+  if (path == null) {
+    return;
+  }
+
+  try {
+    await invoke("export_data", { path: path, includeToken: document.getElementById("export_token").checked });
+    alert("Data exported");
+  } catch (error) {
+    alert("Can't export data: " + error);
+  }
+}
+
+
+window.import_data = import_data;
+async function import_data() {
+
+  var path = await window.__TAURI__.dialog.open({ multiple: false, filters: [{ name: "Gekijou Backup", extensions: ["json"] }] });
+  if (path == null) {
+    return;
+  }
+
+  try {
+    var summary = await invoke("import_data", { path: path, conflict: document.getElementById("import_conflict").value });
+    if (summary.settings == true) {
+      await get_user_settings();
+      invoke("scan_anime_folder");
+    }
+    alert("Imported " + summary.added + " new anime, replaced " + summary.replaced + ", kept " + summary.kept + 
+      ", " + summary.episode_paths + " episode files, " + summary.custom_titles + " custom titles, and " + summary.watch_records + " watch history entries");
+  } catch (error) {
+    alert("Can't import data: " + error);
+  }
+}
+
+
+window.export_list = export_list;
+async function export_list() {
+
+  var format = document.getElementById("export_list_format").value;
+  var extension = { MAL_XML: "xml", CSV: "csv", JSON: "json" }[format];
+  var path = await window.__TAURI__.dialog.save({ defaultPath: "anime_list." + extension, filters: [{ name: format, extensions: [extension] }] });
+  if (path == null) {
+    return;
+  }
+
+  try {
+    var count = await invoke("export_list", { path: path, format: format });
+    alert("Exported " + count + " anime");
+  } catch (error) {
+    alert("Can't export list: " + error);
+  }
+}
+
+
+window.import_mal_list = import_mal_list;
+async function import_mal_list() {
+
+  var path = await window.__TAURI__.dialog.open({ multiple: false, filters: [{ name: "MyAnimeList Export", extensions: ["xml"] }] });
+  if (path == null) {
+    return;
+  }
+  var overwrite = await confirm("Replace anime that are already on your list with the imported entries?");
+
+  try {
+    var summary = await invoke("import_mal_list", { path: path, overwrite: overwrite });
+    alert("Added " + summary.added + " anime, replaced " + summary.replaced + ", skipped " + summary.skipped + 
+      ". " + summary.not_found + " anime couldn't be found on anilist");
+  } catch (error) {
+    alert("Can't import list: " + error);
+  }
+}
+
+
+window.resolve_sync_conflicts = resolve_sync_conflicts;
+async function resolve_sync_conflicts() {
+
+  var conflicts = await invoke("get_sync_conflicts");
+  if (conflicts.length == 0) {
+    alert("There are no conflicting changes");
+    return;
+  }
+
+  for (var i = 0; i < conflicts.length; i++) {
+    var conflict = conflicts[i];
+    var title = conflict.media_id;
+    try {
+      var anime = await invoke("get_anime_info", {id: conflict.media_id});
+      title = null_check(anime.title.romaji, anime.title.romaji, conflict.media_id);
+    } catch (error) {}
+
+    var keep_local = await confirm(title + " was changed on this computer and on the website.\n\n" + 
+      "This computer: " + describe_list_entry(conflict.local) + "\n" + 
+      "Website: " + describe_list_entry(conflict.remote) + "\n\n" + 
+      "OK keeps this computer's change, Cancel keeps the website's change.");
+
+    try {
+      await invoke("resolve_sync_conflict", { mediaId: conflict.media_id, keepLocal: keep_local });
+    } catch (error) {
+      alert("Can't resolve conflict: " + error);
+    }
+  }
+}
+
+// one line summary of a list entry
+function describe_list_entry(entry) {
+  return entry.status + ", episode " + entry.progress + ", score " + entry.score + ", rewatched " + entry.repeat + " times" + 
+    ", started " + null_check_date_string(entry.started_at, "-") + ", completed " + null_check_date_string(entry.completed_at, "-") + 
+    null_check(entry.notes, ", notes: " + entry.notes, "");
+}
+
+
 window.delete_data = delete_data;
 async function delete_data() {
 
@@ -1387,6 +1752,19 @@ async function delete_data() {
     document.getElementById("show_airing").checked = true;
     document.getElementById("folders").value = "";
     document.getElementById("update_delay").selectedIndex = 0;
+    document.getElementById("watched_percent").value = 80;
+    document.getElementById("completion_policy").selectedIndex = 0;
+    document.getElementById("mpv_socket").value = "";
+    document.getElementById("vlc_port").value = "";
+    document.getElementById("vlc_password").value = "";
+    document.getElementById("mpc_hc_port").value = "";
+    document.getElementById("deletion_check_interval").value = 60;
+    document.getElementById("player_detection_interval").value = 10;
+    document.getElementById("library_scan_interval").value = 60;
+    document.getElementById("airing_refresh_interval").value = 60;
+    document.getElementById("cache_refresh_interval").value = 1440;
+    document.getElementById("queue_flush_interval").value = 30;
+    document.getElementById("connectivity_check_interval").value = 300;
     var elements = document.getElementById("color_boxes").childNodes;
     var first = true;
     for (var i=0; i<elements.length; i++) {
@@ -1483,6 +1861,19 @@ async function get_user_settings() {
   document.getElementById("show_adult").checked = user_settings.show_adult;
   document.getElementById("show_airing").checked = user_settings.show_airing_time;
   document.getElementById("update_delay").value = user_settings.update_delay;
+  document.getElementById("watched_percent").value = null_check(user_settings.watched_percent, user_settings.watched_percent, 80);
+  document.getElementById("completion_policy").value = null_check(user_settings.completion_policy, user_settings.completion_policy, "PERCENT");
+  document.getElementById("mpv_socket").value = null_check(user_settings.mpv_socket, user_settings.mpv_socket, "");
+  document.getElementById("vlc_port").value = null_check(user_settings.vlc_port, user_settings.vlc_port, "");
+  document.getElementById("vlc_password").value = null_check(user_settings.vlc_password, user_settings.vlc_password, "");
+  document.getElementById("mpc_hc_port").value = null_check(user_settings.mpc_hc_port, user_settings.mpc_hc_port, "");
+  document.getElementById("deletion_check_interval").value = null_check(user_settings.deletion_check_interval, user_settings.deletion_check_interval, 60);
+  document.getElementById("player_detection_interval").value = null_check(user_settings.player_detection_interval, user_settings.player_detection_interval, 10);
+  document.getElementById("library_scan_interval").value = null_check(user_settings.library_scan_interval, user_settings.library_scan_interval, 60);
+  document.getElementById("airing_refresh_interval").value = null_check(user_settings.airing_refresh_interval, user_settings.airing_refresh_interval, 60);
+  document.getElementById("cache_refresh_interval").value = null_check(user_settings.cache_refresh_interval, user_settings.cache_refresh_interval, 1440);
+  document.getElementById("queue_flush_interval").value = null_check(user_settings.queue_flush_interval, user_settings.queue_flush_interval, 30);
+  document.getElementById("connectivity_check_interval").value = null_check(user_settings.connectivity_check_interval, user_settings.connectivity_check_interval, 300);
 
   if(user_settings.title_language == "") {
     document.getElementById("title_language").value = "romaji";
@@ -1576,6 +1967,19 @@ async function hide_setting_window() {
     show_airing_time: document.getElementById("show_airing").checked,
     folders: document.getElementById("folders").value.split('\n'),
     update_delay: parseInt(document.getElementById("update_delay").value),
+    watched_percent: parseInt(document.getElementById("watched_percent").value),
+    completion_policy: document.getElementById("completion_policy").value,
+    mpv_socket: document.getElementById("mpv_socket").value || null,
+    vlc_port: parseInt(document.getElementById("vlc_port").value) || null,
+    vlc_password: document.getElementById("vlc_password").value || null,
+    mpc_hc_port: parseInt(document.getElementById("mpc_hc_port").value) || null,
+    deletion_check_interval: parseInt(document.getElementById("deletion_check_interval").value),
+    player_detection_interval: parseInt(document.getElementById("player_detection_interval").value),
+    library_scan_interval: parseInt(document.getElementById("library_scan_interval").value),
+    airing_refresh_interval: parseInt(document.getElementById("airing_refresh_interval").value),
+    cache_refresh_interval: parseInt(document.getElementById("cache_refresh_interval").value),
+    queue_flush_interval: parseInt(document.getElementById("queue_flush_interval").value),
+    connectivity_check_interval: parseInt(document.getElementById("connectivity_check_interval").value),
     score_format: null,
     highlight_color: highlight_color,
     current_tab: "",
@@ -1607,24 +2011,43 @@ window.get_oauth_token = get_oauth_token;
 async function get_oauth_token() {
   
   var input = document.getElementById("oauth_code");
-  
-  var success = await invoke("anilist_oauth_token", { code: document.getElementById("oauth_code").value});
+  var was_local = await invoke("is_local_list");
+
+  // the code is exchanged with the website the user logged into
+  var success = [false, ""];
+  if(document.getElementById("website_selector").value == "MyAnimeList") {
+    success = await invoke("mal_oauth_token", { code: input.value});
+  } else {
+    success = await invoke("anilist_oauth_token", { code: input.value});
+  }
 
   input.value = "";
   if(success[0] == true) {
     input.setAttribute("placeholder", "Success");
+    if (was_local == true && await confirm("Upload the list kept on this computer to your account?") == true) {
+      attach_account();
+    }
   } else {
     input.setAttribute("placeholder", "Failed");
     alert(success[1]);
   }
+}
 
-  
-  if(document.getElementById("website_selector").value == "Anilist") {
-    window.open("https://anilist.co/api/v2/oauth/authorize?client_id=13512&redirect_uri=https://anilist.co/api/v2/oauth/pin&response_type=code");
-  } else if(document.getElementById("website_selector").value == "MyAnimeList") {
-    
-    var input = document.getElementById("oauth_code");
-    var success = await invoke("mal_oauth_token", { code: document.getElementById("oauth_code").value});
+
+
+// upload the list that was kept without a account to the account the user logged into
+window.attach_account = attach_account;
+async function attach_account() {
+
+  try {
+    var result = await invoke("attach_account", { username: document.getElementById("user_name").value });
+    if (result.pending > 0) {
+      alert("Uploaded " + result.uploaded + " anime, " + result.pending + " will be uploaded when the website can be reached");
+    } else {
+      alert("Uploaded " + result.uploaded + " anime");
+    }
+  } catch (error) {
+    alert("Can't upload your list: " + error);
   }
 }
 
@@ -1818,6 +2241,7 @@ async function show_manga_info_window(manga_id) {
   document.getElementById("custom_filename").value = null;
   document.getElementById("status_select").value = null;
   document.getElementById("episode_number").value = 0;
+  document.getElementById("repeat_number").value = 0;
   document.getElementById("score_dropdown").value = 0;
   document.getElementById("started_date").value = null;
   document.getElementById("finished_date").value = null;
@@ -2003,6 +2427,47 @@ function show_spoiler_tags() {
 
 
 
+// add a checkbox for each of the user's custom lists and a score box for each advanced scoring category
+function setup_list_options(user_settings, user_data) {
+
+  document.getElementById("private_entry").checked = user_data != null && user_data.private;
+  document.getElementById("hidden_from_status_lists").checked = user_data != null && user_data.hidden_from_status_lists;
+
+  var custom_lists_cell = document.getElementById("custom_lists_cell");
+  custom_lists_cell.innerHTML = "";
+  var custom_lists = null_check(user_settings.custom_lists, user_settings.custom_lists, []);
+  for (var i = 0; i < custom_lists.length; i++) {
+    var label = document.createElement("label");
+    var checkbox = document.createElement("input");
+    checkbox.type = "checkbox";
+    checkbox.value = custom_lists[i];
+    checkbox.checked = user_data != null && user_data.custom_lists.includes(custom_lists[i]);
+    checkbox.addEventListener("change", function () { entry_changed = true; }, false);
+    label.appendChild(checkbox);
+    label.appendChild(document.createTextNode(custom_lists[i]));
+    custom_lists_cell.appendChild(label);
+  }
+
+  var advanced_scores_cell = document.getElementById("advanced_scores_cell");
+  advanced_scores_cell.innerHTML = "";
+  var advanced_scoring = null_check(user_settings.advanced_scoring, user_settings.advanced_scoring, []);
+  document.getElementById("advanced_scores_row").style.display = advanced_scoring.length > 0 ? "" : "none";
+  for (var i = 0; i < advanced_scoring.length; i++) {
+    var label = document.createElement("label");
+    var score = document.createElement("input");
+    score.type = "number";
+    score.min = 0;
+    score.max = 10;
+    score.step = 0.5;
+    score.setAttribute("category", advanced_scoring[i]);
+    score.value = user_data != null ? null_check(user_data.advanced_scores[advanced_scoring[i]], user_data.advanced_scores[advanced_scoring[i]], 0) : 0;
+    score.addEventListener("change", function () { entry_changed = true; }, false);
+    label.appendChild(document.createTextNode(advanced_scoring[i] + " "));
+    label.appendChild(score);
+    advanced_scores_cell.appendChild(label);
+  }
+}
+
 // fill in the user's data into the info window
 async function add_user_data(anime_id, user_settings) {
 
@@ -2019,11 +2484,13 @@ async function add_user_data(anime_id, user_settings) {
     var user_data = await invoke("get_user_info", {id: anime_id});
   
     setup_score_dropdown(user_settings.score_format);
+    setup_list_options(user_settings, user_data);
     console.log(user_data);
     if (user_data != null) {
       document.getElementById("delete_anime").onclick = function() { confirm_delete_entry(user_data.media_id); }
       document.getElementById("status_select").value = user_data.status;
       document.getElementById("episode_number").value = user_data.progress;
+      document.getElementById("repeat_number").value = user_data.repeat;
       document.getElementById("score_dropdown").value = user_data.score;
       document.getElementById("started_date").value = null_check_date_string(user_data.started_at, "");
       document.getElementById("finished_date").value = null_check_date_string(user_data.completed_at, "");
@@ -2032,6 +2499,7 @@ async function add_user_data(anime_id, user_settings) {
       document.getElementById("delete_anime").onclick = function() { }
       document.getElementById("status_select").value = null;
       document.getElementById("episode_number").value = 0;
+      document.getElementById("repeat_number").value = 0;
       document.getElementById("score_dropdown").value = 0;
       document.getElementById("started_date").value = "";
       document.getElementById("finished_date").value = "";
@@ -2078,6 +2546,9 @@ async function hide_anime_info_window(anime_id) {
 var entry_changed = false;
 document.getElementById("status_select").addEventListener("change", function () { entry_changed = true; }, false);
 document.getElementById("episode_number").addEventListener("change", function () { entry_changed = true; }, false);
+document.getElementById("repeat_number").addEventListener("change", function () { entry_changed = true; }, false);
+document.getElementById("private_entry").addEventListener("change", function () { entry_changed = true; }, false);
+document.getElementById("hidden_from_status_lists").addEventListener("change", function () { entry_changed = true; }, false);
 document.getElementById("started_date").addEventListener("change", function () { entry_changed = true; }, false);
 document.getElementById("finished_date").addEventListener("change", function () { entry_changed = true; }, false);
 document.getElementById("score_dropdown").addEventListener("change", function () { entry_changed = true; }, false);
@@ -2103,6 +2574,11 @@ async function update_user_entry(anime_id) {
     'status': document.getElementById("status_select").value,
     'score': parseFloat(document.getElementById("score_dropdown").value),
     'progress': parseInt(document.getElementById("episode_number").value),
+    'repeat': parseInt(document.getElementById("repeat_number").value) || 0,
+    'private': document.getElementById("private_entry").checked,
+    'hidden_from_status_lists': document.getElementById("hidden_from_status_lists").checked,
+    'custom_lists': [],
+    'advanced_scores': {},
     'notes' : document.getElementById("user_notes").value,
     'updated_at' : 0
   };
@@ -2113,6 +2589,17 @@ async function update_user_entry(anime_id) {
     user_entry.id = 0;
   }
 
+  var custom_list_boxes = document.getElementById("custom_lists_cell").getElementsByTagName("input");
+  for (var i = 0; i < custom_list_boxes.length; i++) {
+    if (custom_list_boxes[i].checked) {
+      user_entry.custom_lists.push(custom_list_boxes[i].value);
+    }
+  }
+  var advanced_score_boxes = document.getElementById("advanced_scores_cell").getElementsByTagName("input");
+  for (var i = 0; i < advanced_score_boxes.length; i++) {
+    user_entry.advanced_scores[advanced_score_boxes[i].getAttribute("category")] = parseFloat(advanced_score_boxes[i].value) || 0;
+  }
+
   // don't update anime if user didn't set status
   if (user_entry.status == "") {
     return; 
@@ -2180,6 +2667,7 @@ async function update_user_entry(anime_id) {
       user_entry.status != user_data.status ||
       user_entry.score != user_data.score ||
       user_entry.progress != user_data.progress ||
+      user_entry.repeat != user_data.repeat ||
       started_changed == true ||
       completed_changed == true ||
       user_entry.notes != user_data.notes) {